@@ -1,32 +1,249 @@
-use clap::{Parser, Subcommand};
+use crate::easyeda::footprint::{RasterImageMode, DEFAULT_ARC_MAX_ERROR};
+use crate::easyeda::symbol::SymbolFillStyle;
+use crate::kicad::model::common::KiCadVersion;
+use crate::kicad::model::footprint_library::FootprintAnchor;
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Parser, Clone, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct CliArguments {
     #[command(subcommand)]
     pub command: Command,
+
+    /// Print detailed progress messages
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    pub verbose: bool,
+
+    /// Only print warnings and errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
 }
 
 #[derive(Subcommand, Clone, Debug)]
 pub enum Command {
-    /// Import a component from JLCPCB based on its LCSC code (e.g. C35879)
+    /// Import one or more components from JLCPCB based on their LCSC codes (e.g. C35879)
     Import {
-        code: String,
+        #[arg(required_unless_present = "validate_only")]
+        codes: Vec<String>,
+
+        /// Don't import anything, only check that the existing library files can be loaded and written back
+        #[arg(long, conflicts_with_all = ["only_missing", "resume"])]
+        validate_only: bool,
+
+        /// Skip components that are already present in the library
+        #[arg(long)]
+        only_missing: bool,
+
+        /// Skip components that were completed by a previous, interrupted run
+        #[arg(long)]
+        resume: bool,
+
+        #[command(flatten)]
+        options: LibraryOptions,
+    },
+
+    /// Convert locally saved EasyEDA symbol and footprint data
+    Convert {
+        /// Path to the EasyEDA symbol data
+        #[arg(long)]
+        symbol: String,
+
+        /// Path to the EasyEDA footprint data
+        #[arg(long)]
+        footprint: String,
+
+        /// Name of the converted part (defaults to the symbol file name)
+        #[arg(long)]
+        part_name: Option<String>,
+
+        /// LCSC code of the converted part
+        #[arg(long)]
+        code: Option<String>,
+
+        /// Datasheet URL of the converted part
+        #[arg(long)]
+        datasheet: Option<String>,
+
+        #[command(flatten)]
+        options: LibraryOptions,
+    },
+
+    /// Search the JLCPCB catalog and list the matching parts without importing anything
+    Search {
+        /// Keyword to search for, e.g. a part number or a value
+        keyword: String,
+
+        /// Maximum number of results to list
+        #[arg(long, default_value_t = 10)]
+        limit: u32,
+    },
+
+    /// Recompute the 3D model placement of an imported footprint from its STEP file
+    Realign {
+        /// Path to the .kicad_mod file
+        footprint: String,
+
+        /// KiCad project directory used to resolve ${KIPRJMOD} (defaults to the working directory)
+        #[arg(long)]
+        project_dir: Option<String>,
+    },
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct LibraryOptions {
+    /// Allow updating existing components
+    #[arg(short, long)]
+    pub update: bool,
+
+    /// Set a custom name for the library
+    #[arg(short, long, default_value = "JLCPCB_Components")]
+    pub name: String,
+
+    /// Set a custom description for the library, used in both library tables
+    #[arg(short, long, default_value = "Components downloaded and converted directly from JLCPCB")]
+    pub description: String,
+
+    /// KiCad project directory containing the library tables (defaults to the working directory)
+    #[arg(long)]
+    pub project_dir: Option<String>,
+
+    /// Root directory for the library (relative to project). Directories outside the project are referenced by their absolute path.
+    #[arg(short, long)]
+    pub root: Option<String>,
+
+    /// Fill style used for closed symbol shapes
+    #[arg(long, value_enum, default_value_t = SymbolFillStyle::KiCad)]
+    pub fill_style: SymbolFillStyle,
+
+    /// Offset of the pin names from the symbol body in mm (derived from the pin spacing by default)
+    #[arg(long)]
+    pub pin_name_offset: Option<f32>,
+
+    /// Write the library files on a single line instead of KiCad's indented layout
+    #[arg(long)]
+    pub compact: bool,
+
+    /// Write every list of the library files on its own line, so changes show up as single-line diffs
+    #[arg(long, conflicts_with = "compact")]
+    pub expanded: bool,
+
+    /// KiCad version to generate the footprint for
+    #[arg(long, value_enum, default_value_t = KiCadVersion::V8)]
+    pub kicad_version: KiCadVersion,
+
+    /// Keep part names with characters that are unsafe on some platforms as file names.
+    /// Without this, such characters are removed (e.g. 'LM358/TR' is saved as 'LM358TR').
+    #[arg(long)]
+    pub keep_original_names: bool,
 
-        /// Allow updating existing components
-        #[arg(short, long)]
-        update: bool,
+    /// Footprint search tags (generated from the package by default)
+    #[arg(long)]
+    pub tags: Option<String>,
 
-        /// Set a custom name for the library
-        #[arg(short, long, default_value = "JLCPCB_Components")]
-        name: String,
+    /// Only import the symbol, without a footprint or 3D model
+    #[arg(long)]
+    pub symbol_only: bool,
 
-        /// Set a custom name for the library
-        #[arg(short, long, default_value = "Components downloaded and converted directly from JLCPCB")]
-        description: String,
+    /// Feature of the footprint to place at its origin
+    #[arg(long, value_enum, default_value_t = FootprintAnchor::AsIs)]
+    pub anchor: FootprintAnchor,
 
-        /// Root directory for the library (relative to project)
-        #[arg(short, long)]
-        root: Option<String>,
+    /// What to do with raster images in footprints
+    #[arg(long, value_enum, default_value_t = RasterImageMode::Embed)]
+    pub raster_images: RasterImageMode,
+
+    /// Use the search result for a code that isn't found exactly, if it is the only result
+    #[arg(long)]
+    pub allow_fuzzy_match: bool,
+
+    /// Solder paste margin of SMD pads as a ratio of the pad size (e.g. -0.1 shrinks the paste by 10%)
+    #[arg(long, allow_negative_numbers = true)]
+    pub paste_ratio: Option<f32>,
+
+    /// Maximum deviation in mm when arcs in polygons are replaced by straight segments
+    #[arg(long, default_value_t = DEFAULT_ARC_MAX_ERROR)]
+    pub arc_max_error: f32,
+
+    /// Import footprints even if they have no pads
+    #[arg(long)]
+    pub allow_empty: bool,
+
+    /// Directory for the downloaded 3D models (defaults to 'models' inside the footprint library).
+    /// Models inside the directory set by KICAD8_3DMODEL_DIR (or KICAD7_3DMODEL_DIR) are referenced through that variable.
+    #[arg(long)]
+    pub model_dir: Option<String>,
+
+    /// Download the STEP model even if it already exists, instead of reusing it
+    #[arg(long)]
+    pub overwrite_model: bool,
+
+    /// What the KiCad symbol and footprint names (and file names) are derived from
+    #[arg(long, value_enum, default_value_t = PartNameSource::Mpn)]
+    pub name_from: PartNameSource,
+
+    /// Print a JSON summary of every imported part to stdout, one object per line
+    #[arg(long)]
+    pub json: bool,
+
+    /// Pads that are connected by a net tie, as a comma separated list of pad numbers (e.g. "1,2").
+    /// Can be repeated for footprints with multiple net tie groups.
+    #[arg(long, value_name = "PADS", value_parser = parse_net_tie_group)]
+    pub net_tie: Vec<String>,
+
+    /// Derive the UUIDs of footprint elements from the LCSC code, so repeated imports of a part are identical
+    #[arg(long)]
+    pub deterministic_uuids: bool,
+
+    /// Generator name written into the symbol library and footprint files
+    #[arg(long, value_parser = parse_generator_name)]
+    pub generator: Option<String>,
+}
+
+/// Generator names are written as unquoted identifiers, so they can't contain whitespace, quotes or parentheses
+fn parse_generator_name(name: &str) -> Result<String, String> {
+    if name.is_empty() || name.chars().any(|c| c.is_whitespace() || matches!(c, '"' | '(' | ')')) {
+        return Err("the generator name can't be empty or contain whitespace, quotes or parentheses".into());
     }
-}
\ No newline at end of file
+    Ok(name.to_string())
+}
+
+/// Net tie groups are written the way KiCad formats them, with the pad numbers separated by ", "
+fn parse_net_tie_group(pads: &str) -> Result<String, String> {
+    let pads = pads.split(',').map(str::trim).filter(|p| !p.is_empty()).collect::<Vec<_>>();
+    if pads.len() < 2 {
+        return Err("a net tie needs at least two pad numbers separated by commas".into());
+    }
+    Ok(pads.join(", "))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum PartNameSource {
+    /// The manufacturer part number, sanitized for use in file names
+    #[default]
+    Mpn,
+    /// The LCSC code (e.g. C25804)
+    Lcsc,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generator_name_is_validated() {
+        let arguments = CliArguments::try_parse_from(["jlcrs", "import", "C25804", "--generator", "acme_libgen"]).unwrap();
+        let Command::Import { options, .. } = arguments.command else { panic!("expected the import command") };
+        assert_eq!(options.generator.as_deref(), Some("acme_libgen"));
+
+        assert!(CliArguments::try_parse_from(["jlcrs", "import", "C25804", "--generator", "acme libgen"]).is_err());
+    }
+
+    #[test]
+    fn net_tie_groups_are_normalized() {
+        let arguments = CliArguments::try_parse_from(["jlcrs", "import", "C25804", "--net-tie", "1,2", "--net-tie", " 3 , 4,5 "]).unwrap();
+        let Command::Import { options, .. } = arguments.command else { panic!("expected the import command") };
+        assert_eq!(options.net_tie, vec!["1, 2".to_string(), "3, 4, 5".to_string()]);
+
+        assert!(CliArguments::try_parse_from(["jlcrs", "import", "C25804", "--net-tie", "1"]).is_err());
+    }
+}
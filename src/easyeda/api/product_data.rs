@@ -1,6 +1,9 @@
 use std::collections::HashMap;
+use itertools::Itertools;
+use log::warn;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use crate::easyeda::errors::ProductSearchError;
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -184,3 +187,167 @@ pub struct Model3d {
     pub uri: String,
     pub transform: String,
 }
+
+const MAX_CANDIDATES: usize = 5;
+
+impl ProductDataResponse {
+    /// Finds the product with the given code. With `allow_fuzzy_match`, a single search result
+    /// is accepted even if its number doesn't match the code.
+    pub fn find_product(&self, code: &str, allow_fuzzy_match: bool) -> Result<&ProductInfo, ProductSearchError> {
+        let product_list = &self.result.product_list;
+        if let Some(product) = product_list.iter().find(|p| p.number == code) {
+            return Ok(product);
+        }
+
+        let normalized_code = normalize_product_code(code);
+        if let Some(product) = product_list.iter().find(|p| normalize_product_code(&p.number) == normalized_code) {
+            return Ok(product);
+        }
+
+        if allow_fuzzy_match && let [product] = product_list.as_slice() {
+            warn!("Product code '{}' not found, using the only search result '{}' ({}) instead", code, product.number, product.mpn);
+            return Ok(product);
+        }
+
+        if product_list.is_empty() {
+            return Err(ProductSearchError::ProductNotFound(code.into()));
+        }
+
+        let candidates = product_list.iter()
+            .take(MAX_CANDIDATES)
+            .map(|p| format!("{} ({})", p.number, p.mpn))
+            .join(", ");
+        Err(ProductSearchError::ProductNotFoundWithCandidates(code.into(), candidates))
+    }
+}
+
+impl ProductInfo {
+    pub fn description(&self) -> &str {
+        match self.device_info.description.trim() {
+            "" => self.device_info.description2.trim(),
+            description => description,
+        }
+    }
+}
+
+const MAX_DESCRIPTION_LENGTH: usize = 60;
+
+/// Formats the products as a plain text table with one row per product
+pub fn format_product_table(products: &[ProductInfo]) -> String {
+    let header = ["LCSC", "MPN", "Description", "Package"].map(String::from);
+    let rows = products.iter()
+        .map(|p| {
+            let mut description = p.description().replace(['\r', '\n'], " ");
+            if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+                description = description.chars().take(MAX_DESCRIPTION_LENGTH - 3).collect::<String>() + "...";
+            }
+            [p.number.clone(), p.mpn.clone(), description, p.package.clone()]
+        })
+        .collect_vec();
+
+    let widths = (0..header.len())
+        .map(|column| std::iter::once(&header).chain(&rows).map(|row| row[column].chars().count()).max().unwrap_or(0))
+        .collect_vec();
+
+    std::iter::once(&header).chain(&rows)
+        .map(|row| row.iter().zip(&widths).map(|(cell, width)| format!("{cell:<width$}")).join("  ").trim_end().to_string())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+/// Uppercases the code, drops separators and strips leading zeros from the number ("c-035879" becomes "C35879")
+fn normalize_product_code(code: &str) -> String {
+    let code = code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_uppercase();
+
+    match code.strip_prefix('C') {
+        Some(number) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => {
+            let number = number.trim_start_matches('0');
+            format!("C{}", if number.is_empty() { "0" } else { number })
+        }
+        _ => code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product(number: &str, mpn: &str) -> ProductInfo {
+        ProductInfo {
+            number: number.into(),
+            mpn: mpn.into(),
+            ..Default::default()
+        }
+    }
+
+    fn response(products: Vec<ProductInfo>) -> ProductDataResponse {
+        ProductDataResponse {
+            result: ProductsResult {
+                product_list: products,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn find_matching_product() {
+        let response = response(vec![product("C35878", "A"), product("C35879", "B")]);
+        let product = response.find_product("C35879", false).unwrap();
+        assert_eq!(product.mpn, "B");
+    }
+
+    #[test]
+    fn not_found_lists_candidates() {
+        let response = response(vec![product("C35878", "RC0603FR-0710KL"), product("C35880", "RC0603FR-0712KL")]);
+        let error = response.find_product("C35879", false).unwrap_err();
+        assert!(matches!(error, ProductSearchError::ProductNotFoundWithCandidates(_, _)));
+
+        let message = error.to_string();
+        assert!(message.contains("'C35879'"));
+        assert!(message.contains("C35878 (RC0603FR-0710KL)"));
+        assert!(message.contains("C35880 (RC0603FR-0712KL)"));
+    }
+
+    #[test]
+    fn not_found_without_candidates() {
+        let response = response(vec![]);
+        let error = response.find_product("C35879", false).unwrap_err();
+        assert!(matches!(error, ProductSearchError::ProductNotFound(_)));
+    }
+
+    #[test]
+    fn find_product_with_different_number_format() {
+        let response = response(vec![product("c-035879", "A")]);
+        let product = response.find_product("C35879", false).unwrap();
+        assert_eq!(product.mpn, "A");
+    }
+
+    #[test]
+    fn products_are_formatted_as_table() {
+        let mut resistor = product("C25804", "0603WAF1002T5E");
+        resistor.package = "0603".into();
+        resistor.device_info.description2 = "10kΩ ±1% 100mW".into();
+        let table = format_product_table(&[resistor, product("C1", "LONG-MPN-NAME")]);
+
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "LCSC    MPN             Description     Package");
+        assert_eq!(lines[1], "C25804  0603WAF1002T5E  10kΩ ±1% 100mW  0603");
+        assert_eq!(lines[2], "C1      LONG-MPN-NAME");
+    }
+
+    #[test]
+    fn fuzzy_match_uses_single_result() {
+        let single_response = response(vec![product("C35880", "A")]);
+        assert!(single_response.find_product("C35879", false).is_err());
+        assert_eq!(single_response.find_product("C35879", true).unwrap().mpn, "A");
+
+        // With several results it's ambiguous which one was meant
+        let ambiguous_response = response(vec![product("C35878", "A"), product("C35880", "B")]);
+        assert!(ambiguous_response.find_product("C35879", true).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use crate::easyeda::errors::ParserError;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use flate2::read::GzDecoder;
+use std::borrow::Cow;
+use std::io::Read;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Decodes a `data_str` payload that may be delivered as base64 and/or gzip instead of plain text
+pub fn decode_data_str(data: &str) -> Result<Cow<'_, str>, ParserError> {
+    let trimmed = data.trim();
+    if trimmed.is_empty() || trimmed.starts_with('[') {
+        return Ok(Cow::Borrowed(data));
+    }
+
+    let bytes = STANDARD.decode(trimmed)
+        .map_err(|e| ParserError::DecodingError(format!("invalid base64 data: {e}")))?;
+    decode_text(bytes).map(Cow::Owned)
+}
+
+/// Decodes an HTTP response body, decompressing it first if it is still gzip compressed
+pub fn decode_response_body(bytes: Vec<u8>) -> Result<String, ParserError> {
+    decode_text(bytes)
+}
+
+fn decode_text(mut bytes: Vec<u8>) -> Result<String, ParserError> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)
+            .map_err(|e| ParserError::DecodingError(format!("invalid gzip data: {e}")))?;
+        bytes = decompressed;
+    }
+
+    String::from_utf8(bytes)
+        .map_err(|e| ParserError::DecodingError(format!("invalid UTF-8 data: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    const PLAIN_DATA: &str = "[\"DOCTYPE\",\"SYMBOL\",\"1.1\"]\n[\"PART\",\"TEST.1\",{\"BBOX\":[-10,-10,10,10]}]";
+
+    #[test]
+    fn plain_data_is_unchanged() {
+        assert!(matches!(decode_data_str(PLAIN_DATA).unwrap(), Cow::Borrowed(PLAIN_DATA)));
+    }
+
+    #[test]
+    fn base64_data() {
+        let encoded = STANDARD.encode(PLAIN_DATA);
+        assert_eq!(decode_data_str(&encoded).unwrap(), PLAIN_DATA);
+    }
+
+    #[test]
+    fn base64_gzip_data() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(PLAIN_DATA.as_bytes()).unwrap();
+        let encoded = STANDARD.encode(encoder.finish().unwrap());
+        assert_eq!(decode_data_str(&encoded).unwrap(), PLAIN_DATA);
+    }
+
+    #[test]
+    fn gzip_response_body() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(PLAIN_DATA.as_bytes()).unwrap();
+        assert_eq!(decode_response_body(encoder.finish().unwrap()).unwrap(), PLAIN_DATA);
+        assert_eq!(decode_response_body(PLAIN_DATA.as_bytes().to_vec()).unwrap(), PLAIN_DATA);
+    }
+
+    #[test]
+    fn invalid_data() {
+        assert!(matches!(decode_data_str("not a payload"), Err(ParserError::DecodingError(_))));
+    }
+}
@@ -15,11 +15,42 @@ pub enum ParserError {
     #[error("Invalid {0} property type: {1}")]
     InvalidPropertyType(ParserType, String),
 
-    #[error("Invalid {0} array length: {1}")]
-    InvalidArrayLength(ParserType, String),
+    #[error("Invalid {parser_type} array length for {property_type}: expected {expected} parameters, found {actual} in {line}")]
+    InvalidArrayLength {
+        parser_type: ParserType,
+        property_type: String,
+        expected: String,
+        actual: usize,
+        line: String,
+    },
 
     #[error("Format error in {0}: {1}")]
     FormatError(ParserType, String),
+
+    #[error("Failed to decode EasyEDA data: {0}")]
+    DecodingError(String),
+
+    #[error("The EasyEDA {0} data is empty")]
+    EmptyData(ParserType),
+}
+
+const MAX_ERROR_LINE_LENGTH: usize = 80;
+
+impl ParserError {
+    pub fn invalid_array_length(parser_type: ParserType, property_type: &str, expected: &str, actual: usize, line: &str) -> Self {
+        let line = match line.char_indices().nth(MAX_ERROR_LINE_LENGTH) {
+            Some((end, _)) => format!("{}...", &line[..end]),
+            None => line.to_string(),
+        };
+
+        ParserError::InvalidArrayLength {
+            parser_type,
+            property_type: property_type.into(),
+            expected: expected.into(),
+            actual,
+            line,
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -45,9 +76,14 @@ pub enum FootprintConverterError {
     #[error("Elements are not supported on this layer: {0}")]
     UnsupportedLayer(String),
 
-    #[error("Unsupported pad drill rotation: {0}")]
-    UnsupportedDrillRotation(u32),
-
     #[error("Unsupported inner layer: {0}")]
     UnsupportedInnerLayer(String),
-}
\ No newline at end of file
+}
+#[derive(Error, Debug)]
+pub enum ProductSearchError {
+    #[error("Product code not found: '{0}'")]
+    ProductNotFound(String),
+
+    #[error("Product code not found: '{0}'. Did you mean one of these? {1}")]
+    ProductNotFoundWithCandidates(String, String),
+}
@@ -1,12 +1,20 @@
+use crate::easyeda::encoding;
+use crate::easyeda::geometry;
 use crate::easyeda::geometry::Point2D;
 use crate::easyeda::json_reader::JsonArrayReader;
 use crate::easyeda::errors::{FootprintConverterError, ParserError, ParserType};
-use crate::kicad::model::common::{Font, FontSize, Position, StrokeDefinition, TextEffect, TextJustifyHorizontal, TextJustifyVertical};
-use crate::kicad::model::footprint_library::{DrillDefinition, FootprintArc, FootprintAttributes, FootprintCircle, FootprintLibrary, FootprintLine, FootprintPad, FootprintPadPrimitives, FootprintPolygon, FootprintRectangle, FootprintText, FootprintTextType, FootprintType, PadShape, PadType, PcbLayer, PrimitivesContainer, Scalar2D, Scalar3D};
+use crate::kicad::model::common::{Font, FontSize, KiCadVersion, Position, StrokeDefinition, TextEffect, TextJustifyHorizontal, TextJustifyVertical};
+use crate::kicad::model::footprint_library::{AnchorType, ClearanceType, DrillDefinition, FootprintArc, FootprintAttributes, FootprintCircle, FootprintImage, FootprintLibrary, FootprintLine, FootprintPad, FootprintPadOptions, FootprintPadPrimitives, FootprintPolygon, FootprintRectangle, FootprintText, FootprintTextType, FootprintType, FootprintZone, FootprintZoneConnectPads, FootprintZoneFillSettings, FootprintZoneKeepoutSettings, HatchStyle, PadShape, PadTeardrops, PadType, PcbLayer, PrimitivesContainer, Scalar2D, Scalar3D, ZoneFillMode};
+use crate::kicad::model::graphical::GraphicPolygon;
+use crate::units::{INCH_TO_MM, MIL_TO_MM};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
-use std::f32::consts::PI;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use log::warn;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
 use std::ops::Add;
 
 #[allow(unused)]
@@ -14,24 +22,63 @@ use std::ops::Add;
 pub struct EasyEDAFootprint {
     pub head: Option<Head>,
     pub canvas: Canvas,
-    pub layers: HashMap<u8, Layer>,
-    pub physical_layers: HashMap<u8, PhysicalLayer>,
+    pub layers: BTreeMap<u8, Layer>,
+    pub physical_layers: BTreeMap<u8, PhysicalLayer>,
     pub active_layer: u8,
-    pub fills: HashMap<String, Fill>,
-    pub polygons: HashMap<String, Poly>,
-    pub pads: HashMap<String, Pad>,
+    // Elements are kept sorted by their id, so they are converted in the same order on every run
+    pub fills: BTreeMap<String, Fill>,
+    pub polygons: BTreeMap<String, Poly>,
+    pub pads: BTreeMap<String, Pad>,
     pub attributes: Vec<Attribute>,
 
     pub part_number: Option<String>,
+    pub datasheet: Option<String>,
+    pub package: Option<String>,
+    pub kicad_version: KiCadVersion,
     pub nets: Vec<Net>,
     pub rule_template: Option<RuleTemplate>,
     pub rules: Vec<Rule>,
     pub primitives: Vec<Primitive>,
-    pub strings: HashMap<String, StringObject>,
-    pub vias: HashMap<String, Via>,
-    pub images: HashMap<String, Image>,
+    pub strings: BTreeMap<String, StringObject>,
+    pub vias: BTreeMap<String, Via>,
+    pub images: BTreeMap<String, Image>,
+    pub regions: BTreeMap<String, Region>,
+    pub solid_regions: BTreeMap<String, SolidRegion>,
+    pub raster_images: RasterImageMode,
+    pub paste_ratio: Option<f32>,
+    /// Maximum distance in mm between an arc and the straight segments replacing it in polygons
+    pub arc_max_error: f32,
 }
 
+/// Controls what happens to images that hold raster data instead of vector paths
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum RasterImageMode {
+    /// Embed the image as a bitmap on the Dwgs.User layer
+    #[default]
+    Embed,
+    /// Leave the image out of the footprint
+    Skip,
+}
+
+static INNER_LAYER_NAME: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)^\s*(inner|signal|in|l)\s*[-_.]?\s*(\d+)\s*$").unwrap());
+
+/// Returns the 1-based inner layer index from names like `Inner1`, `Signal1`, `In1` or `L2`.
+/// `L<n>` counts the whole stack-up starting with the top layer, so `L2` is the first inner layer.
+fn parse_inner_layer_index(name: &str) -> Option<usize> {
+    let captures = INNER_LAYER_NAME.captures(name)?;
+    let index = captures[2].parse::<usize>().ok()?;
+    match captures[1].eq_ignore_ascii_case("l") {
+        true => index.checked_sub(1),
+        false => Some(index),
+    }
+}
+
+/// The arc approximation error KiCad itself uses by default
+pub const DEFAULT_ARC_MAX_ERROR: f32 = 0.005;
+
+/// Pixel density KiCad assumes for embedded bitmaps at a scale of 1
+const KICAD_BITMAP_PPI: f32 = 300.0;
+
 impl EasyEDAFootprint {
     #[allow(unused)]
     pub fn load_and_parse(path: &str) -> anyhow::Result<EasyEDAFootprint> {
@@ -40,21 +87,28 @@ impl EasyEDAFootprint {
     }
 
     pub fn parse(symbol_data: &str) -> anyhow::Result<EasyEDAFootprint> {
+        let symbol_data = encoding::decode_data_str(symbol_data)?;
+        if symbol_data.trim().is_empty() {
+            return Err(ParserError::EmptyData(ParserType::Footprint).into());
+        }
+
         let mut canvas = None;
         let mut head = None;
         let mut rule_template = None;
         let mut rules = Vec::new();
-        let mut physical_layers = HashMap::new();
-        let mut layers = HashMap::new();
-        let mut fills = HashMap::new();
-        let mut pads = HashMap::new();
-        let mut vias = HashMap::new();
-        let mut polygons = HashMap::new();
+        let mut physical_layers = BTreeMap::new();
+        let mut layers = BTreeMap::new();
+        let mut fills = BTreeMap::new();
+        let mut pads = BTreeMap::new();
+        let mut vias = BTreeMap::new();
+        let mut polygons = BTreeMap::new();
         let mut attributes = Vec::new();
         let mut nets = Vec::new();
         let mut primitives = Vec::new();
-        let mut strings = HashMap::new();
-        let mut images = HashMap::new();
+        let mut strings = BTreeMap::new();
+        let mut images = BTreeMap::new();
+        let mut regions: BTreeMap<String, Region> = BTreeMap::new();
+        let mut solid_regions: BTreeMap<String, SolidRegion> = BTreeMap::new();
 
         let mut active_layer = 0;
 
@@ -123,6 +177,10 @@ impl EasyEDAFootprint {
                             poly.attributes.push(attribute);
                         } else if let Some(pad) = pads.get_mut(parent_id) {
                             pad.attributes.push(attribute);
+                        } else if let Some(region) = regions.get_mut(parent_id) {
+                            region.attributes.push(attribute);
+                        } else if let Some(region) = solid_regions.get_mut(parent_id) {
+                            region.attributes.push(attribute);
                         } else {
                             panic!("Invalid footprint attribute: {:?}", attribute);
                         }
@@ -134,13 +192,25 @@ impl EasyEDAFootprint {
                 FootprintProperty::IMAGE(image) => {
                     images.insert(image.id.clone(), image);
                 }
+                FootprintProperty::REGION(region) => {
+                    regions.insert(region.id.clone(), region);
+                }
+                FootprintProperty::SOLIDREGION(region) => {
+                    solid_regions.insert(region.id.clone(), region);
+                }
             }
         }
 
+        let canvas = canvas
+            .ok_or_else(|| ParserError::FormatError(ParserType::Footprint, "the CANVAS definition is missing".into()))?;
+
         Ok(EasyEDAFootprint {
             head,
-            canvas: canvas.unwrap(),
+            canvas,
             part_number: None,
+            datasheet: None,
+            package: None,
+            kicad_version: KiCadVersion::default(),
             layers,
             physical_layers,
             active_layer,
@@ -153,6 +223,11 @@ impl EasyEDAFootprint {
             rules,
             strings,
             images,
+            regions,
+            solid_regions,
+            raster_images: RasterImageMode::default(),
+            paste_ratio: None,
+            arc_max_error: DEFAULT_ARC_MAX_ERROR,
             attributes,
             primitives,
         })
@@ -167,7 +242,7 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
             node_identifier: "footprint".to_string(),
 
             footprint_id: "test-footprint-0603".to_string(),
-            version: Some(20240108),
+            version: Some(self.kicad_version.footprint_version()),
             generator: Some("easyeda-to-kicad".into()),
             generator_version: None,
             model: None,
@@ -178,6 +253,7 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                 exclude_from_pos_files: false,
                 board_only: false,
             }),
+            net_tie_pad_groups: Vec::new(),
             lines: Vec::new(),
             arcs: Vec::new(),
             texts: Vec::new(),
@@ -186,11 +262,13 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
             polygons: Vec::new(),
             rectangles: Vec::new(),
             zones: Vec::new(),
+            images: Vec::new(),
             description: None,
             properties: Vec::new(),
             tags: None,
             layer: PcbLayer::FCu,
             solder_mask_margin: None,
+            clearance: None,
             zone_connect: None,
         };
 
@@ -202,10 +280,9 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
             ..Default::default()
         };
 
-        let scale_factor = 0.0254;
-
-        let mut max_y = f32::MIN;
-        let mut min_y = f32::MAX;
+        let scale_factor = MIL_TO_MM;
+        footprint.solder_mask_margin = self.get_solder_mask_expansion().map(|v| v * scale_factor);
+        footprint.clearance = self.get_default_rule_value(CLEARANCE_RULES).map(|v| v * scale_factor);
 
         fn get_kicad_layer(layer: &Layer) -> Result<Option<PcbLayer>, FootprintConverterError> {
             match layer.layer_type.as_str() {
@@ -227,40 +304,9 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                 "TOP" => Ok(Some(PcbLayer::FCu)),
                 "BOTTOM" => Ok(Some(PcbLayer::BCu)),
                 "MULTI" => Ok(None),
-                "SIGNAL" => {
-                    match layer.name.as_str() {
-                        "Inner1" => Ok(Some(PcbLayer::In1Cu)),
-                        "Inner2" => Ok(Some(PcbLayer::In2Cu)),
-                        "Inner3" => Ok(Some(PcbLayer::In3Cu)),
-                        "Inner4" => Ok(Some(PcbLayer::In4Cu)),
-                        "Inner5" => Ok(Some(PcbLayer::In5Cu)),
-                        "Inner6" => Ok(Some(PcbLayer::In6Cu)),
-                        "Inner7" => Ok(Some(PcbLayer::In7Cu)),
-                        "Inner8" => Ok(Some(PcbLayer::In8Cu)),
-                        "Inner9" => Ok(Some(PcbLayer::In9Cu)),
-                        "Inner10" => Ok(Some(PcbLayer::In10Cu)),
-                        "Inner11" => Ok(Some(PcbLayer::In11Cu)),
-                        "Inner12" => Ok(Some(PcbLayer::In12Cu)),
-                        "Inner13" => Ok(Some(PcbLayer::In13Cu)),
-                        "Inner14" => Ok(Some(PcbLayer::In14Cu)),
-                        "Inner15" => Ok(Some(PcbLayer::In15Cu)),
-                        "Inner16" => Ok(Some(PcbLayer::In16Cu)),
-                        "Inner17" => Ok(Some(PcbLayer::In17Cu)),
-                        "Inner18" => Ok(Some(PcbLayer::In18Cu)),
-                        "Inner19" => Ok(Some(PcbLayer::In19Cu)),
-                        "Inner20" => Ok(Some(PcbLayer::In20Cu)),
-                        "Inner21" => Ok(Some(PcbLayer::In21Cu)),
-                        "Inner22" => Ok(Some(PcbLayer::In22Cu)),
-                        "Inner23" => Ok(Some(PcbLayer::In23Cu)),
-                        "Inner24" => Ok(Some(PcbLayer::In24Cu)),
-                        "Inner25" => Ok(Some(PcbLayer::In25Cu)),
-                        "Inner26" => Ok(Some(PcbLayer::In26Cu)),
-                        "Inner27" => Ok(Some(PcbLayer::In27Cu)),
-                        "Inner28" => Ok(Some(PcbLayer::In28Cu)),
-                        "Inner29" => Ok(Some(PcbLayer::In29Cu)),
-                        "Inner30" => Ok(Some(PcbLayer::In30Cu)),
-                        str => Err(FootprintConverterError::UnsupportedInnerLayer(str.to_string()))
-                    }
+                "SIGNAL" => match parse_inner_layer_index(&layer.name).and_then(PcbLayer::inner_copper) {
+                    Some(kicad_layer) => Ok(Some(kicad_layer)),
+                    None => Err(FootprintConverterError::UnsupportedInnerLayer(layer.name.clone())),
                 }
                 str => Err(FootprintConverterError::UnsupportedLayer(format!("{:?}", str))),
             }
@@ -276,7 +322,7 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
             }
 
             let kicad_layer = kicad_layer.unwrap();
-            Self::populate_footprint_shapes(path, &mut footprint, kicad_layer, polygon.width, false, None, scale_factor, None);
+            Self::populate_footprint_shapes(path, &mut footprint, kicad_layer, polygon.width, false, polygon.is_locked, None, scale_factor, self.arc_max_error, None);
         }
 
         // Non-mechanical fills
@@ -289,7 +335,69 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
             }
 
             let kicad_layer = kicad_layer.unwrap();
-            Self::populate_footprint_shapes(&path_list, &mut footprint, kicad_layer, fill.width, true, None, scale_factor, None);
+            if !fill.net.is_empty() && PcbLayer::all_copper().contains(&kicad_layer) {
+                if let Some(zones) = Self::create_zones(&path_list, &fill.net, vec![kicad_layer], None, scale_factor, self.arc_max_error) {
+                    footprint.zones.extend(zones);
+                    continue;
+                }
+            }
+            Self::populate_footprint_shapes(&path_list, &mut footprint, kicad_layer, fill.width, true, fill.is_locked, None, scale_factor, self.arc_max_error, None);
+        }
+
+        // Keepout regions
+        for (_id, region) in &self.regions {
+            let layer = self.layers.get(&region.layer_id).unwrap();
+            let kicad_layers = match get_kicad_layer(layer)? {
+                Some(kicad_layer) if PcbLayer::all_copper().contains(&kicad_layer) => vec![kicad_layer],
+                None if layer.layer_type == "MULTI" => vec![PcbLayer::FCu, PcbLayer::BCu],
+                _ => {
+                    warn!("Skipping the keepout region '{}' on the non-copper layer '{}'", region.id, layer.name);
+                    continue;
+                }
+            };
+            let Some(keepout_settings) = region.get_keepout_settings() else {
+                continue;
+            };
+
+            let path_list = region.path.as_array().unwrap();
+            match Self::create_zones(path_list, "", kicad_layers, Some(keepout_settings), scale_factor, self.arc_max_error) {
+                Some(zones) => footprint.zones.extend(zones),
+                None => warn!("Skipping the keepout region '{}', its outline is not a plain polygon", region.id),
+            }
+        }
+
+        // Solid regions, either keepouts or plain filled areas
+        for (_id, region) in &self.solid_regions {
+            let layer = self.layers.get(&region.layer_id).unwrap();
+            let kicad_layers = match get_kicad_layer(layer)? {
+                Some(kicad_layer) => vec![kicad_layer],
+                None if layer.layer_type == "MULTI" => vec![PcbLayer::FCu, PcbLayer::BCu],
+                None => continue,
+            };
+            let is_copper = kicad_layers.iter().all(|l| PcbLayer::all_copper().contains(l));
+            let path_list = region.path.as_array().unwrap();
+
+            if let Some(keepout_settings) = region.get_keepout_settings() {
+                if !is_copper {
+                    warn!("Skipping the solid keepout region '{}' on the non-copper layer '{}'", region.id, layer.name);
+                    continue;
+                }
+                match Self::create_zones(path_list, "", kicad_layers, Some(keepout_settings), scale_factor, self.arc_max_error) {
+                    Some(zones) => footprint.zones.extend(zones),
+                    None => warn!("Skipping the solid keepout region '{}', its outline is not a plain polygon", region.id),
+                }
+                continue;
+            }
+
+            if !region.net.is_empty() && is_copper {
+                if let Some(zones) = Self::create_zones(path_list, &region.net, kicad_layers.clone(), None, scale_factor, self.arc_max_error) {
+                    footprint.zones.extend(zones);
+                    continue;
+                }
+            }
+            for kicad_layer in kicad_layers {
+                Self::populate_footprint_shapes(path_list, &mut footprint, kicad_layer, 0.0, true, region.is_locked, None, scale_factor, self.arc_max_error, None);
+            }
         }
 
         // Mechanical NPTH fills
@@ -316,8 +424,9 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                         pad_type: PadType::NpThruHole,
                         pad_shape: PadShape::Circle,
                         position: Position { x: center_x, y: center_y, angle: None },
-                        size: Scalar2D::new("size", radius * 2.0, radius * 2.0), // todo
-                        locked: false,
+                        size: Scalar2D::size(radius * 2.0, radius * 2.0), // todo
+                        rect_delta: None,
+                        locked: fill.is_locked,
                         drill: Some(DrillDefinition {
                             oval: false,
                             diameter: radius * 2.0,
@@ -347,13 +456,14 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                         clearance: None,
                         options: None,
                         primitives: None,
+                        teardrops: None,
                     };
 
                     footprint.attributes.as_mut().unwrap().footprint_type = FootprintType::ThroughHole;
                     footprint.pads.push(ki_pad);
                 } else {
                     let kicad_layer = PcbLayer::EdgeCuts;
-                    Self::populate_footprint_shapes(path, &mut footprint, kicad_layer, 0.05, false, None, scale_factor, None);
+                    Self::populate_footprint_shapes(path, &mut footprint, kicad_layer, 0.05, false, fill.is_locked, None, scale_factor, self.arc_max_error, None);
                 }
             }
         }
@@ -361,11 +471,20 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
         // Pads [THT + SMD]
         for (_id, pad) in &self.pads {
             let layer = self.layers.get(&pad.layer_id).unwrap();
-            let path = pad.path.as_ref().unwrap().as_array().unwrap();
+            let mut path = pad.path.as_ref().unwrap().as_array().unwrap().clone();
             let kicad_layer = get_kicad_layer(layer)?;
 
-            max_y = max_y.max(pad.center_y * scale_factor);
-            min_y = min_y.min(pad.center_y * scale_factor);
+            // KiCad pads have a single shape, only a special shape meant for the pad's own layer can be applied
+            match pad.get_special_pad_shapes() {
+                Some(special_shapes) => for special_shape in special_shapes {
+                    if layer.layer_type != "MULTI" && (special_shape.start_layer_id..=special_shape.end_layer_id).contains(&pad.layer_id) {
+                        path = special_shape.shape;
+                    } else {
+                        warn!("Pad {} has a different shape on layers {} to {}, which is not converted", pad.num, special_shape.start_layer_id, special_shape.end_layer_id);
+                    }
+                }
+                None => warn!("The special shapes of pad {} could not be decoded and are not converted: {:?}", pad.num, pad.special_pad),
+            }
 
             let mut ki_pad = FootprintPad {
                 number: pad.num.clone(),
@@ -374,10 +493,11 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                 position: Position {
                     x: pad.center_x * scale_factor,
                     y: -pad.center_y * scale_factor,
-                    angle: Some(pad.rotation),
+                    angle: Some(Position::normalize_angle(pad.rotation)),
                 },
-                size: Scalar2D::new("size", 0.0, 0.0), // todo
-                locked: false,
+                size: Scalar2D::size(0.0, 0.0), // todo
+                rect_delta: None,
+                locked: pad.is_locked,
                 drill: None,
                 layers: match layer.layer_type.as_str() {
                     "MULTI" => {
@@ -398,15 +518,17 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                 pin_function: None,
                 pin_type: None,
                 die_length: None,
-                solder_mask_margin: pad.top_solder_expansion.or(Some(2.0)).map(|v| v * scale_factor),
+                solder_mask_margin: pad.top_solder_expansion.map(|v| v * scale_factor),
                 solder_paste_margin: pad.top_paste_expansion.or(Some(0.0)).map(|v| v * scale_factor).map(|v| v.max(0.0)),
                 solder_paste_margin_ratio: None,
                 zone_connection: None,
                 clearance: None,
                 options: None,
                 primitives: None,
+                teardrops: None,
             };
 
+            // EasyEDA specifies pad sizes before rotation, same as KiCad does with the `at` angle
             if path.len() == 4 && path.get(0).unwrap().as_str().is_some_and(|s| s == "RECT") {
                 ki_pad.pad_shape = PadShape::Rect;
                 ki_pad.size.x = path.get(1).unwrap().as_f64().unwrap() as f32 * scale_factor;
@@ -422,13 +544,17 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                 /*if pad.rotation.abs() == 90.0 || pad.rotation.abs() == 270.0 {
                     (ki_pad.size.x, ki_pad.size.y) = (ki_pad.size.y, ki_pad.size.x);
                 }*/
+            } else if let Some((size, rect_delta)) = path.get(0).unwrap().as_str()
+                .filter(|s| *s == "POLY")
+                .and_then(|_| Self::get_trapezoid_pad_size(path.get(1).unwrap().as_array()?, Point2D::new(pad.center_x, pad.center_y), scale_factor)) {
+                ki_pad.pad_shape = PadShape::Trapezoid;
+                ki_pad.size = size;
+                ki_pad.rect_delta = Some(rect_delta);
             } else if path.get(0).unwrap().as_str().is_some_and(|s| s == "POLY") {
                 let path_data = path.get(1).unwrap().as_array().unwrap().clone();
                 // let path_data = Self::parse_path_expression(path_data, scale_factor);
 
                 ki_pad.pad_shape = PadShape::Custom;
-                ki_pad.size.x = 0.01;
-                ki_pad.size.y = 0.01;
 
                 let mut pad_primitives = FootprintPadPrimitives {
                     width: Some(0.2),
@@ -440,11 +566,20 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                     arcs: Vec::new(),
                     curves: Vec::new(),
                     annotation_boxes: Vec::new(),
+                    texts: Vec::new(),
                 };
 
-                Self::populate_footprint_shapes(&path_data, &mut pad_primitives, PcbLayer::FCu, 0.1, true, None, scale_factor, Some(Point2D::new(-pad.center_x * scale_factor, pad.center_y * scale_factor)));
+                Self::populate_footprint_shapes(&path_data, &mut pad_primitives, PcbLayer::FCu, 0.1, true, false, None, scale_factor, self.arc_max_error, Some(Point2D::new(-pad.center_x * scale_factor, pad.center_y * scale_factor)));
                 pad_primitives.width = None;
                 pad_primitives.fill = None;
+
+                // KiCad uses the anchor pad for ratsnest and clearance checks, so keep it as large as the shape allows
+                let (anchor_width, anchor_height) = Self::get_custom_pad_anchor_size(&pad_primitives).unwrap_or((MIN_ANCHOR_SIZE, MIN_ANCHOR_SIZE));
+                ki_pad.size = Scalar2D::size(anchor_width, anchor_height);
+                ki_pad.options = Some(FootprintPadOptions {
+                    clearance: ClearanceType::Outline,
+                    anchor: AnchorType::Rect,
+                });
                 ki_pad.primitives = Some(pad_primitives);
             } else {
                 return Err(FootprintConverterError::UnsupportedPadShape(format!("{:?}", pad)));
@@ -460,28 +595,51 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                 let hole_shape = hole_shape.get(0).unwrap().as_str().unwrap();
                 assert!(hole_shape == "SLOT" || hole_shape == "ROUND", "The following THT hole shape is not supported: '{}'", hole_shape);
 
-                if let Some(hole_rotation) = pad.hole_rotation {
-                    let hole_rotation = hole_rotation.abs() as u32 % 360;
-                    match hole_rotation {
-                        0 | 180 => {}
-                        90 | 270 => {
-                            (hole_param1, hole_param2) = (hole_param2, hole_param1)
-                        }
-                        rot => {
-                            return Err(FootprintConverterError::UnsupportedDrillRotation(rot))
-                        }
+                let hole_offset = Point2D::new(pad.hole_offset_x * scale_factor, pad.hole_offset_y * scale_factor);
+                if let Some(hole_rotation) = pad.hole_rotation.filter(|_| hole_shape == "SLOT") {
+                    // KiCad drills always follow the pad orientation, so a slot can only be turned by whole quarter turns
+                    let hole_rotation = hole_rotation.rem_euclid(360.0);
+                    let quarter_turns = (hole_rotation / 90.0).round();
+                    if (hole_rotation - quarter_turns * 90.0).abs() > 0.001 {
+                        warn!("The slot of pad {} is rotated by {}°, it is aligned to the nearest quarter turn", pad.num, hole_rotation);
+                    }
+                    if quarter_turns as u32 % 2 == 1 {
+                        (hole_param1, hole_param2) = (hole_param2, hole_param1)
                     }
                 }
 
                 ki_pad.pad_type = PadType::ThruHole;
                 ki_pad.drill = Some(DrillDefinition {
                     oval: hole_shape == "SLOT",
-                    offset: Some(Scalar2D::new("offset", pad.hole_offset_x * scale_factor, pad.hole_offset_y * scale_factor)),
+                    offset: Some(hole_offset.to_scalar_2d(Scalar2D::offset)),
                     width: Some(hole_param2 * scale_factor),
                     diameter: hole_param1 * scale_factor,
                 });
             }
 
+            // Pads without a paste opening, such as card-edge fingers, are contacts rather than soldered pads
+            let paste_expansion = match kicad_layer {
+                Some(PcbLayer::BCu) => pad.bottom_paste_expansion,
+                _ => pad.top_paste_expansion,
+            }.unwrap_or(0.0) * scale_factor;
+            if matches!(ki_pad.pad_type, PadType::Smd) && paste_expansion * 2.0 <= -ki_pad.size.x.min(ki_pad.size.y) {
+                ki_pad.pad_type = PadType::Connect;
+                ki_pad.layers.retain(|l| !matches!(l, PcbLayer::FPaste | PcbLayer::BPaste));
+                ki_pad.solder_paste_margin = None;
+            }
+
+            if matches!(ki_pad.pad_type, PadType::Smd) {
+                ki_pad.solder_paste_margin_ratio = self.paste_ratio;
+            }
+
+            if pad.has_teardrop() {
+                if self.kicad_version.has_pad_teardrops() {
+                    ki_pad.teardrops = Some(PadTeardrops::default());
+                } else {
+                    log::debug!("Pad {} requests a teardrop, which is not supported by KiCad {:?}", pad.num, self.kicad_version);
+                }
+            }
+
             footprint.pads.push(ki_pad);
         }
 
@@ -496,8 +654,9 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                     y: -via.center_y * scale_factor,
                     angle: None,
                 },
-                size: Scalar2D::new("size", via.via_diameter * scale_factor, via.via_diameter * scale_factor), // todo
-                locked: false,
+                size: Scalar2D::size(via.via_diameter * scale_factor, via.via_diameter * scale_factor), // todo
+                rect_delta: None,
+                locked: via.is_locked,
                 drill: Some(DrillDefinition {
                     oval: false,
                     diameter: via.hole_diameter * scale_factor,
@@ -520,13 +679,14 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                 pin_function: None,
                 pin_type: None,
                 die_length: None,
-                solder_mask_margin: via.top_solder_expansion.or(Some(2.0)).map(|v| v * scale_factor),
+                solder_mask_margin: via.top_solder_expansion.map(|v| v * scale_factor),
                 solder_paste_margin: None,
                 solder_paste_margin_ratio: None,
                 zone_connection: None,
                 clearance: None,
                 options: None,
                 primitives: None,
+                teardrops: None,
             };
 
             footprint.pads.push(ki_pad);
@@ -555,11 +715,21 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
                 8 | 9 | _ => (Some(TextJustifyHorizontal::Right), Some(TextJustifyVertical::Top)),
             };
 
+            // Mirrored text is meant to be read from the back of the board. Both EasyEDA and KiCad
+            // mirror the text around its anchor, so the justification carries over unchanged.
+            let kicad_layer = if string.is_mirrored {
+                text_style.justify.mirror = true;
+                kicad_layer.to_back()
+            } else {
+                kicad_layer
+            };
+
             footprint.texts.push(FootprintText {
                 text_type: FootprintTextType::User,
                 text: string.text.clone(),
                 position: Position { x: string.pos_x * scale_factor, y: -string.pos_y * scale_factor, angle: Some(string.angle) },
-                unlocked: Some(true),
+                // Locked strings keep their position relative to the footprint
+                unlocked: Some(!string.is_locked).filter(|u| *u),
                 layer: kicad_layer,
                 hide: false,
                 effects: text_style,
@@ -567,47 +737,204 @@ impl TryInto<FootprintLibrary> for EasyEDAFootprint {
             });
         }
 
-        max_y += default_text_effect.font.size.height;
-        min_y -= default_text_effect.font.size.height;
-
-        // Reference Property
-        footprint.properties.push(crate::kicad::model::footprint_library::FootprintProperty {
-            key: "Reference".into(),
-            value: Some("Ref**".into()),
-            position: Scalar3D::new("at", 0.0, -max_y, 0.0),
-            layer: PcbLayer::FSilkS,
-            hide: Some(false),
-            unlocked: None,
-            uuid: None,
-            effects: default_text_effect.clone(),
-        });
+        // Raster images, vector images are made of paths that are not converted yet
+        for image in self.images.values() {
+            let Some((mime_type, payload)) = image.get_raster_data() else {
+                continue;
+            };
 
-        // Value Property
-        footprint.properties.push(crate::kicad::model::footprint_library::FootprintProperty {
-            key: "Value".into(),
-            value: Some("Val**".into()),
-            position: Scalar3D::new("at", 0.0, -min_y, 0.0),
-            layer: PcbLayer::FFab,
-            hide: Some(false),
-            unlocked: None,
-            uuid: None,
-            effects: default_text_effect.clone(),
-        });
+            if self.raster_images == RasterImageMode::Skip {
+                warn!("Skipping raster image {} in the footprint", image.id);
+                continue;
+            }
+
+            let Some(pixel_width) = get_png_width(payload).filter(|_| mime_type == "image/png") else {
+                warn!("Skipping raster image {}: only PNG images can be embedded ({})", image.id, mime_type);
+                continue;
+            };
+
+            let width = image.width * scale_factor;
+            let native_width = pixel_width as f32 / KICAD_BITMAP_PPI * INCH_TO_MM;
+
+            // EasyEDA mirrors and rotates the image around its top-left corner, KiCad places it by its center
+            // and can't transform the bitmap itself, so only the center follows the transformation
+            if image.angle != 0.0 || image.is_mirrored {
+                warn!("Raster image {} is rotated or mirrored, KiCad will show it upright", image.id);
+            }
+            let mut center_offset = Point2D::new(image.width / 2.0, image.height / 2.0);
+            if image.is_mirrored {
+                center_offset = center_offset.mirror_x();
+            }
+            let center = Point2D::new(image.start_x, -image.start_y) + center_offset.rotate(-image.angle);
+
+            footprint.images.push(FootprintImage {
+                position: Scalar2D::at(center.x * scale_factor, center.y * scale_factor),
+                layer: PcbLayer::DwgsUser,
+                scale: Some(width / native_width),
+                data: payload.to_string(),
+                uuid: None,
+            });
+        }
+
+        // Mounting holes and other mechanical-only parts have nothing to place or order
+        if !footprint.pads.is_empty() && footprint.pads.iter().all(|p| matches!(p.pad_type, PadType::NpThruHole)) {
+            let attributes = footprint.attributes.as_mut().unwrap();
+            attributes.exclude_from_bom = true;
+            attributes.exclude_from_pos_files = true;
+        }
+
+        // Courtyard around the pads and the component body
+        let is_through_hole = matches!(footprint.attributes.as_ref().unwrap().footprint_type, FootprintType::ThroughHole);
+        let is_bottom_only = !footprint.pads.is_empty() && footprint.pads.iter().all(|p| !p.layers.contains(&PcbLayer::FCu));
+        let courtyard_layers = match (is_through_hole, is_bottom_only) {
+            (true, _) => vec![PcbLayer::FCrtYd, PcbLayer::BCrtYd],
+            (false, true) => vec![PcbLayer::BCrtYd],
+            (false, false) => vec![PcbLayer::FCrtYd],
+        };
+        let body_layers = [PcbLayer::FCu, PcbLayer::BCu, PcbLayer::FFab];
+        if let Some((min_x, min_y, max_x, max_y)) = footprint.get_layer_bounds(&body_layers) {
+            for layer in courtyard_layers {
+                footprint.add_rectangle(FootprintRectangle {
+                    start: Scalar2D::start(round_to_grid(min_x - COURTYARD_CLEARANCE), round_to_grid(min_y - COURTYARD_CLEARANCE)),
+                    end: Scalar2D::end(round_to_grid(max_x + COURTYARD_CLEARANCE), round_to_grid(max_y + COURTYARD_CLEARANCE)),
+                    layer,
+                    width: Some(COURTYARD_WIDTH),
+                    fill: Some(false),
+                    stroke: None,
+                    uuid: None,
+                    locked: false,
+                });
+            }
+        }
+
+        // Reference goes above the silkscreen, Value below the fabrication outline
+        let silk_extents = footprint.get_layer_extents_y(&[PcbLayer::FSilkS, PcbLayer::FCu, PcbLayer::FCrtYd]).unwrap_or((0.0, 0.0));
+        let fab_extents = footprint.get_layer_extents_y(&[PcbLayer::FFab, PcbLayer::FCu]).unwrap_or((0.0, 0.0));
+        let reference_y = silk_extents.0 - default_text_effect.font.size.height;
+        let value_y = fab_extents.1 + default_text_effect.font.size.height;
+
+        // The Reference follows the mirroring of the EasyEDA designator attribute
+        let mut reference_effect = default_text_effect.clone();
+        reference_effect.justify.mirror = self.attributes.iter().any(|a| a.key == "Designator" && a.is_mirrored);
+        let reference_layer = if reference_effect.justify.mirror { PcbLayer::BSilkS } else { PcbLayer::FSilkS };
+
+        if self.kicad_version.has_footprint_field_properties() {
+            // Reference Property
+            footprint.properties.push(crate::kicad::model::footprint_library::FootprintProperty {
+                key: "Reference".into(),
+                value: Some("Ref**".into()),
+                position: Scalar3D::at(0.0, reference_y, 0.0),
+                layer: reference_layer,
+                hide: Some(false),
+                unlocked: None,
+                uuid: None,
+                effects: reference_effect,
+            });
+
+            // Value Property
+            footprint.properties.push(crate::kicad::model::footprint_library::FootprintProperty {
+                key: "Value".into(),
+                value: Some("Val**".into()),
+                position: Scalar3D::at(0.0, value_y, 0.0),
+                layer: PcbLayer::FFab,
+                hide: Some(false),
+                unlocked: None,
+                uuid: None,
+                effects: default_text_effect.clone(),
+            });
+        } else {
+            footprint.texts.push(FootprintText {
+                text_type: FootprintTextType::Reference,
+                text: "Ref**".into(),
+                position: Position { x: 0.0, y: reference_y, angle: None },
+                unlocked: None,
+                layer: reference_layer,
+                hide: false,
+                effects: reference_effect,
+                uuid: None,
+            });
+
+            footprint.texts.push(FootprintText {
+                text_type: FootprintTextType::Value,
+                text: "Val**".into(),
+                position: Position { x: 0.0, y: value_y, angle: None },
+                unlocked: None,
+                layer: PcbLayer::FFab,
+                hide: false,
+                effects: default_text_effect.clone(),
+                uuid: None,
+            });
+        }
+
+        if let Some(part_number) = &self.part_number {
+            footprint.add_hidden_property("LCSC", part_number);
+        }
+        if let Some(datasheet) = &self.datasheet {
+            footprint.add_hidden_property("Datasheet", datasheet);
+        }
+
+        // Tag the footprint with its package so KiCad's footprint search can find it
+        let package = self.package.clone().or_else(|| self.head.as_ref().and_then(|h| h.title.clone()));
+        if let Some(package) = package.filter(|p| !p.trim().is_empty()) {
+            let mount_type = match footprint.attributes.as_ref().map(|a| &a.footprint_type) {
+                Some(FootprintType::ThroughHole) => "THT",
+                _ => "SMD",
+            };
+            footprint.tags = Some(format!("{} {}", package.trim(), mount_type));
+        }
 
         Ok(footprint)
     }
 }
 
+/// Anchor pad size used for custom pads when no larger anchor fits inside the shape
+const MIN_ANCHOR_SIZE: f32 = 0.01;
+
+const COURTYARD_CLEARANCE: f32 = 0.25;
+const COURTYARD_WIDTH: f32 = 0.05;
+
+/// Consecutive polygon points closer than this (in mm) are merged
+const POINT_TOLERANCE: f32 = 0.0001;
+
+/// Drops points that (nearly) coincide with the previous one, they would form zero-length segments
+fn remove_coincident_points(points: &mut Vec<Scalar2D>) {
+    points.dedup_by(|point, previous| (point.x - previous.x).hypot(point.y - previous.y) < POINT_TOLERANCE);
+}
+
+/// Rounds a courtyard coordinate outwards to the 0.01mm grid
+fn round_to_grid(value: f32) -> f32 {
+    match value < 0.0 {
+        true => (value * 100.0).floor() / 100.0,
+        false => (value * 100.0).ceil() / 100.0,
+    }
+}
+
 #[derive(Debug)]
 pub enum PathCommand {
     MoveTo { position: Point2D },
     LineTo { position: Point2D },
     ArcTo { end: Point2D, rotation: f32 },
-    CenterArcTo { end: Point2D, rotation: f32 },
+    CenterArcTo { center: Point2D, rotation: f32 },
     Circle { center: Point2D, radius: f32 },
     Rectangle { start: Point2D, width: f32, height: f32, rotation: f32, corner_radius: f32 },
 }
 
+impl PathCommand {
+    /// Returns the end point (with the Y axis flipped) and the sweep angle of an arc starting at `start`.
+    /// `ARC` gives the end point directly, `CARC` gives the center the start point is rotated around.
+    fn get_arc_end(&self, start: Point2D) -> Option<(Point2D, f32)> {
+        match self {
+            PathCommand::ArcTo { end, rotation } => Some((Point2D::new(end.x, -end.y), *rotation)),
+            PathCommand::CenterArcTo { center, rotation } => {
+                // Positive angles are counter-clockwise in EasyEDA, which is clockwise once the Y axis is flipped
+                let center = Point2D::new(center.x, -center.y);
+                Some((geometry::rotate_point(start, center, -*rotation), *rotation))
+            }
+            _ => None,
+        }
+    }
+}
+
 impl Add for Point2D {
     type Output = Self;
 
@@ -617,13 +944,60 @@ impl Add for Point2D {
 }
 
 impl EasyEDAFootprint {
+    /// Returns the footprint-wide solder mask expansion, set either as a footprint attribute
+    /// or as a default solder mask expansion rule
+    fn get_solder_mask_expansion(&self) -> Option<f32> {
+        let attribute_value = self.attributes.iter()
+            .filter(|a| SOLDER_MASK_EXPANSION_RULES.contains(&normalize_rule_type(&a.key).as_str()))
+            .find_map(|a| a.value.as_ref().and_then(|v| v.trim().parse::<f32>().ok()));
+        if attribute_value.is_some() {
+            return attribute_value;
+        }
+
+        self.get_default_rule_value(SOLDER_MASK_EXPANSION_RULES)
+    }
+
+    /// Returns the value of the default rule of the given types
+    fn get_default_rule_value(&self, rule_types: &[&str]) -> Option<f32> {
+        self.rules.iter()
+            .filter(|r| r.is_default && rule_types.contains(&normalize_rule_type(&r.rule_type).as_str()))
+            .find_map(Rule::get_value)
+    }
+
+    /// Describes the design rules that have no KiCad equivalent, so they can be reviewed by hand.
+    /// Returns None if every rule was converted.
+    pub fn describe_unmapped_rules(&self) -> Option<String> {
+        let unmapped_rules = self.rules.iter()
+            .filter(|r| {
+                let rule_type = normalize_rule_type(&r.rule_type);
+                let is_mapped = SOLDER_MASK_EXPANSION_RULES.contains(&rule_type.as_str()) || CLEARANCE_RULES.contains(&rule_type.as_str());
+                !(r.is_default && is_mapped && r.get_value().is_some())
+            })
+            .map(|r| format!("{} '{}'{}: {}", r.rule_type, r.name, if r.is_default { " (default)" } else { "" }, r.context))
+            .collect::<Vec<_>>();
+        if unmapped_rules.is_empty() {
+            return None;
+        }
+
+        let mut description = String::from("EasyEDA design rules that could not be converted to KiCad:\n");
+        if let Some(rule_template) = &self.rule_template {
+            description.push_str(&format!("Rule template: {}\n", rule_template.name));
+        }
+        for rule in unmapped_rules {
+            description.push_str(&format!("  {rule}\n"));
+        }
+        Some(description)
+    }
+
     fn populate_footprint_shapes(
         paths: &Vec<Value>,
         footprint: &mut impl PrimitivesContainer,
         layer: PcbLayer, stroke_width: f32,
         filled: bool,
+        locked: bool,
         stroke: Option<StrokeDefinition>,
         scale_factor: f32,
+        arc_max_error: f32,
         offset: Option<Point2D>,
     ) -> bool {
         if paths.len() == 0 {
@@ -633,7 +1007,7 @@ impl EasyEDAFootprint {
         // Handle nested arrays on the top level
         if paths.iter().all(|path| path.is_array()) {
             for sub_path in paths.iter().map(|path| path.as_array().unwrap()) {
-                Self::populate_footprint_shapes(sub_path, footprint, layer, stroke_width, filled, stroke.clone(), scale_factor, offset);
+                Self::populate_footprint_shapes(sub_path, footprint, layer, stroke_width, filled, locked, stroke.clone(), scale_factor, arc_max_error, offset);
             }
             return true;
         }
@@ -652,7 +1026,7 @@ impl EasyEDAFootprint {
                 PathCommand::MoveTo { position } => PathCommand::MoveTo { position: position + offset },
                 PathCommand::LineTo { position } => PathCommand::MoveTo { position: position + offset },
                 PathCommand::ArcTo { end, rotation } => PathCommand::ArcTo { end: end + offset, rotation },
-                PathCommand::CenterArcTo { end, rotation } => PathCommand::CenterArcTo { end: end + offset, rotation },
+                PathCommand::CenterArcTo { center, rotation } => PathCommand::CenterArcTo { center: center + offset, rotation },
                 PathCommand::Circle { center, radius } => PathCommand::Circle { center: center + offset, radius },
                 PathCommand::Rectangle { start, width, height, rotation, corner_radius } => PathCommand::Rectangle { start: start + offset, width, height, rotation, corner_radius }
             }).collect()
@@ -668,30 +1042,40 @@ impl EasyEDAFootprint {
                 match command {
                     PathCommand::Circle { center, radius } => {
                         footprint.add_circle(FootprintCircle {
-                            center: Scalar2D::new("center", center.x, center.y),
-                            end: Scalar2D::new("end", center.x + radius, center.y),
+                            center: Scalar2D::center(center.x, center.y),
+                            end: Scalar2D::end(center.x + radius, center.y),
                             layer,
                             width: Some(stroke_width * scale_factor),
                             fill: Some(filled),
                             stroke: None,
                             uuid: None,
-                            locked: false,
+                            locked,
                         })
                     }
                     PathCommand::Rectangle { start, width, height, rotation, corner_radius } => {
                         if rotation == 0.0 && corner_radius == 0.0 {
                             footprint.add_rectangle(FootprintRectangle {
-                                start: Scalar2D::new("center", start.x, start.y),
-                                end: Scalar2D::new("end", start.x + width, start.y + height),
+                                start: Scalar2D::start(start.x, start.y),
+                                end: Scalar2D::end(start.x + width, start.y + height),
                                 layer,
                                 width: Some(stroke_width * scale_factor),
                                 fill: Some(filled),
                                 stroke: None,
                                 uuid: None,
-                                locked: false,
+                                locked,
+                            })
+                        } else if corner_radius == 0.0 {
+                            footprint.add_polygon(FootprintPolygon {
+                                fill: Some(filled),
+                                layer,
+                                width: Some(stroke_width * scale_factor),
+                                points: Self::get_rectangle_corners(start, width, height, rotation).iter().map(|c| c.to_scalar_2d(Scalar2D::xy)).collect(),
+                                stroke: None,
+                                uuid: None,
+                                locked,
                             })
                         } else {
-                            todo!("Angled rectangles or corner radii are not implemented yet")
+                            todo!("Rectangles with corner radii are not implemented yet")
                         }
                     }
                     PathCommand::MoveTo { .. } |
@@ -707,16 +1091,24 @@ impl EasyEDAFootprint {
                     Self::expand_bbox_to_shape(&path[0], &mut bb_min, &mut bb_max);
                     Self::expand_bbox_to_shape(&path[1], &mut bb_min, &mut bb_max);
                     footprint.add_line(FootprintLine {
-                        start: Scalar2D::new("start", start.x, start.y),
-                        end: Scalar2D::new("end", end.x, end.y),
+                        start: Scalar2D::start(start.x, start.y),
+                        end: Scalar2D::end(end.x, end.y),
                         layer,
                         width: Some(stroke_width * scale_factor),
                         uuid: None,
-                        locked: false,
+                        locked,
                         stroke: None,
                     });
                 }
 
+                // Handle open copper traces, KiCad would close them if they were emitted as polygons
+                trace if !filled && PcbLayer::all_copper().contains(&layer) && !Self::is_closed_path(trace) => {
+                    for command in trace {
+                        Self::expand_bbox_to_shape(&command, &mut bb_min, &mut bb_max);
+                    }
+                    Self::add_open_trace(trace, footprint, layer, stroke_width * scale_factor, locked);
+                }
+
                 // Handle polygons
                 polygon => {
                     let mut points = vec![];
@@ -724,15 +1116,16 @@ impl EasyEDAFootprint {
                         Self::expand_bbox_to_shape(&command, &mut bb_min, &mut bb_max);
                         match command {
                             PathCommand::MoveTo { position } => {
-                                points.push(position.to_scalar_2d("xy"));
+                                points.push(position.to_scalar_2d(Scalar2D::xy));
                             }
                             PathCommand::LineTo { position } => {
-                                points.push(position.to_scalar_2d("xy"));
+                                points.push(position.to_scalar_2d(Scalar2D::xy));
                             }
                             PathCommand::ArcTo { .. } | PathCommand::CenterArcTo { .. } => unreachable!(),
                             PathCommand::Circle { .. } | PathCommand::Rectangle { .. } => unreachable!(),
                         }
                     }
+                    remove_coincident_points(&mut points);
 
                     footprint.add_polygon(FootprintPolygon {
                         fill: Some(filled),
@@ -741,32 +1134,37 @@ impl EasyEDAFootprint {
                         points,
                         stroke: None,
                         uuid: None,
-                        locked: false,
+                        locked,
                     })
                 }
             }
         } else if contains_arcs {
-            // println!("{:?}", path);
             match path.as_slice() {
                 // Handle standalone arc
-                [PathCommand::MoveTo { position: start }, PathCommand::ArcTo { end, rotation }] |
-                [PathCommand::MoveTo { position: start }, PathCommand::CenterArcTo { end, rotation }] => {
-                    let start = Point2D::new(start.x, start.y);
-                    let end = Point2D::new(end.x, -end.y);
-                    let mid = Self::get_arc_center(start, end, *rotation);
+                [PathCommand::MoveTo { position: start }, arc @ (PathCommand::ArcTo { .. } | PathCommand::CenterArcTo { .. })] => {
+                    let (end, rotation) = arc.get_arc_end(*start).unwrap();
+                    let mid = geometry::get_arc_mid_point(*start, end, rotation);
                     footprint.add_arc(FootprintArc {
-                        start: Scalar2D::new("start", start.x, start.y),
-                        mid: Some(Scalar2D::new("mid", mid.x, mid.y)),
-                        end: Scalar2D::new("end", end.x, end.y),
+                        start: Scalar2D::start(start.x, start.y),
+                        mid: Some(Scalar2D::mid(mid.x, mid.y)),
+                        end: Scalar2D::end(end.x, end.y),
                         layer,
                         width: Some(stroke_width * scale_factor),
                         angle: None,
                         stroke: None,
                         uuid: None,
-                        locked: false,
+                        locked,
                     });
                 }
 
+                // Handle open copper traces, KiCad would close them if they were emitted as polygons
+                trace if !filled && PcbLayer::all_copper().contains(&layer) && !Self::is_closed_path(trace) => {
+                    for command in trace {
+                        Self::expand_bbox_to_shape(&command, &mut bb_min, &mut bb_max);
+                    }
+                    Self::add_open_trace(trace, footprint, layer, stroke_width * scale_factor, locked);
+                }
+
                 // Handle polygons
                 polygon => {
                     let mut points = vec![];
@@ -774,27 +1172,27 @@ impl EasyEDAFootprint {
                     for command in polygon {
                         match command {
                             PathCommand::MoveTo { position } => {
-                                points.push(position.to_scalar_2d("xy"));
+                                points.push(position.to_scalar_2d(Scalar2D::xy));
                                 last_position = position.clone();
                             }
                             PathCommand::LineTo { position } => {
-                                points.push(position.to_scalar_2d("xy"));
+                                points.push(position.to_scalar_2d(Scalar2D::xy));
                                 last_position = position.clone();
                             }
-                            PathCommand::ArcTo { end, rotation } |
-                            PathCommand::CenterArcTo { end, rotation } => {
-                                let end = Point2D::new(end.x, -end.y);
+                            arc @ (PathCommand::ArcTo { .. } | PathCommand::CenterArcTo { .. }) => {
+                                let (end, rotation) = arc.get_arc_end(last_position).unwrap();
 
-                                for mid in Self::interpolate_arc_points(last_position, end, -*rotation, 8.0) {
-                                    points.push(mid.to_scalar_2d("xy"));
+                                for mid in geometry::interpolate_arc_points(last_position, end, -rotation, arc_max_error) {
+                                    points.push(mid.to_scalar_2d(Scalar2D::xy));
                                 }
 
-                                points.push(end.to_scalar_2d("xy"));
+                                points.push(end.to_scalar_2d(Scalar2D::xy));
                                 last_position = end.clone();
                             }
                             PathCommand::Circle { .. } | PathCommand::Rectangle { .. } => unreachable!(),
                         }
                     }
+                    remove_coincident_points(&mut points);
 
                     footprint.add_polygon(FootprintPolygon {
                         fill: Some(filled),
@@ -803,7 +1201,7 @@ impl EasyEDAFootprint {
                         points,
                         stroke: None,
                         uuid: None,
-                        locked: false,
+                        locked,
                     })
                 }
             }
@@ -812,6 +1210,218 @@ impl EasyEDAFootprint {
         true
     }
 
+    /// Emits an open copper path as individual line and arc segments
+    fn add_open_trace(trace: &[PathCommand], footprint: &mut impl PrimitivesContainer, layer: PcbLayer, width: f32, locked: bool) {
+        let mut last_position = None;
+        for command in trace {
+            match (command, last_position) {
+                (PathCommand::MoveTo { position }, _) => last_position = Some(*position),
+                (PathCommand::LineTo { position }, Some(start)) => {
+                    footprint.add_line(FootprintLine {
+                        start: start.to_scalar_2d(Scalar2D::start),
+                        end: position.to_scalar_2d(Scalar2D::end),
+                        layer,
+                        width: Some(width),
+                        uuid: None,
+                        locked,
+                        stroke: None,
+                    });
+                    last_position = Some(*position);
+                }
+                (arc @ (PathCommand::ArcTo { .. } | PathCommand::CenterArcTo { .. }), Some(start)) => {
+                    let (end, rotation) = arc.get_arc_end(start).unwrap();
+                    let mid = geometry::get_arc_mid_point(start, end, rotation);
+                    footprint.add_arc(FootprintArc {
+                        start: start.to_scalar_2d(Scalar2D::start),
+                        mid: Some(mid.to_scalar_2d(Scalar2D::mid)),
+                        end: end.to_scalar_2d(Scalar2D::end),
+                        layer,
+                        width: Some(width),
+                        angle: None,
+                        stroke: None,
+                        uuid: None,
+                        locked,
+                    });
+                    last_position = Some(end);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Converts a copper fill or keepout region into zones, returns None if the outline is not made of plain polygons
+    fn create_zones(
+        paths: &Vec<Value>,
+        net_name: &str,
+        layers: Vec<PcbLayer>,
+        keepout_settings: Option<FootprintZoneKeepoutSettings>,
+        scale_factor: f32,
+        arc_max_error: f32,
+    ) -> Option<Vec<FootprintZone>> {
+        let mut outlines = FootprintPadPrimitives {
+            width: None,
+            fill: None,
+            rectangles: Vec::new(),
+            circles: Vec::new(),
+            polygons: Vec::new(),
+            lines: Vec::new(),
+            arcs: Vec::new(),
+            curves: Vec::new(),
+            annotation_boxes: Vec::new(),
+            texts: Vec::new(),
+        };
+        Self::populate_footprint_shapes(paths, &mut outlines, layers[0], 0.0, true, false, None, scale_factor, arc_max_error, None);
+
+        let has_other_shapes = !outlines.rectangles.is_empty() || !outlines.circles.is_empty() || !outlines.lines.is_empty()
+            || !outlines.arcs.is_empty() || !outlines.curves.is_empty();
+        if outlines.polygons.is_empty() || has_other_shapes {
+            return None;
+        }
+
+        Some(outlines.polygons.into_iter().map(|polygon| FootprintZone {
+            net: 0,
+            net_name: net_name.into(),
+            layer: layers.clone(),
+            uuid: None,
+            name: None,
+            hatch_style: HatchStyle::Edge,
+            hatch_pitch: 0.5,
+            priority: None,
+            connect_pads: FootprintZoneConnectPads {
+                connection_type: None,
+                clearance: 0.0,
+            },
+            min_thickness: 0.25,
+            filled_areas_thickness: Some(false),
+            keepout_settings: keepout_settings.clone(),
+            fill_settings: FootprintZoneFillSettings {
+                fill: keepout_settings.is_none().then_some(true),
+                mode: ZoneFillMode::Solid,
+                thermal_gap: 0.5,
+                thermal_bridge_width: 0.5,
+                smoothing: None,
+                radius: None,
+                island_removal_mode: None,
+                island_area_min: None,
+                hatch_thickness: None,
+                hatch_gap: None,
+                hatch_orientation: None,
+                hatch_smoothing_level: None,
+                hatch_smoothing_value: None,
+                hatch_border_algorithm: None,
+                hatch_min_hole_area: None,
+            },
+            coordinate_points: GraphicPolygon {
+                points: polygon.points,
+                layer: None,
+                width: None,
+                fill: None,
+                uuid: None,
+            },
+            filled_polygons: vec![],
+            fill_segments: vec![],
+        }).collect())
+    }
+
+    /// Returns the size and `rect_delta` of the pad if its outline is a trapezoid centered on the pad origin,
+    /// with one pair of parallel edges that KiCad's trapezoid pad shape can express
+    fn get_trapezoid_pad_size(path_data: &Vec<Value>, center: Point2D, scale_factor: f32) -> Option<(Scalar2D, Scalar2D)> {
+        const EPSILON: f32 = 1e-4;
+
+        if path_data.iter().any(|v| v.is_array()) {
+            return None;
+        }
+        let path = Self::parse_path_expression(path_data.clone(), scale_factor);
+        let mut points = path.iter()
+            .map(|c| match c {
+                PathCommand::MoveTo { position } | PathCommand::LineTo { position } => Some(Point2D::new(position.x - center.x * scale_factor, position.y + center.y * scale_factor)),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        if points.len() == 5 && (points[0].x - points[4].x).abs() < EPSILON && (points[0].y - points[4].y).abs() < EPSILON {
+            points.pop();
+        }
+        if points.len() != 4 {
+            return None;
+        }
+
+        // Measures the two edges perpendicular to the first axis, which must sit at -half and +half of it.
+        // Rectangles are left to the custom pad conversion
+        let measure = |first: fn(&Point2D) -> f32, second: fn(&Point2D) -> f32| -> Option<(f32, f32, f32)> {
+            let half = points.iter().map(|p| first(p).abs()).fold(0.0, f32::max);
+            let edge_length = |sign: f32| -> Option<f32> {
+                let edge = points.iter().filter(|p| (first(p) - sign * half).abs() < EPSILON).map(second).collect::<Vec<_>>();
+                match edge.as_slice() {
+                    [a, b] if (a + b).abs() < EPSILON && (a - b).abs() > EPSILON => Some((a - b).abs()),
+                    _ => None,
+                }
+            };
+            let (near, far) = (edge_length(-1.0)?, edge_length(1.0)?);
+            Some((half * 2.0, near, far)).filter(|_| (near - far).abs() > EPSILON)
+        };
+
+        if let Some((width, left, right)) = measure(|p| p.x, |p| p.y) {
+            return Some((Scalar2D::size(width, (left + right) / 2.0), Scalar2D::rect_delta((left - right) / 2.0, 0.0)));
+        }
+        if let Some((height, top, bottom)) = measure(|p| p.y, |p| p.x) {
+            return Some((Scalar2D::size((top + bottom) / 2.0, height), Scalar2D::rect_delta(0.0, (bottom - top) / 2.0)));
+        }
+        None
+    }
+
+    /// Returns the size of the largest rectangle centered on the pad origin that still fits inside the pad's polygons
+    fn get_custom_pad_anchor_size(primitives: &FootprintPadPrimitives) -> Option<(f32, f32)> {
+        let polygons = primitives.polygons.iter()
+            .map(|p| p.points.iter().map(|p| Point2D::new(p.x, p.y)).collect::<Vec<_>>())
+            .filter(|p| p.len() >= 3)
+            .collect::<Vec<_>>();
+
+        let points = polygons.iter().flatten().collect::<Vec<_>>();
+        let min_x = points.iter().map(|p| p.x).reduce(f32::min)?;
+        let min_y = points.iter().map(|p| p.y).reduce(f32::min)?;
+        let max_x = points.iter().map(|p| p.x).reduce(f32::max)?;
+        let max_y = points.iter().map(|p| p.y).reduce(f32::max)?;
+
+        // Start from the bounding box and shrink until the rectangle's corners and edge centers are covered
+        let mut half_width = (-min_x).min(max_x);
+        let mut half_height = (-min_y).min(max_y);
+        while half_width * 2.0 >= MIN_ANCHOR_SIZE && half_height * 2.0 >= MIN_ANCHOR_SIZE {
+            let is_covered = [(-1.0, -1.0), (0.0, -1.0), (1.0, -1.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (-1.0, 1.0), (-1.0, 0.0)]
+                .map(|(x, y)| Point2D::new(x * half_width, y * half_height))
+                .iter()
+                .all(|p| polygons.iter().any(|polygon| geometry::is_point_in_polygon(*p, polygon)));
+            if is_covered {
+                return Some((half_width * 2.0, half_height * 2.0));
+            }
+
+            half_width *= 0.9;
+            half_height *= 0.9;
+        }
+
+        None
+    }
+
+    /// Returns the corners of a rectangle that is rotated around its top-left corner,
+    /// counter-clockwise like other EasyEDA angles, which is clockwise once the Y axis is flipped
+    fn get_rectangle_corners(start: Point2D, width: f32, height: f32, rotation: f32) -> [Point2D; 4] {
+        [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)]
+            .map(|(x, y)| start + Point2D::new(x, y).rotate(-rotation))
+    }
+
+    fn get_command_position(command: &PathCommand) -> Option<Point2D> {
+        match command {
+            PathCommand::MoveTo { position } | PathCommand::LineTo { position } => Some(*position),
+            _ => None,
+        }
+    }
+
+    fn is_closed_path(path: &[PathCommand]) -> bool {
+        match (path.first().and_then(Self::get_command_position), path.last().and_then(Self::get_command_position)) {
+            (Some(first), Some(last)) => (first.x - last.x).abs() < 1e-6 && (first.y - last.y).abs() < 1e-6,
+            _ => false,
+        }
+    }
+
     fn parse_path_expression(mut path: Vec<Value>, scale_factor: f32) -> Vec<PathCommand> {
         // Ensure that the first element is a Move ("M") command
         if path.first().unwrap().is_f64() || path.first().unwrap().is_i64() {
@@ -868,7 +1478,7 @@ impl EasyEDAFootprint {
                 },
                 "CARC" => PathCommand::CenterArcTo {
                     rotation: param_iter.next().unwrap().as_f64().unwrap() as f32,
-                    end: Point2D::new(
+                    center: Point2D::new(
                         param_iter.next().unwrap().as_f64().unwrap() as f32 * scale_factor,
                         param_iter.next().unwrap().as_f64().unwrap() as f32 * scale_factor,
                     ),
@@ -897,147 +1507,6 @@ impl EasyEDAFootprint {
         path
     }
 
-    fn get_arc_center(start: Point2D, end: Point2D, angle: f32) -> Point2D {
-        // Calculate chord midpoint
-        let chord_mid = Point2D {
-            x: (start.x + end.x) / 2.0,
-            y: (start.y + end.y) / 2.0,
-        };
-
-        // Calculate chord length
-        let chord_length = f32::sqrt(
-            (end.x - start.x).powi(2) +
-                (end.y - start.y).powi(2)
-        );
-
-        // Convert arc angle to radians and get central angle
-        let arc_angle = angle.abs() * std::f32::consts::PI / 180.0;
-        let central_angle = arc_angle / 2.0;
-
-        // Calculate radius using r = c/(2*sin(θ/2))
-        let radius = chord_length / (2.0 * central_angle.sin());
-
-        // Calculate sagitta (height of arc from chord)
-        let sagitta = radius * (1.0 - central_angle.cos());
-
-        // Calculate perpendicular vector to chord
-        let dx = end.x - start.x;
-        let dy = end.y - start.y;
-
-        // Direction depends on angle sign
-        let sign = if angle < 0.0 { -1.0 } else { 1.0 };
-        let perp_x = -dy * sign;
-        let perp_y = dx * sign;
-
-        // Normalize perpendicular vector
-        let perp_length = f32::sqrt(perp_x.powi(2) + perp_y.powi(2));
-        let unit_perp_x = perp_x / perp_length;
-        let unit_perp_y = perp_y / perp_length;
-
-        // Calculate arc midpoint
-        Point2D {
-            x: chord_mid.x + unit_perp_x * sagitta,
-            y: chord_mid.y + unit_perp_y * sagitta,
-        }
-    }
-
-    fn get_point_on_arc(start: Point2D, end: Point2D, mut angle: f32, t: f32) -> Point2D {
-        // For major arcs, flip the direction to match SVG arc behavior
-        if angle.abs() > 180.0 {
-            angle = -angle;
-        }
-
-        // Calculate chord properties
-        let dx = end.x - start.x;
-        let dy = end.y - start.y;
-        let chord_length = (dx * dx + dy * dy).sqrt();
-        let angle_radians = angle * PI / 180.0;
-
-        // Calculate radius and center
-        let radius = (chord_length / 2.0) / (angle_radians.abs() / 2.0).sin();
-
-        // Find the middle point of the chord
-        let mid_x = (start.x + end.x) / 2.0;
-        let mid_y = (start.y + end.y) / 2.0;
-
-        // Calculate the center point
-        let direction = if angle >= 0.0 { 1.0 } else { -1.0 };
-        let center_distance = (radius * radius - (chord_length * chord_length / 4.0)).sqrt();
-        let normalized_dx = dx / chord_length;
-        let normalized_dy = dy / chord_length;
-        let center_x = mid_x - direction * center_distance * normalized_dy;
-        let center_y = mid_y + direction * center_distance * normalized_dx;
-
-        // Calculate angles relative to center
-        let start_angle = (start.y - center_y).atan2(start.x - center_x);
-        let end_angle = (end.y - center_y).atan2(end.x - center_x);
-
-        // Calculate smaller angle between start and end
-        let mut delta_angle = end_angle - start_angle;
-
-        // Normalize to -2PI to 2PI range
-        delta_angle = delta_angle % (2.0 * PI);
-
-        // Convert to -PI to PI range
-        if delta_angle > PI {
-            delta_angle -= 2.0 * PI;
-        }
-        if delta_angle < -PI {
-            delta_angle += 2.0 * PI;
-        }
-
-        // For major arcs, take the long way around
-        if (angle >= 0.0 && angle > 180.0) || (angle < 0.0 && angle < -180.0) {
-            if delta_angle >= 0.0 {
-                delta_angle -= 2.0 * PI;
-            } else {
-                delta_angle += 2.0 * PI;
-            }
-        }
-
-        // Interpolate the angle
-        let interpolated_angle = start_angle + delta_angle * t;
-
-        // Calculate final point position
-        Point2D {
-            x: center_x + radius * interpolated_angle.cos(),
-            y: center_y + radius * interpolated_angle.sin(),
-        }
-    }
-
-    fn get_arc_length(start: Point2D, end: Point2D, angle_degrees: f32) -> f32 {
-        // Calculate chord length using distance formula
-        let dx = end.x - start.x;
-        let dy = end.y - start.y;
-        let chord_length = (dx * dx + dy * dy).sqrt();
-
-        // Convert angle to radians (using absolute value for the formula)
-        let angle_radians = angle_degrees.abs() * PI / 180.0;
-
-        // Calculate radius using formula: R = (chord length/2) / sin(angle/2)
-        let radius = (chord_length / 2.0) / (angle_radians / 2.0).sin();
-
-        // Calculate arc length using formula: L = R * angle (in radians)
-        radius * angle_radians
-    }
-
-    fn interpolate_arc_points(start: Point2D, end: Point2D, angle: f32, density: f32) -> Vec<Point2D> {
-        let length = Self::get_arc_length(start, end, angle);
-
-        let num_points = (length * density).round() as usize;
-
-        if num_points == 0 {
-            return Vec::new();
-        }
-
-        (1..=num_points)
-            .map(|i| {
-                let t = i as f32 / (num_points + 1) as f32;
-                Self::get_point_on_arc(start, end, angle, t)
-            })
-            .collect()
-    }
-
     fn expand_bbox_to_shape(command: &PathCommand, min: &mut Point2D, max: &mut Point2D) {
         match command {
             PathCommand::MoveTo { position } => {
@@ -1052,12 +1521,12 @@ impl EasyEDAFootprint {
                 max.x = max.x.max(position.x);
                 max.y = max.y.max(position.y);
             }
-            PathCommand::ArcTo { end, rotation: _ } |
-            PathCommand::CenterArcTo { end, rotation: _ } => {
-                min.x = min.x.min(end.x);
-                min.y = min.y.min(end.y);
-                max.x = max.x.max(end.x);
-                max.y = max.y.max(end.y);
+            PathCommand::ArcTo { end: position, rotation: _ } |
+            PathCommand::CenterArcTo { center: position, rotation: _ } => {
+                min.x = min.x.min(position.x);
+                min.y = min.y.min(position.y);
+                max.x = max.x.max(position.x);
+                max.y = max.y.max(position.y);
             }
             PathCommand::Circle { center, radius } => {
                 assert!(*radius >= 0.0, "Circles with negative radius are not supported");
@@ -1070,30 +1539,7 @@ impl EasyEDAFootprint {
             PathCommand::Rectangle { start, width, height, rotation, .. } => {
                 assert!(*width >= 0.0 && *height >= 0.0, "Rectangles with negative sizes are not supported");
 
-                // Convert rotation to radians
-                let rotation_rad = rotation.to_radians();
-                let cos_rot = rotation_rad.cos();
-                let sin_rot = rotation_rad.sin();
-
-                // Calculate all four corners of the rectangle
-                let corners = [
-                    start,  // top-left
-                    &Point2D {  // top-right
-                        x: start.x + width * cos_rot,
-                        y: start.y + width * sin_rot,
-                    },
-                    &Point2D {  // bottom-left
-                        x: start.x - height * sin_rot,
-                        y: start.y + height * cos_rot,
-                    },
-                    &Point2D {  // bottom-right
-                        x: start.x + width * cos_rot - height * sin_rot,
-                        y: start.y + width * sin_rot + height * cos_rot,
-                    }
-                ];
-
-                // Update bounding box to include all corners
-                for corner in corners.iter() {
+                for corner in Self::get_rectangle_corners(*start, *width, *height, *rotation) {
                     min.x = min.x.min(corner.x);
                     min.y = min.y.min(corner.y);
                     max.x = max.x.max(corner.x);
@@ -1114,9 +1560,9 @@ pub struct DocType {
 pub struct Head {
     pub editor_version: String,
     pub import_flag: u32,
-    pub uuid: String,
-    pub source: String,
-    pub title: String,
+    pub uuid: Option<String>,
+    pub source: Option<String>,
+    pub title: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1168,6 +1614,80 @@ pub struct Poly {
     pub attributes: Vec<Attribute>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Region {
+    pub id: String,
+    pub group_id: u32,
+    pub layer_id: u8,
+    pub width: f32,
+    pub rule_types: Vec<u32>,
+    pub path: Value,
+    pub name: Option<String>,
+    pub is_locked: bool,
+
+    pub attributes: Vec<Attribute>,
+}
+
+impl Region {
+    const NO_COMPONENTS: u32 = 2;
+    const NO_VIAS: u32 = 3;
+    const NO_TRACKS: u32 = 5;
+    const NO_FILLS: u32 = 6;
+    const NO_POURS: u32 = 7;
+
+    /// Returns what the region keeps out, or None if it doesn't forbid anything KiCad can express
+    pub fn get_keepout_settings(&self) -> Option<FootprintZoneKeepoutSettings> {
+        let forbids = |rule_type| self.rule_types.contains(&rule_type);
+        let settings = FootprintZoneKeepoutSettings {
+            tracks_allowed: !forbids(Self::NO_TRACKS),
+            vias_allowed: !forbids(Self::NO_VIAS),
+            pads_allowed: !forbids(Self::NO_COMPONENTS),
+            copper_pour_allowed: !forbids(Self::NO_FILLS) && !forbids(Self::NO_POURS),
+            footprints_allowed: !forbids(Self::NO_COMPONENTS),
+        };
+
+        let forbids_anything = !settings.tracks_allowed || !settings.vias_allowed || !settings.pads_allowed
+            || !settings.copper_pour_allowed || !settings.footprints_allowed;
+        Some(settings).filter(|_| forbids_anything)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SolidRegion {
+    pub id: String,
+    pub group_id: u32,
+    pub net: String,
+    pub layer_id: u8,
+    pub path: Value,
+    pub region_type: Option<String>, // "solid" | "cutout" | "npth"
+    pub is_locked: bool,
+
+    pub attributes: Vec<Attribute>,
+}
+
+impl SolidRegion {
+    /// Cutouts keep copper pours out and NPTH regions keep everything out, solid regions are plain fills
+    pub fn get_keepout_settings(&self) -> Option<FootprintZoneKeepoutSettings> {
+        match self.region_type.as_deref() {
+            Some("cutout") => Some(FootprintZoneKeepoutSettings {
+                tracks_allowed: true,
+                vias_allowed: true,
+                pads_allowed: true,
+                copper_pour_allowed: false,
+                footprints_allowed: true,
+            }),
+            Some("npth") => Some(FootprintZoneKeepoutSettings {
+                tracks_allowed: false,
+                vias_allowed: false,
+                pads_allowed: false,
+                copper_pour_allowed: false,
+                footprints_allowed: false,
+            }),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Net {
     pub name: String,
@@ -1192,6 +1712,26 @@ pub struct Rule {
     pub context: Value,
 }
 
+impl Rule {
+    /// Returns the value of a rule that sets a single dimension
+    pub fn get_value(&self) -> Option<f32> {
+        match &self.context {
+            Value::Number(value) => value.as_f64(),
+            Value::Object(context) => ["top", "topExpansion", "value"].iter()
+                .find_map(|key| context.get(*key).and_then(Value::as_f64)),
+            _ => None,
+        }.map(|v| v as f32)
+    }
+}
+
+/// Rule types are compared without spacing and case, as EasyEDA versions spell them differently
+fn normalize_rule_type(rule_type: &str) -> String {
+    rule_type.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+const SOLDER_MASK_EXPANSION_RULES: &[&str] = &["soldermaskexpansion", "maskexpansion"];
+const CLEARANCE_RULES: &[&str] = &["clearance", "safespacing"];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Primitive {
     pub name: String,
@@ -1235,6 +1775,68 @@ pub struct Image {
     pub is_locked: bool,
 }
 
+/// A pad shape that replaces the default one on a range of layers
+#[derive(Debug)]
+pub struct SpecialPadShape {
+    pub start_layer_id: u8,
+    pub end_layer_id: u8,
+    pub shape: Vec<Value>,
+}
+
+impl Pad {
+    /// Decodes the `[start layer, end layer, shape]` entries of the special pad field.
+    /// Returns None if the field holds anything else.
+    pub fn get_special_pad_shapes(&self) -> Option<Vec<SpecialPadShape>> {
+        match &self.special_pad {
+            Some(Value::Array(entries)) => entries.iter()
+                .map(|entry| {
+                    let entry = entry.as_array()?;
+                    Some(SpecialPadShape {
+                        start_layer_id: entry.first()?.as_u64()? as u8,
+                        end_layer_id: entry.get(1)?.as_u64()? as u8,
+                        shape: entry.get(2)?.as_array()?.clone(),
+                    })
+                })
+                .collect(),
+            Some(Value::Null) | None => Some(Vec::new()),
+            Some(_) => None,
+        }
+    }
+
+    /// Older exports lack the teardrop field, newer ones use a flag or a settings object
+    pub fn has_teardrop(&self) -> bool {
+        match &self.teardrop {
+            Some(Value::Bool(enabled)) => *enabled,
+            Some(Value::Number(number)) => number.as_f64().is_some_and(|n| n != 0.0),
+            Some(Value::String(string)) => !string.is_empty() && string != "0",
+            Some(Value::Array(array)) => !array.is_empty(),
+            Some(Value::Object(object)) => !object.is_empty(),
+            Some(Value::Null) | None => false,
+        }
+    }
+}
+
+impl Image {
+    /// Returns the MIME type and base64 payload when the path holds a raster data URI
+    pub fn get_raster_data(&self) -> Option<(&str, &str)> {
+        let uri = self.path.first()?.as_str()?;
+        let (header, payload) = uri.strip_prefix("data:")?.split_once(',')?;
+        let mime_type = header.strip_suffix(";base64")?;
+        Some((mime_type, payload))
+    }
+}
+
+fn get_png_width(payload: &str) -> Option<u32> {
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    let data = STANDARD.decode(payload).ok()?;
+    if !data.starts_with(PNG_SIGNATURE) || data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+
+    Some(u32::from_be_bytes(data.get(16..20)?.try_into().ok()?)).filter(|w| *w > 0)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Pad {
     pub id: String,
@@ -1247,7 +1849,7 @@ pub struct Pad {
     pub rotation: f32,
     pub hole: Option<Value>, // TODO
     pub path: Option<Value>, // TODO
-    pub special_pad: Option<Value>, // TODO
+    pub special_pad: Option<Value>,
     pub hole_offset_y: f32,
     pub hole_offset_x: f32,
     pub hole_rotation: Option<f32>,
@@ -1264,6 +1866,9 @@ pub struct Pad {
     pub spoke_width: Option<f32>,
     pub spoke_angle: Option<f32>,
     pub unused_inner_layers: Option<Value>,
+    pub teardrop: Option<Value>,
+    /// Fields of newer EasyEDA versions that are not interpreted yet
+    pub extra_fields: Vec<Value>,
 
     pub attributes: Vec<Attribute>,
 }
@@ -1346,6 +1951,8 @@ pub enum FootprintProperty {
     PRIMITIVE(Primitive),
     STRING(StringObject),
     IMAGE(Image),
+    REGION(Region),
+    SOLIDREGION(SolidRegion),
     ATTR(Attribute),
     CANVAS(Canvas),
 }
@@ -1365,7 +1972,7 @@ impl FootprintProperty {
         match property_type.as_str() {
             "DOCTYPE" => {
                 if reader.remaining() != 2 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "2", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::DOCTYPE(DocType {
@@ -1375,22 +1982,22 @@ impl FootprintProperty {
             }
             "HEAD" => {
                 if reader.remaining() != 1 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "1", reader.remaining(), line));
                 }
 
                 let parameters = reader.read_value().unwrap();
 
                 Ok(Some(FootprintProperty::HEAD(Head {
-                    editor_version: parameters["editorVersion"].as_str().unwrap().to_string(),
-                    import_flag: parameters["importFlag"].as_u64().unwrap() as u32,
-                    uuid: parameters["uuid"].as_str().unwrap().to_string(),
-                    source: parameters["source"].as_str().unwrap().to_string(),
-                    title: parameters["title"].as_str().unwrap().to_string(),
+                    editor_version: parameters["editorVersion"].as_str().unwrap_or_default().to_string(),
+                    import_flag: parameters["importFlag"].as_u64().unwrap_or(0) as u32,
+                    uuid: parameters["uuid"].as_str().map(|s| s.to_string()),
+                    source: parameters["source"].as_str().map(|s| s.to_string()),
+                    title: parameters["title"].as_str().map(|s| s.to_string()),
                 })))
             }
             "LAYER" => {
                 if reader.remaining() != 8 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "8", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::LAYER(Layer {
@@ -1406,7 +2013,7 @@ impl FootprintProperty {
             }
             "LAYER_PHYS" => {
                 if reader.remaining() != 6 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "6", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::LAYER_PHYS(PhysicalLayer {
@@ -1420,14 +2027,14 @@ impl FootprintProperty {
             }
             "ACTIVE_LAYER" => {
                 if reader.remaining() != 1 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "1", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::ACTIVELAYER(reader.read_u8().unwrap())))
             }
             "FILL" => {
                 if reader.remaining() != 8 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "8", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::FILL(Fill {
@@ -1445,7 +2052,7 @@ impl FootprintProperty {
             }
             "POLY" => {
                 if reader.remaining() != 7 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "7", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::POLY(Poly {
@@ -1462,7 +2069,7 @@ impl FootprintProperty {
             }
             "PAD" => {
                 if reader.remaining() < 21 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "at least 21", reader.remaining(), line));
                 }
 
                 let mut pad = Pad {
@@ -1493,6 +2100,8 @@ impl FootprintProperty {
                     spoke_width: None,
                     spoke_angle: None,
                     unused_inner_layers: None,
+                    teardrop: None,
+                    extra_fields: Vec::new(),
 
                     attributes: Vec::new(),
                 };
@@ -1512,12 +2121,18 @@ impl FootprintProperty {
                 if reader.can_read() {
                     pad.unused_inner_layers = Some(reader.read_value().unwrap());
                 }
+                if reader.can_read() {
+                    pad.teardrop = reader.read_value();
+                }
+                while reader.can_read() {
+                    pad.extra_fields.push(reader.read_value().unwrap());
+                }
 
                 Ok(Some(FootprintProperty::PAD(pad)))
             }
             "VIA" => {
                 if reader.remaining() < 12 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "at least 12", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::VIA(Via {
@@ -1540,7 +2155,7 @@ impl FootprintProperty {
             }
             "NET" => {
                 if reader.remaining() != 7 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "7", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::NET(Net {
@@ -1555,7 +2170,7 @@ impl FootprintProperty {
             }
             "RULE_TEMPLATE" => {
                 if reader.remaining() != 1 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "1", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::RULE_TEMPLATE(RuleTemplate {
@@ -1564,7 +2179,7 @@ impl FootprintProperty {
             }
             "RULE" => {
                 if reader.remaining() != 4 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "4", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::RULE(Rule {
@@ -1576,7 +2191,7 @@ impl FootprintProperty {
             }
             "PRIMITIVE" => {
                 if reader.remaining() != 3 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "3", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::PRIMITIVE(Primitive {
@@ -1587,7 +2202,7 @@ impl FootprintProperty {
             }
             "STRING" => {
                 if reader.remaining() != 17 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "17", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::STRING(StringObject {
@@ -1610,9 +2225,62 @@ impl FootprintProperty {
                     is_locked: reader.read_bool().unwrap(),
                 })))
             }
+            "REGION" => {
+                if reader.remaining() < 6 {
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "at least 6", reader.remaining(), line));
+                }
+
+                let mut region = Region {
+                    id: reader.read_string().unwrap(),
+                    group_id: reader.read_u32().unwrap_or(0),
+                    layer_id: reader.read_u8().unwrap(),
+                    width: reader.read_f32().unwrap_or(0.0),
+                    rule_types: reader.read_value().unwrap().as_array()
+                        .map(|types| types.iter().filter_map(|t| t.as_u64()).map(|t| t as u32).collect())
+                        .unwrap_or_default(),
+                    path: reader.read_value().unwrap(),
+                    name: None,
+                    is_locked: false,
+
+                    attributes: Vec::new(),
+                };
+                if reader.can_read() {
+                    region.name = reader.read_string();
+                }
+                if reader.can_read() {
+                    region.is_locked = reader.read_bool().unwrap_or(false);
+                }
+
+                Ok(Some(FootprintProperty::REGION(region)))
+            }
+            "SOLIDREGION" => {
+                if reader.remaining() < 5 {
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "at least 5", reader.remaining(), line));
+                }
+
+                let mut region = SolidRegion {
+                    id: reader.read_string().unwrap(),
+                    group_id: reader.read_u32().unwrap_or(0),
+                    net: reader.read_string().unwrap_or_default(),
+                    layer_id: reader.read_u8().unwrap(),
+                    path: reader.read_value().unwrap(),
+                    region_type: None,
+                    is_locked: false,
+
+                    attributes: Vec::new(),
+                };
+                if reader.can_read() {
+                    region.region_type = reader.read_string();
+                }
+                if reader.can_read() {
+                    region.is_locked = reader.read_bool().unwrap_or(false);
+                }
+
+                Ok(Some(FootprintProperty::SOLIDREGION(region)))
+            }
             "IMAGE" => {
                 if reader.remaining() != 11 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "11", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::IMAGE(Image {
@@ -1632,7 +2300,7 @@ impl FootprintProperty {
             "FONT" => { Ok(None) }
             "ATTR" => {
                 if reader.remaining() != 21 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "21", reader.remaining(), line));
                 }
 
                 Ok(Some(FootprintProperty::ATTR(Attribute {
@@ -1661,7 +2329,7 @@ impl FootprintProperty {
             }
             "CANVAS" => {
                 if reader.remaining() < 7 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Footprint, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Footprint, &property_type, "at least 7", reader.remaining(), line));
                 }
 
                 let mut canvas = Canvas {
@@ -1701,4 +2369,735 @@ impl FootprintProperty {
             _ => Err(ParserError::InvalidPropertyType(ParserType::Footprint, property_type.to_string())),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kicad::syntax::{KiCadParser, SyntaxItemSerializable};
+    use crate::kicad::model::footprint_library::FootprintAnchor;
+    use itertools::Itertools;
+
+    const RESISTOR_FOOTPRINT: &str = r##"["DOCTYPE","FOOTPRINT","1.8"]
+["HEAD",{"editorVersion":"2.2.32.3","importFlag":0,"uuid":"0a4b4e5c8b1f4c3e9d2a7b6c5d4e3f21","source":"easyeda-pro","title":"R0603"}]
+["CANVAS",0,0,"mil",5,5,5,5,0.5,0.5]
+["LAYER",1,"TOP","Top Layer",3,"#ff0000",1,"#7f0000",1]
+["LAYER",3,"TOP_SILK","Top Silkscreen Layer",3,"#ffcc00",1,"#7f6600",1]
+["PAD","e1",0,"",1,"1",-30,0,0,null,["RECT",40,36,0],[],0,0,0,1,1,null,null,null,null,0]
+["PAD","e2",0,"",1,"2",30,0,0,null,["RECT",40,36,0],[],0,0,0,1,1,null,null,null,null,0]"##;
+
+    #[test]
+    fn mechanical_footprint_is_excluded_from_bom() {
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap().try_into().unwrap();
+        let attributes = footprint.attributes.as_ref().unwrap();
+        assert!(!attributes.exclude_from_bom && !attributes.exclude_from_pos_files);
+
+        let mounting_hole = r##"["DOCTYPE","FOOTPRINT","1.8"]
+["CANVAS",0,0,"mil",5,5,5,5,0.5,0.5]
+["LAYER",12,"MULTI","Multi-Layer",3,"#c0c0c0",1,"#606060",1]
+["FILL","e1",0,"",12,0,0,[["CIRCLE",0,0,60]],0]"##;
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(mounting_hole).unwrap().try_into().unwrap();
+        assert!(footprint.pads.iter().all(|p| matches!(p.pad_type, PadType::NpThruHole)));
+        let attributes = footprint.attributes.as_ref().unwrap();
+        assert!(attributes.exclude_from_bom && attributes.exclude_from_pos_files);
+    }
+
+    #[test]
+    fn kicad_8_footprint_version() {
+        let mut footprint = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap();
+        footprint.kicad_version = KiCadVersion::V8;
+        let footprint: FootprintLibrary = footprint.try_into().unwrap();
+
+        assert!(footprint.version.is_some_and(|v| (20240000..20250000).contains(&v)));
+        assert!(footprint.properties.iter().any(|p| p.key == "Reference"));
+    }
+
+    #[test]
+    fn kicad_7_footprint_version() {
+        let mut footprint = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap();
+        footprint.kicad_version = KiCadVersion::V7;
+
+        // KiCad 7 refuses to open anything newer than its own 20221018 format
+        let mut footprint: FootprintLibrary = footprint.try_into().unwrap();
+        assert_eq!(footprint.version, Some(20221018));
+        assert!(footprint.properties.is_empty());
+        assert!(footprint.texts.iter().any(|t| matches!(t.text_type, FootprintTextType::Reference)));
+
+        // KiCad 7 only knows plain key/value footprint properties
+        footprint.add_hidden_property("Description", "Resistor");
+        let syntax = footprint.serialize();
+        let property = syntax.children.iter().find(|c| c.name == "property").unwrap();
+        assert_eq!(property.arguments.len(), 2);
+        assert!(property.children.is_empty());
+        let output = KiCadParser::stringify_tokens::<FootprintLibrary>(&KiCadParser::generate_tokens(&syntax));
+        assert!(output.contains(r#"(property "Description" "Resistor")"#));
+    }
+
+    #[test]
+    fn parse_head_without_source_and_title() {
+        let line = r#"["HEAD",{"editorVersion":"2.2.32.3","importFlag":0,"uuid":"d1c8a8ef0b2a4cbd8f5f7d1b1c1c5b1a"}]"#;
+        let Some(FootprintProperty::HEAD(head)) = FootprintProperty::parse_line(line).unwrap() else {
+            panic!("Expected a HEAD property");
+        };
+
+        assert_eq!(head.editor_version, "2.2.32.3");
+        assert_eq!(head.uuid.as_deref(), Some("d1c8a8ef0b2a4cbd8f5f7d1b1c1c5b1a"));
+        assert!(head.source.is_none());
+        assert!(head.title.is_none());
+    }
+
+    #[test]
+    fn parse_empty_head() {
+        let Some(FootprintProperty::HEAD(head)) = FootprintProperty::parse_line(r#"["HEAD",{}]"#).unwrap() else {
+            panic!("Expected a HEAD property");
+        };
+
+        assert_eq!(head.editor_version, "");
+        assert_eq!(head.import_flag, 0);
+        assert!(head.uuid.is_none());
+    }
+
+    #[test]
+    fn pad_without_solder_expansion_has_no_mask_margin() {
+        let footprint = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap();
+        let footprint: FootprintLibrary = footprint.try_into().unwrap();
+
+        assert_eq!(footprint.pads.len(), 2);
+        assert!(footprint.pads.iter().all(|p| p.solder_mask_margin.is_none()));
+    }
+
+    #[test]
+    fn pad_with_solder_expansion_has_mask_margin() {
+        let data = RESISTOR_FOOTPRINT.replace(r#"0,0,0,1,1,null,null"#, r#"0,0,0,1,1,4,4"#);
+        let footprint = EasyEDAFootprint::parse(&data).unwrap();
+        let footprint: FootprintLibrary = footprint.try_into().unwrap();
+
+        for pad in footprint.pads.iter() {
+            let margin = pad.solder_mask_margin.unwrap();
+            assert!((margin - 4.0 * 0.0254).abs() < 1e-6);
+        }
+    }
+
+    fn rect_pad_footprint(rotation: f32) -> FootprintLibrary {
+        let data = RESISTOR_FOOTPRINT.replace(
+            r#"["PAD","e1",0,"",1,"1",-30,0,0,null,["RECT",40,36,0]"#,
+            &format!(r#"["PAD","e1",0,"",1,"1",-30,0,{rotation},null,["RECT",80,40,0]"#),
+        );
+        let footprint = EasyEDAFootprint::parse(&data).unwrap();
+        footprint.try_into().unwrap()
+    }
+
+    fn pad_extents(pad: &FootprintPad) -> (f32, f32) {
+        let angle = pad.position.angle.unwrap_or(0.0).to_radians();
+        let (sin, cos) = (angle.sin().abs(), angle.cos().abs());
+        (pad.size.x * cos + pad.size.y * sin, pad.size.x * sin + pad.size.y * cos)
+    }
+
+    #[test]
+    fn rect_pad_without_rotation() {
+        let footprint = rect_pad_footprint(0.0);
+        let pad = footprint.pads.iter().find(|p| p.number == "1").unwrap();
+
+        assert!(matches!(pad.pad_shape, PadShape::Rect));
+        assert_eq!(pad.position.angle, Some(0.0));
+        let (width, height) = pad_extents(pad);
+        assert!((width - 80.0 * 0.0254).abs() < 1e-4);
+        assert!((height - 40.0 * 0.0254).abs() < 1e-4);
+    }
+
+    #[test]
+    fn rect_pad_rotated_by_90_degrees() {
+        let footprint = rect_pad_footprint(90.0);
+        let pad = footprint.pads.iter().find(|p| p.number == "1").unwrap();
+
+        assert!(matches!(pad.pad_shape, PadShape::Rect));
+        assert_eq!(pad.position.angle, Some(90.0));
+        assert!((pad.size.x - 80.0 * 0.0254).abs() < 1e-4);
+        assert!((pad.size.y - 40.0 * 0.0254).abs() < 1e-4);
+        let (width, height) = pad_extents(pad);
+        assert!((width - 40.0 * 0.0254).abs() < 1e-4);
+        assert!((height - 80.0 * 0.0254).abs() < 1e-4);
+    }
+
+    fn slot_pad_footprint(hole_rotation: f32) -> FootprintLibrary {
+        let data = RESISTOR_FOOTPRINT
+            .replace(r##"["LAYER",3,"##, r##"["LAYER",12,"MULTI","Multi-Layer",3,"#c0c0c0",1,"#606060",1]
+["LAYER",3,"##)
+            .replace(
+                r#"["PAD","e1",0,"",1,"1",-30,0,0,null,["RECT",40,36,0],[],0,0,0,1,1"#,
+                &format!(r#"["PAD","e1",0,"",12,"1",-30,0,0,["SLOT",20,10],["RECT",80,40,0],[],0,0,{hole_rotation},1,1"#),
+            );
+        EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn slot_rotation_keeps_the_pad_orientation() {
+        for (hole_rotation, swapped) in [(0.0, false), (-90.0, true), (270.0, true), (-180.0, false), (-30.0, false), (60.0, true)] {
+            let footprint = slot_pad_footprint(hole_rotation);
+            let pad = footprint.pads.iter().find(|p| p.number == "1").unwrap();
+            let drill = pad.drill.as_ref().unwrap();
+
+            assert_eq!(pad.position.angle, Some(0.0), "hole rotation {hole_rotation}");
+            assert_eq!((pad.size.x, pad.size.y), (80.0 * 0.0254, 40.0 * 0.0254));
+            let expected = if swapped { (10.0, 20.0) } else { (20.0, 10.0) };
+            assert_eq!((drill.diameter, drill.width.unwrap()), (expected.0 * 0.0254, expected.1 * 0.0254), "hole rotation {hole_rotation}");
+        }
+    }
+
+    #[test]
+    fn reference_and_value_clear_the_pads() {
+        let footprint = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap();
+        let footprint: FootprintLibrary = footprint.try_into().unwrap();
+
+        let (pads_top, pads_bottom) = footprint.get_layer_extents_y(&[PcbLayer::FCu]).unwrap();
+        let reference = footprint.properties.iter().find(|p| p.key == "Reference").unwrap();
+        let value = footprint.properties.iter().find(|p| p.key == "Value").unwrap();
+        assert!(reference.position.y < pads_top);
+        assert!(value.position.y > pads_bottom);
+    }
+
+    #[test]
+    fn part_number_and_datasheet_properties() {
+        let mut footprint = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap();
+        footprint.part_number = Some("C25804".into());
+        footprint.datasheet = Some("https://example.com/datasheet.pdf".into());
+        let footprint: FootprintLibrary = footprint.try_into().unwrap();
+
+        let lcsc = footprint.properties.iter().find(|p| p.key == "LCSC").unwrap();
+        assert_eq!(lcsc.value.as_deref(), Some("C25804"));
+        let datasheet = footprint.properties.iter().find(|p| p.key == "Datasheet").unwrap();
+        assert_eq!(datasheet.value.as_deref(), Some("https://example.com/datasheet.pdf"));
+    }
+
+    fn courtyard_layers(footprint: &FootprintLibrary) -> Vec<PcbLayer> {
+        footprint.rectangles.iter()
+            .map(|r| r.layer)
+            .filter(|l| matches!(l, PcbLayer::FCrtYd | PcbLayer::BCrtYd))
+            .collect()
+    }
+
+    #[test]
+    fn smd_footprint_has_front_courtyard() {
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap().try_into().unwrap();
+        assert_eq!(courtyard_layers(&footprint), vec![PcbLayer::FCrtYd]);
+
+        let courtyard = footprint.rectangles.iter().find(|r| r.layer == PcbLayer::FCrtYd).unwrap();
+        let (pads_min_x, _, pads_max_x, _) = footprint.get_layer_bounds(&[PcbLayer::FCu]).unwrap();
+        assert!(courtyard.start.x <= pads_min_x - COURTYARD_CLEARANCE);
+        assert!(courtyard.end.x >= pads_max_x + COURTYARD_CLEARANCE);
+    }
+
+    #[test]
+    fn tht_footprint_has_courtyards_on_both_sides() {
+        let data = RESISTOR_FOOTPRINT
+            .replace(r##"["LAYER",3,"##, r##"["LAYER",12,"MULTI","Multi-Layer",3,"#c0c0c0",1,"#606060",1]
+["LAYER",3,"##)
+            .replace(r#"0,"",1,"1""#, r#"0,"",12,"1""#)
+            .replace(r#"0,"",1,"2""#, r#"0,"",12,"2""#)
+            .replace(r#"0,null,["RECT",40,36,0]"#, r#"0,["ROUND",20,20],["RECT",40,36,0]"#);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        assert!(matches!(footprint.attributes.as_ref().unwrap().footprint_type, FootprintType::ThroughHole));
+        assert_eq!(courtyard_layers(&footprint), vec![PcbLayer::FCrtYd, PcbLayer::BCrtYd]);
+    }
+
+    #[test]
+    fn copper_trace_becomes_copper_lines() {
+        let data = format!("{}\n{}", RESISTOR_FOOTPRINT, r#"["POLY","e3",0,"",1,10,[0,0,"L",50,0,50,50],0]"#);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        assert!(footprint.polygons.iter().all(|p| p.layer != PcbLayer::FCu));
+        let traces = footprint.lines.iter().filter(|l| l.layer == PcbLayer::FCu).collect::<Vec<_>>();
+        assert_eq!(traces.len(), 2);
+        for trace in traces {
+            assert!((trace.width.unwrap() - 10.0 * 0.0254).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn curved_copper_trace_keeps_its_arc() {
+        let data = format!("{}\n{}", RESISTOR_FOOTPRINT, r#"["POLY","e3",0,"",1,10,[0,0,"L",50,0,"ARC",90,100,50],0]"#);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        assert!(footprint.polygons.iter().all(|p| p.layer != PcbLayer::FCu));
+        assert_eq!(footprint.lines.iter().filter(|l| l.layer == PcbLayer::FCu).count(), 1);
+        let arcs = footprint.arcs.iter().filter(|a| a.layer == PcbLayer::FCu).collect::<Vec<_>>();
+        assert_eq!(arcs.len(), 1);
+
+        // The arc continues from the end of the straight segment and bulges away from its chord
+        let arc = arcs[0];
+        let mid = arc.mid.as_ref().unwrap();
+        let chord_mid = ((arc.start.x + arc.end.x) / 2.0, (arc.start.y + arc.end.y) / 2.0);
+        assert!((arc.start.x - 50.0 * 0.0254).abs() < 1e-4 && arc.start.y.abs() < 1e-4);
+        assert!((arc.end.x - 100.0 * 0.0254).abs() < 1e-4 && (arc.end.y + 50.0 * 0.0254).abs() < 1e-4);
+        assert!(((mid.x - chord_mid.0).powi(2) + (mid.y - chord_mid.1).powi(2)).sqrt() > 0.1);
+    }
+
+    #[test]
+    fn center_arc_rotates_around_its_center() {
+        let data = format!("{}\n{}", RESISTOR_FOOTPRINT, r#"["POLY","e3",0,"",3,10,[0,0,"CARC",90,100,0],0]"#);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        let arcs = footprint.arcs.iter().filter(|a| a.layer == PcbLayer::FSilkS).collect::<Vec<_>>();
+        assert_eq!(arcs.len(), 1);
+
+        // A quarter turn counter-clockwise around (100, 0), with the Y axis flipped
+        let arc = arcs[0];
+        let mid = arc.mid.as_ref().unwrap();
+        let offset = 100.0 * (1.0 - std::f32::consts::FRAC_1_SQRT_2);
+        assert!(arc.start.x.abs() < 1e-4 && arc.start.y.abs() < 1e-4);
+        assert!((arc.end.x - 100.0 * 0.0254).abs() < 1e-4 && (arc.end.y - 100.0 * 0.0254).abs() < 1e-4);
+        assert!((mid.x - offset * 0.0254).abs() < 1e-4 && (mid.y - (100.0 - offset) * 0.0254).abs() < 1e-4);
+    }
+
+    #[test]
+    fn interpolated_arc_has_no_coincident_points() {
+        // The line after the arc starts where the arc ends, and the tiny arc collapses into its end point
+        let data = format!("{}\n{}", RESISTOR_FOOTPRINT, r#"["POLY","e3",0,"",3,10,[0,0,"L",100,0,"ARC",180,100,100,"L",100,100,"ARC",90,100.001,100.001,"L",0,100,0,0],0]"#);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        let polygon = footprint.polygons.iter().find(|p| p.layer == PcbLayer::FSilkS).unwrap();
+        assert!(polygon.points.len() > 6);
+        for segment in polygon.points.windows(2) {
+            assert!((segment[1].x - segment[0].x).hypot(segment[1].y - segment[0].y) >= POINT_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn rotated_rectangle_becomes_polygon() {
+        let data = format!("{}\n{}", RESISTOR_FOOTPRINT, r#"["POLY","e3",0,"",3,10,["R",0,0,100,50,90,0],0]"#);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        let polygon = footprint.polygons.iter().find(|p| p.layer == PcbLayer::FSilkS).unwrap();
+        let corners = polygon.points.iter().map(|p| (p.x / 0.0254, p.y / 0.0254)).collect::<Vec<_>>();
+        let expected = [(0.0, 0.0), (0.0, -100.0), (50.0, -100.0), (50.0, 0.0)];
+        assert_eq!(corners.len(), expected.len());
+        for (corner, expected) in corners.iter().zip(expected) {
+            assert!((corner.0 - expected.0).abs() < 1e-3 && (corner.1 - expected.1).abs() < 1e-3, "{:?} != {:?}", corner, expected);
+        }
+    }
+
+    #[test]
+    fn special_pad_shape_of_the_pad_layer_is_applied() {
+        let data = RESISTOR_FOOTPRINT.replacen(r#"["RECT",40,36,0],[]"#, r#"["RECT",40,36,0],[[1,1,["ELLIPSE",50,50]]]"#, 1);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        let pad = footprint.pads.iter().find(|p| p.number == "1").unwrap();
+        assert!(matches!(pad.pad_shape, PadShape::Oval));
+        assert!((pad.size.x - 50.0 * 0.0254).abs() < 1e-4);
+    }
+
+    #[test]
+    fn malformed_special_pad_is_not_decoded() {
+        let data = RESISTOR_FOOTPRINT.replacen(r#"["RECT",40,36,0],[]"#, r#"["RECT",40,36,0],[[1,"TOP"]]"#, 1);
+        let easyeda_footprint = EasyEDAFootprint::parse(&data).unwrap();
+        let pad = easyeda_footprint.pads.values().find(|p| p.num == "1").unwrap();
+        assert!(pad.get_special_pad_shapes().is_none());
+
+        // The default shape is still converted
+        let footprint: FootprintLibrary = easyeda_footprint.try_into().unwrap();
+        assert!(matches!(footprint.pads.iter().find(|p| p.number == "1").unwrap().pad_shape, PadShape::Rect));
+    }
+
+    #[test]
+    fn copper_fill_with_net_becomes_zone() {
+        let data = format!("{}\n{}", RESISTOR_FOOTPRINT, r#"["FILL","e4",0,"GND",1,0,0,[[-20,-20,"L",20,-20,20,20,-20,20]],0]"#);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        assert_eq!(footprint.zones.len(), 1);
+        let zone = &footprint.zones[0];
+        assert_eq!(zone.net_name, "GND");
+        assert_eq!(zone.layer, vec![PcbLayer::FCu]);
+        assert_eq!(zone.coordinate_points.points.len(), 4);
+        assert!(footprint.polygons.iter().all(|p| p.layer != PcbLayer::FCu));
+
+        let tokens = KiCadParser::generate_tokens(&footprint.serialize());
+        let output = KiCadParser::stringify_tokens::<FootprintLibrary>(&tokens);
+        let reparsed: FootprintLibrary = SyntaxItemSerializable::deserialize(&KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&output)));
+        assert_eq!(reparsed.zones.len(), 1);
+    }
+
+    #[test]
+    fn solid_regions_become_zones() {
+        let data = format!("{}\n{}\n{}", RESISTOR_FOOTPRINT,
+            r#"["SOLIDREGION","e5",0,"",1,[[-20,-20,"L",20,-20,20,20,-20,20]],"cutout",0]"#,
+            r#"["SOLIDREGION","e6",0,"GND",1,[[-20,-20,"L",20,-20,20,20,-20,20]],"solid",0]"#);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        assert_eq!(footprint.zones.len(), 2);
+        let keepout = footprint.zones.iter().find(|z| z.keepout_settings.is_some()).unwrap();
+        let settings = keepout.keepout_settings.as_ref().unwrap();
+        assert!(!settings.copper_pour_allowed && settings.tracks_allowed);
+        assert_eq!(keepout.layer, vec![PcbLayer::FCu]);
+
+        let filled = footprint.zones.iter().find(|z| z.keepout_settings.is_none()).unwrap();
+        assert_eq!(filled.net_name, "GND");
+        assert_eq!(filled.coordinate_points.points.len(), 4);
+    }
+
+    #[test]
+    fn keepout_region_becomes_keepout_zone() {
+        let data = format!("{}\n{}\n{}", RESISTOR_FOOTPRINT,
+            r#"["REGION","e5",0,1,0,[3,5],[[-20,-20,"L",20,-20,20,20,-20,20]],"",0]"#,
+            r#"["REGION","e6",0,1,0,[],[[-20,-20,"L",20,-20,20,20,-20,20]],"",0]"#);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        // The region without any rule types doesn't keep anything out
+        assert_eq!(footprint.zones.len(), 1);
+        let zone = &footprint.zones[0];
+        assert_eq!(zone.layer, vec![PcbLayer::FCu]);
+        let settings = zone.keepout_settings.as_ref().unwrap();
+        assert!(!settings.tracks_allowed && !settings.vias_allowed);
+        assert!(settings.pads_allowed && settings.copper_pour_allowed && settings.footprints_allowed);
+
+        let tokens = KiCadParser::generate_tokens(&footprint.serialize());
+        let output = KiCadParser::stringify_tokens::<FootprintLibrary>(&tokens);
+        let reparsed: FootprintLibrary = SyntaxItemSerializable::deserialize(&KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&output)));
+        assert!(reparsed.zones[0].keepout_settings.as_ref().is_some_and(|s| !s.tracks_allowed));
+    }
+
+    #[test]
+    fn copper_fill_without_net_stays_polygon() {
+        let data = format!("{}\n{}", RESISTOR_FOOTPRINT, r#"["FILL","e4",0,"",1,0,0,[[-20,-20,"L",20,-20,20,20,-20,20]],0]"#);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        assert!(footprint.zones.is_empty());
+        assert!(footprint.polygons.iter().any(|p| p.layer == PcbLayer::FCu));
+    }
+
+    #[test]
+    fn recenter_on_center_of_pads() {
+        let mut footprint: FootprintLibrary = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap().try_into().unwrap();
+        footprint.translate(1.0, 2.0);
+        footprint.recenter(FootprintAnchor::CenterOfPads);
+
+        let pad_x = footprint.pads.iter().map(|p| p.position.x).collect::<Vec<_>>();
+        assert!((pad_x[0] + pad_x[1]).abs() < 1e-4);
+        assert!(footprint.pads.iter().all(|p| p.position.y.abs() < 1e-4));
+    }
+
+    #[test]
+    fn recenter_on_pad_1() {
+        let mut footprint: FootprintLibrary = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap().try_into().unwrap();
+        let pad_1 = footprint.pads.iter().find(|p| p.number == "1").unwrap().position.x;
+        let pad_2 = footprint.pads.iter().find(|p| p.number == "2").unwrap().position.x;
+        footprint.recenter(FootprintAnchor::Pad1);
+
+        let pad_1_position = &footprint.pads.iter().find(|p| p.number == "1").unwrap().position;
+        assert!(pad_1_position.x.abs() < 1e-4 && pad_1_position.y.abs() < 1e-4);
+        let pad_2_x = footprint.pads.iter().find(|p| p.number == "2").unwrap().position.x;
+        assert!((pad_2_x - (pad_2 - pad_1)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn poly_pad_anchor_fits_inside_the_shape() {
+        let data = RESISTOR_FOOTPRINT.replace(
+            r#"["RECT",40,36,0],[],0,0,0,1,1,null,null,null,null,0]
+["PAD","e2""#,
+            r#"["POLY",[-50,-20,"L",-10,-20,-10,20,-50,20]],[],0,0,0,1,1,null,null,null,null,0]
+["PAD","e2""#,
+        );
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        let pad = footprint.pads.iter().find(|p| p.number == "1").unwrap();
+        assert!(matches!(pad.pad_shape, PadShape::Custom));
+        assert!(pad.size.x > 0.9 && pad.size.x <= 1.0161);
+        assert!(pad.size.y > 0.9 && pad.size.y <= 1.0161);
+    }
+
+    #[test]
+    fn short_pad_array_reports_lengths_and_line() {
+        let line = r#"["PAD","e1",0,"",1,"1",-30,0,0]"#;
+        let error = FootprintProperty::parse_line(line).unwrap_err().to_string();
+
+        assert!(error.contains("PAD"));
+        assert!(error.contains("expected at least 21 parameters, found 8"));
+        assert!(error.contains(line));
+    }
+
+    #[test]
+    fn long_lines_are_truncated_in_errors() {
+        let line = format!(r#"["PAD","e1",0,"{}"]"#, "x".repeat(200));
+        let error = FootprintProperty::parse_line(&line).unwrap_err().to_string();
+
+        assert!(error.contains(&line[..80]));
+        assert!(!error.contains(&line));
+    }
+
+    #[test]
+    fn empty_footprint_data_is_rejected() {
+        let error = EasyEDAFootprint::parse(" \n ").unwrap_err();
+        assert_eq!(error.to_string(), "The EasyEDA Footprint data is empty");
+    }
+
+    #[test]
+    fn footprint_without_canvas_is_rejected() {
+        let error = EasyEDAFootprint::parse(r#"["DOCTYPE","FOOTPRINT","1.8"]"#).unwrap_err();
+        assert!(error.to_string().contains("CANVAS"));
+    }
+
+    #[test]
+    fn repeated_conversions_get_the_same_uuids() {
+        let pads = (1..=12)
+            .map(|n| format!(r#"["PAD","e{n}",0,"",1,"{n}",{},0,0,null,["RECT",20,20,0],[],0,0,0,1,1,null,null,null,null,0]"#, n * 40))
+            .join("\n");
+        let data = format!("{}\n{pads}", RESISTOR_FOOTPRINT.lines().take(5).join("\n"));
+        let convert = |description: Option<&str>| {
+            let mut footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+            if let Some(description) = description {
+                footprint.add_hidden_property("Description", description);
+            }
+            footprint.assign_deterministic_uuids("C25804");
+            footprint
+        };
+
+        let (first, second) = (convert(None), convert(Some("Resistor")));
+        let pad_uuids = |footprint: &FootprintLibrary| footprint.pads.iter().map(|p| (p.number.clone(), p.uuid.clone().unwrap())).collect_vec();
+        assert_eq!(pad_uuids(&first), pad_uuids(&second));
+        assert_eq!(pad_uuids(&first).iter().map(|(_, uuid)| uuid).unique().count(), 12);
+        assert_eq!(first.properties.iter().map(|p| &p.uuid).collect_vec(), second.properties.iter().take(first.properties.len()).map(|p| &p.uuid).collect_vec());
+    }
+
+    #[test]
+    fn card_edge_pad_becomes_connect_pad() {
+        // The paste expansion closes the whole paste opening of the 40x36 mil pad
+        let data = RESISTOR_FOOTPRINT.replace(
+            r#"["PAD","e1",0,"",1,"1",-30,0,0,null,["RECT",40,36,0],[],0,0,0,1,1,null,null,null,null,0]"#,
+            r#"["PAD","e1",0,"",1,"1",-30,0,0,null,["RECT",40,36,0],[],0,0,0,1,1,0,0,-20,-20,0]"#,
+        );
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        let pad = footprint.pads.iter().find(|p| p.number == "1").unwrap();
+        assert!(matches!(pad.pad_type, PadType::Connect));
+        assert!(!pad.layers.contains(&PcbLayer::FPaste));
+        assert!(pad.layers.contains(&PcbLayer::FMask));
+
+        let pad = footprint.pads.iter().find(|p| p.number == "2").unwrap();
+        assert!(matches!(pad.pad_type, PadType::Smd));
+    }
+
+    #[test]
+    fn bottom_card_edge_pad_uses_the_bottom_paste_expansion() {
+        let data = RESISTOR_FOOTPRINT
+            .replace(r##"["LAYER",3,"##, r##"["LAYER",2,"BOTTOM","Bottom Layer",3,"#0000ff",1,"#00007f",1]
+["LAYER",3,"##)
+            .replace(
+                r#"["PAD","e1",0,"",1,"1",-30,0,0,null,["RECT",40,36,0],[],0,0,0,1,1,null,null,null,null,0]"#,
+                r#"["PAD","e1",0,"",2,"1",-30,0,0,null,["RECT",40,36,0],[],0,0,0,1,1,0,0,0,-20,0]"#,
+            )
+            .replace(
+                r#"["PAD","e2",0,"",1,"2",30,0,0,null,["RECT",40,36,0],[],0,0,0,1,1,null,null,null,null,0]"#,
+                r#"["PAD","e2",0,"",2,"2",30,0,0,null,["RECT",40,36,0],[],0,0,0,1,1,0,0,-20,0,0]"#,
+            );
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        let pad = footprint.pads.iter().find(|p| p.number == "1").unwrap();
+        assert!(matches!(pad.pad_type, PadType::Connect));
+        assert!(pad.layers.contains(&PcbLayer::BCu));
+
+        // Only the top side of this bottom pad has its paste opening closed
+        let pad = footprint.pads.iter().find(|p| p.number == "2").unwrap();
+        assert!(matches!(pad.pad_type, PadType::Smd));
+    }
+
+    #[test]
+    fn tags_are_generated_from_package() {
+        let mut footprint = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap();
+        footprint.package = Some("0603".into());
+        let footprint: FootprintLibrary = footprint.try_into().unwrap();
+        assert_eq!(footprint.tags.as_deref(), Some("0603 SMD"));
+
+        // Without a package the title of the EasyEDA footprint is used
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap().try_into().unwrap();
+        assert_eq!(footprint.tags.as_deref(), Some("R0603 SMD"));
+    }
+
+    const RASTER_IMAGE_LINE: &str = r##"["IMAGE","e3",0,3,-50,50,100,100,0,0,["data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg=="],0]"##;
+
+    #[test]
+    fn raster_image_is_embedded_as_bitmap() {
+        let data = format!("{}\n{}", RESISTOR_FOOTPRINT, RASTER_IMAGE_LINE);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        assert_eq!(footprint.images.len(), 1);
+        let image = &footprint.images[0];
+        assert_eq!(image.layer, PcbLayer::DwgsUser);
+        assert!(image.data.starts_with("iVBORw0KGgo"));
+        assert!(image.position.x.abs() < 1e-4 && image.position.y.abs() < 1e-4);
+        // A single pixel at 300 PPI stretched over 100 mil
+        assert!((image.scale.unwrap() - 30.0).abs() < 1e-3);
+
+        let serialized = KiCadParser::generate_tokens(&footprint.serialize());
+        let serialized = KiCadParser::stringify_tokens::<FootprintLibrary>(&serialized);
+        assert!(serialized.contains("(image"));
+        assert!(serialized.contains("\"Dwgs.User\""));
+    }
+
+    #[test]
+    fn rotated_raster_image_is_centered_on_the_rotated_area() {
+        let data = format!("{}\n{}", RESISTOR_FOOTPRINT, RASTER_IMAGE_LINE.replace("100,100,0,0,", "100,100,90,0,"));
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        // Turning counter-clockwise around the top-left corner moves the image above its anchor
+        let image = &footprint.images[0];
+        assert!(image.position.x.abs() < 1e-4 && (image.position.y + 100.0 * 0.0254).abs() < 1e-4);
+    }
+
+    #[test]
+    fn raster_image_is_skipped_on_request() {
+        let data = format!("{}\n{}", RESISTOR_FOOTPRINT, RASTER_IMAGE_LINE);
+        let mut footprint = EasyEDAFootprint::parse(&data).unwrap();
+        footprint.raster_images = RasterImageMode::Skip;
+        let footprint: FootprintLibrary = footprint.try_into().unwrap();
+
+        assert!(footprint.images.is_empty());
+    }
+
+    #[test]
+    fn mirrored_text_is_placed_on_the_back() {
+        let data = format!("{}\n{}\n{}\n{}",
+            RESISTOR_FOOTPRINT,
+            r##"["STRING","e4",0,3,0,40,"TOP","default",20,2,0,0,1,0,0,0,0,0]"##,
+            r##"["STRING","e5",0,3,0,-40,"BACK","default",20,2,0,0,1,0,0,0,1,0]"##,
+            r##"["ATTR","e6",0,"",3,0,60,"Designator","R1",0,1,"default",20,2,0,0,1,0,0,0,1,0]"##,
+        );
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+
+        let top = footprint.texts.iter().find(|t| t.text == "TOP").unwrap();
+        assert_eq!(top.layer, PcbLayer::FSilkS);
+        assert!(!top.effects.justify.mirror);
+
+        let back = footprint.texts.iter().find(|t| t.text == "BACK").unwrap();
+        assert_eq!(back.layer, PcbLayer::BSilkS);
+        assert!(back.effects.justify.mirror);
+
+        let reference = footprint.properties.iter().find(|p| p.key == "Reference").unwrap();
+        assert_eq!(reference.layer, PcbLayer::BSilkS);
+        assert!(reference.effects.justify.mirror);
+    }
+
+    #[test]
+    fn paste_ratio_is_set_on_smd_pads() {
+        let mut footprint = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap();
+        footprint.paste_ratio = Some(-0.1);
+        let footprint: FootprintLibrary = footprint.try_into().unwrap();
+
+        assert_eq!(footprint.pads.len(), 2);
+        assert!(footprint.pads.iter().all(|p| p.solder_paste_margin_ratio == Some(-0.1)));
+    }
+
+    #[test]
+    fn locked_state_is_preserved() {
+        let data = format!("{}\n{}",
+            RESISTOR_FOOTPRINT.replace(r#"[],0,0,0,1,1,null,null,null,null,0]"#, r#"[],0,0,0,1,1,null,null,null,null,1]"#),
+            r##"["POLY","e7",0,"",3,10,[-50,40,"L",50,40],1]"##,
+        );
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&data).unwrap().try_into().unwrap();
+        assert!(footprint.pads.iter().all(|p| p.locked));
+        assert!(footprint.lines.iter().all(|l| l.locked));
+
+        // The locked flag survives writing and reading the footprint back
+        let tokens = KiCadParser::generate_tokens(&footprint.serialize());
+        let output = KiCadParser::stringify_tokens::<FootprintLibrary>(&tokens);
+        let footprint = FootprintLibrary::deserialize(&KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&output)));
+        assert_eq!(footprint.pads.len(), 2);
+        assert!(footprint.pads.iter().all(|p| p.locked));
+        assert_eq!(footprint.lines.len(), 1);
+        assert!(footprint.lines[0].locked);
+    }
+
+    #[test]
+    fn footprint_solder_mask_expansion_is_applied() {
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap().try_into().unwrap();
+        assert_eq!(footprint.solder_mask_margin, None);
+
+        let source = format!("{RESISTOR_FOOTPRINT}\n{}", r##"["ATTR","e5",0,"",3,null,null,"Solder Mask Expansion","2",0,0,"default",45,6,0,0,3,0,0,0,0,0]"##);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&source).unwrap().try_into().unwrap();
+        assert!((footprint.solder_mask_margin.unwrap() - 0.0508).abs() < 1e-6);
+
+        let source = format!("{RESISTOR_FOOTPRINT}\n{}", r##"["RULE","SolderMaskExpansion","Default",1,{"top":4,"bottom":4}]"##);
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&source).unwrap().try_into().unwrap();
+        assert!((footprint.solder_mask_margin.unwrap() - 0.1016).abs() < 1e-6);
+    }
+
+    #[test]
+    fn design_rules_are_converted_or_reported() {
+        let source = format!("{RESISTOR_FOOTPRINT}\n{}\n{}\n{}",
+            r##"["RULE_TEMPLATE","Default"]"##,
+            r##"["RULE","Safe Spacing","Default",1,{"value":8}]"##,
+            r##"["RULE","Track Width","Power",0,{"min":10,"max":40}]"##);
+        let footprint = EasyEDAFootprint::parse(&source).unwrap();
+
+        let unmapped_rules = footprint.describe_unmapped_rules().unwrap();
+        assert!(unmapped_rules.contains("Track Width 'Power'"));
+        assert!(!unmapped_rules.contains("Safe Spacing"));
+
+        let footprint: FootprintLibrary = footprint.try_into().unwrap();
+        assert!((footprint.clearance.unwrap() - 0.2032).abs() < 1e-6);
+
+        let footprint = EasyEDAFootprint::parse(RESISTOR_FOOTPRINT).unwrap();
+        assert!(footprint.describe_unmapped_rules().is_none());
+    }
+
+    #[test]
+    fn inner_layer_names_are_recognized() {
+        assert_eq!(parse_inner_layer_index("Inner1"), Some(1));
+        assert_eq!(parse_inner_layer_index("signal 12"), Some(12));
+        assert_eq!(parse_inner_layer_index("In3"), Some(3));
+        assert_eq!(parse_inner_layer_index("L2"), Some(1));
+        assert_eq!(parse_inner_layer_index("Top"), None);
+
+        assert_eq!(parse_inner_layer_index("Inner30").and_then(PcbLayer::inner_copper), Some(PcbLayer::In30Cu));
+        assert_eq!(parse_inner_layer_index("Inner31").and_then(PcbLayer::inner_copper), None);
+        assert_eq!(parse_inner_layer_index("L1").and_then(PcbLayer::inner_copper), None);
+    }
+
+    #[test]
+    fn trapezoid_pad_is_detected() {
+        // 40 mil wide, the left edge is 40 mil tall and the right edge 20 mil
+        let source = RESISTOR_FOOTPRINT.replacen(
+            r#"["RECT",40,36,0]"#,
+            r#"["POLY",[-50,20,"L",-10,10,-10,-10,-50,-20]]"#,
+            1,
+        );
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(&source).unwrap().try_into().unwrap();
+        let pad = footprint.pads.iter().find(|p| p.number == "1").unwrap();
+        assert!(matches!(pad.pad_shape, PadShape::Trapezoid));
+        assert!(pad.primitives.is_none());
+        assert!((pad.size.x - 1.016).abs() < 1e-4 && (pad.size.y - 0.762).abs() < 1e-4);
+        let rect_delta = pad.rect_delta.as_ref().unwrap();
+        assert!((rect_delta.x - 0.254).abs() < 1e-4 && rect_delta.y.abs() < 1e-4);
+
+        let tokens = KiCadParser::generate_tokens(&footprint.serialize());
+        let output = KiCadParser::stringify_tokens::<FootprintLibrary>(&tokens);
+        assert!(output.contains("(rect_delta 0.254 0)"));
+    }
+
+    #[test]
+    fn pad_teardrop_is_emitted_when_supported() {
+        let source = RESISTOR_FOOTPRINT.replace(
+            r#"["RECT",40,36,0],[],0,0,0,1,1,null,null,null,null,0]"#,
+            r#"["RECT",40,36,0],[],0,0,0,1,1,null,null,null,null,0,null,null,null,null,null,true,"reserved"]"#,
+        );
+        let footprint = EasyEDAFootprint::parse(&source).unwrap();
+        let pad = footprint.pads.values().next().unwrap();
+        assert!(pad.has_teardrop());
+        assert_eq!(pad.extra_fields, vec![Value::String("reserved".into())]);
+
+        let mut v8_footprint = footprint;
+        v8_footprint.kicad_version = KiCadVersion::V8;
+        let v8_footprint: FootprintLibrary = v8_footprint.try_into().unwrap();
+        assert!(v8_footprint.pads.iter().all(|p| p.teardrops == Some(PadTeardrops::default())));
+
+        let tokens = KiCadParser::generate_tokens(&v8_footprint.serialize());
+        let output = KiCadParser::stringify_tokens::<FootprintLibrary>(&tokens);
+        assert!(output.contains("(teardrops"));
+        let parsed = FootprintLibrary::deserialize(&KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&output)));
+        assert_eq!(parsed.pads[0].teardrops, Some(PadTeardrops::default()));
+
+        let mut v7_footprint = EasyEDAFootprint::parse(&source).unwrap();
+        v7_footprint.kicad_version = KiCadVersion::V7;
+        let v7_footprint: FootprintLibrary = v7_footprint.try_into().unwrap();
+        assert!(v7_footprint.pads.iter().all(|p| p.teardrops.is_none()));
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
 use crate::kicad::model::footprint_library::Scalar2D;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -12,7 +13,396 @@ impl Point2D {
         Point2D { x, y }
     }
 
-    pub fn to_scalar_2d(&self, identifier: &str) -> Scalar2D {
-        Scalar2D::new(identifier, self.x, self.y)
+    pub fn to_scalar_2d(&self, constructor: fn(f32, f32) -> Scalar2D) -> Scalar2D {
+        constructor(self.x, self.y)
     }
-}
\ No newline at end of file
+
+    /// Rotates the point around the origin, positive angles (in degrees) go from the +X towards the +Y axis
+    pub fn rotate(&self, angle: f32) -> Point2D {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Point2D {
+            x: self.x * cos - self.y * sin,
+            y: self.x * sin + self.y * cos,
+        }
+    }
+
+    /// Mirrors the point across the Y axis
+    pub fn mirror_x(&self) -> Point2D {
+        Point2D { x: -self.x, y: self.y }
+    }
+}
+
+pub fn get_arc_mid_point(start: Point2D, end: Point2D, angle: f32) -> Point2D {
+    // Calculate chord midpoint
+    let chord_mid = Point2D {
+        x: (start.x + end.x) / 2.0,
+        y: (start.y + end.y) / 2.0,
+    };
+
+    // Calculate chord length
+    let chord_length = f32::sqrt(
+        (end.x - start.x).powi(2) +
+            (end.y - start.y).powi(2)
+    );
+
+    // Convert arc angle to radians and get central angle
+    let arc_angle = angle.abs() * std::f32::consts::PI / 180.0;
+    let central_angle = arc_angle / 2.0;
+
+    // Calculate radius using r = c/(2*sin(θ/2))
+    let radius = chord_length / (2.0 * central_angle.sin());
+
+    // Calculate sagitta (height of arc from chord)
+    let sagitta = radius * (1.0 - central_angle.cos());
+
+    // Calculate perpendicular vector to chord
+    let dx = end.x - start.x;
+    let dy = end.y - start.y;
+
+    // Direction depends on angle sign
+    let sign = if angle < 0.0 { -1.0 } else { 1.0 };
+    let perp_x = -dy * sign;
+    let perp_y = dx * sign;
+
+    // Normalize perpendicular vector
+    let perp_length = f32::sqrt(perp_x.powi(2) + perp_y.powi(2));
+    let unit_perp_x = perp_x / perp_length;
+    let unit_perp_y = perp_y / perp_length;
+
+    // Calculate arc midpoint
+    Point2D {
+        x: chord_mid.x + unit_perp_x * sagitta,
+        y: chord_mid.y + unit_perp_y * sagitta,
+    }
+}
+
+pub fn get_point_on_arc(start: Point2D, end: Point2D, mut angle: f32, t: f32) -> Point2D {
+    // For major arcs, flip the direction to match SVG arc behavior
+    if angle.abs() > 180.0 {
+        angle = -angle;
+    }
+
+    // Calculate chord properties
+    let dx = end.x - start.x;
+    let dy = end.y - start.y;
+    let chord_length = (dx * dx + dy * dy).sqrt();
+    let angle_radians = angle * PI / 180.0;
+
+    // Calculate radius and center
+    let radius = (chord_length / 2.0) / (angle_radians.abs() / 2.0).sin();
+
+    // Find the middle point of the chord
+    let mid_x = (start.x + end.x) / 2.0;
+    let mid_y = (start.y + end.y) / 2.0;
+
+    // Calculate the center point
+    let direction = if angle >= 0.0 { 1.0 } else { -1.0 };
+    let center_distance = (radius * radius - (chord_length * chord_length / 4.0)).sqrt();
+    let normalized_dx = dx / chord_length;
+    let normalized_dy = dy / chord_length;
+    let center_x = mid_x - direction * center_distance * normalized_dy;
+    let center_y = mid_y + direction * center_distance * normalized_dx;
+
+    // Calculate angles relative to center
+    let start_angle = (start.y - center_y).atan2(start.x - center_x);
+    let end_angle = (end.y - center_y).atan2(end.x - center_x);
+
+    // Calculate smaller angle between start and end
+    let mut delta_angle = end_angle - start_angle;
+
+    // Normalize to -2PI to 2PI range
+    delta_angle = delta_angle % (2.0 * PI);
+
+    // Convert to -PI to PI range
+    if delta_angle > PI {
+        delta_angle -= 2.0 * PI;
+    }
+    if delta_angle < -PI {
+        delta_angle += 2.0 * PI;
+    }
+
+    // For major arcs, take the long way around
+    if (angle >= 0.0 && angle > 180.0) || (angle < 0.0 && angle < -180.0) {
+        if delta_angle >= 0.0 {
+            delta_angle -= 2.0 * PI;
+        } else {
+            delta_angle += 2.0 * PI;
+        }
+    }
+
+    // Interpolate the angle
+    let interpolated_angle = start_angle + delta_angle * t;
+
+    // Calculate final point position
+    Point2D {
+        x: center_x + radius * interpolated_angle.cos(),
+        y: center_y + radius * interpolated_angle.sin(),
+    }
+}
+
+pub fn get_arc_length(start: Point2D, end: Point2D, angle_degrees: f32) -> f32 {
+    // Calculate chord length using distance formula
+    let dx = end.x - start.x;
+    let dy = end.y - start.y;
+    let chord_length = (dx * dx + dy * dy).sqrt();
+
+    // Convert angle to radians (using absolute value for the formula)
+    let angle_radians = angle_degrees.abs() * PI / 180.0;
+
+    // Calculate radius using formula: R = (chord length/2) / sin(angle/2)
+    let radius = (chord_length / 2.0) / (angle_radians / 2.0).sin();
+
+    // Calculate arc length using formula: L = R * angle (in radians)
+    radius * angle_radians
+}
+
+/// Upper bound for the number of segments a single arc is split into
+const MAX_ARC_SEGMENTS: usize = 360;
+
+/// Returns the points between `start` and `end` needed to replace the arc with straight segments
+/// that deviate from it by at most `max_error`, so small arcs get few points and large arcs many.
+pub fn interpolate_arc_points(start: Point2D, end: Point2D, angle: f32, max_error: f32) -> Vec<Point2D> {
+    let sweep = angle.abs().to_radians();
+    let radius = get_arc_length(start, end, angle) / sweep;
+    if !radius.is_finite() || radius <= 0.0 || max_error <= 0.0 {
+        return Vec::new();
+    }
+
+    // A chord spanning `segment_angle` deviates from the arc by `radius * (1 - cos(segment_angle / 2))`
+    let segment_angle = 2.0 * (1.0 - (max_error / radius).min(1.0)).acos();
+    let num_segments = ((sweep / segment_angle).ceil() as usize).clamp(1, MAX_ARC_SEGMENTS);
+
+    (1..num_segments)
+        .map(|i| get_point_on_arc(start, end, angle, i as f32 / num_segments as f32))
+        .collect()
+}
+
+/// Returns the signed sweep angle (in degrees) of the arc passing through `start`, `mid` and `end`,
+/// using the same sign convention as [`get_arc_mid_point`].
+pub fn get_arc_angle(start: Point2D, mid: Point2D, end: Point2D) -> f32 {
+    // Inscribed angle at the mid point, subtending the start-end chord
+    let (ax, ay) = (start.x - mid.x, start.y - mid.y);
+    let (bx, by) = (end.x - mid.x, end.y - mid.y);
+    let inscribed_angle = (ax * by - ay * bx).atan2(ax * bx + ay * by).abs();
+
+    // The arc containing the mid point spans the rest of the circle
+    let sweep = 2.0 * PI - 2.0 * inscribed_angle;
+
+    // Direction depends on which side of the chord the mid point lies on
+    let cross = (end.x - start.x) * (mid.y - start.y) - (end.y - start.y) * (mid.x - start.x);
+    let sign = if cross < 0.0 { -1.0 } else { 1.0 };
+
+    sign * sweep * 180.0 / PI
+}
+
+/// Flattens an SVG path expression (`d` attribute) into polylines, one for each sub-path.
+/// Curves and elliptical arcs are reduced to their end points.
+pub fn parse_svg_path(path: &str) -> Vec<Vec<Point2D>> {
+    let mut tokens = Vec::new();
+    let mut number = String::new();
+    for c in path.chars() {
+        let starts_new_number = (c == '-' || c == '+') && !number.is_empty() && !number.ends_with(['e', 'E'])
+            || c == '.' && number.contains('.') && !number.contains(['e', 'E']);
+        if c.is_ascii_digit() || c == '.' || c == '-' || c == '+' || c == 'e' || c == 'E' {
+            if starts_new_number {
+                tokens.push(std::mem::take(&mut number));
+            }
+            number.push(c);
+        } else {
+            if !number.is_empty() {
+                tokens.push(std::mem::take(&mut number));
+            }
+            if c.is_ascii_alphabetic() {
+                tokens.push(c.to_string());
+            }
+        }
+    }
+    if !number.is_empty() {
+        tokens.push(number);
+    }
+
+    let mut polylines = Vec::new();
+    let mut current: Vec<Point2D> = Vec::new();
+    let mut position = Point2D::new(0.0, 0.0);
+    let mut subpath_start = position;
+    let mut command = 'M';
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(token) = tokens.peek() {
+        if let Some(c) = token.chars().next().filter(|c| c.is_ascii_alphabetic()) {
+            command = c;
+            tokens.next();
+            if command == 'Z' || command == 'z' {
+                if !current.is_empty() {
+                    current.push(subpath_start);
+                    polylines.push(std::mem::take(&mut current));
+                }
+                position = subpath_start;
+                continue;
+            }
+        }
+
+        let param_count = match command.to_ascii_uppercase() {
+            'M' | 'L' | 'T' => 2,
+            'H' | 'V' => 1,
+            'S' | 'Q' => 4,
+            'C' => 6,
+            'A' => 7,
+            _ => return polylines,
+        };
+        let params = tokens.by_ref().take(param_count).filter_map(|t| t.parse::<f32>().ok()).collect::<Vec<_>>();
+        if params.len() != param_count {
+            break;
+        }
+
+        let relative = command.is_ascii_lowercase();
+        let base = if relative { position } else { Point2D::new(0.0, 0.0) };
+        position = match command.to_ascii_uppercase() {
+            'H' => Point2D::new(base.x + params[0], position.y),
+            'V' => Point2D::new(position.x, base.y + params[0]),
+            _ => Point2D::new(base.x + params[param_count - 2], base.y + params[param_count - 1]),
+        };
+
+        if command == 'M' || command == 'm' {
+            if current.len() > 1 {
+                polylines.push(std::mem::take(&mut current));
+            }
+            current.clear();
+            subpath_start = position;
+
+            // Subsequent coordinate pairs are implicit line commands
+            command = if relative { 'l' } else { 'L' };
+        }
+        current.push(position);
+    }
+
+    if current.len() > 1 {
+        polylines.push(current);
+    }
+
+    polylines
+}
+
+/// Even-odd test of whether the point lies inside the closed polygon
+pub fn is_point_in_polygon(point: Point2D, polygon: &[Point2D]) -> bool {
+    let mut inside = false;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.y > point.y) != (b.y > point.y) && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Returns the center of the circle passing through the three points, or `None` if they are collinear
+pub fn get_circle_center(a: Point2D, b: Point2D, c: Point2D) -> Option<Point2D> {
+    let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+    if d.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let a_sq = a.x.powi(2) + a.y.powi(2);
+    let b_sq = b.x.powi(2) + b.y.powi(2);
+    let c_sq = c.x.powi(2) + c.y.powi(2);
+    Some(Point2D {
+        x: (a_sq * (b.y - c.y) + b_sq * (c.y - a.y) + c_sq * (a.y - b.y)) / d,
+        y: (a_sq * (c.x - b.x) + b_sq * (a.x - c.x) + c_sq * (b.x - a.x)) / d,
+    })
+}
+
+/// Rotates the point around the center, positive angles (in degrees) go from the +X towards the +Y axis
+pub fn rotate_point(point: Point2D, center: Point2D, angle: f32) -> Point2D {
+    let rotated = Point2D::new(point.x - center.x, point.y - center.y).rotate(angle);
+    Point2D::new(center.x + rotated.x, center.y + rotated.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn arc_interpolation_adapts_to_radius() {
+        let max_error = 0.005;
+        let small_arc = interpolate_arc_points(Point2D::new(-0.5, 0.0), Point2D::new(0.5, 0.0), 180.0, max_error);
+        let large_arc = interpolate_arc_points(Point2D::new(-20.0, 0.0), Point2D::new(20.0, 0.0), 180.0, max_error);
+        assert!(small_arc.len() < large_arc.len());
+
+        // Every segment stays within the allowed error from the arc
+        for (radius, points) in [(0.5, &small_arc), (20.0, &large_arc)] {
+            let points = std::iter::once(Point2D::new(-radius, 0.0)).chain(points.iter().copied()).chain(std::iter::once(Point2D::new(radius, 0.0))).collect::<Vec<_>>();
+            for segment in points.windows(2) {
+                let mid = Point2D::new((segment[0].x + segment[1].x) / 2.0, (segment[0].y + segment[1].y) / 2.0);
+                let deviation = radius - (mid.x * mid.x + mid.y * mid.y).sqrt();
+                assert!(deviation <= max_error * 1.01, "deviation {} exceeds {}", deviation, max_error);
+            }
+        }
+    }
+
+    #[test]
+    fn arc_mid_point_half_circle() {
+        let mid = get_arc_mid_point(Point2D::new(0.0, 0.0), Point2D::new(2.0, 0.0), 180.0);
+        assert_close(mid.x, 1.0);
+        assert_close(mid.y, 1.0);
+
+        let mid = get_arc_mid_point(Point2D::new(0.0, 0.0), Point2D::new(2.0, 0.0), -180.0);
+        assert_close(mid.x, 1.0);
+        assert_close(mid.y, -1.0);
+    }
+
+    #[test]
+    fn arc_angle_matches_mid_point() {
+        let start = Point2D::new(1.0, 2.0);
+        let end = Point2D::new(4.0, -1.0);
+        for angle in [30.0, 90.0, 135.0, -45.0, -120.0] {
+            let mid = get_arc_mid_point(start, end, angle);
+            assert_close(get_arc_angle(start, mid, end), angle);
+        }
+    }
+
+    #[test]
+    fn arc_angle_collinear() {
+        let angle = get_arc_angle(Point2D::new(0.0, 0.0), Point2D::new(1.0, 0.0), Point2D::new(2.0, 0.0));
+        assert_close(angle, 0.0);
+    }
+
+    #[test]
+    fn svg_path_polylines() {
+        let polylines = parse_svg_path("M 0 0 L 10 0 l 0 10 H 0 Z M20,20 30,20");
+        assert_eq!(polylines.len(), 2);
+
+        let square = polylines[0].iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+        assert_eq!(square, vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0), (0.0, 0.0)]);
+
+        let line = polylines[1].iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+        assert_eq!(line, vec![(20.0, 20.0), (30.0, 20.0)]);
+    }
+
+    #[test]
+    fn arc_length_quarter_circle() {
+        let length = get_arc_length(Point2D::new(1.0, 0.0), Point2D::new(0.0, 1.0), 90.0);
+        assert_close(length, PI / 2.0);
+    }
+
+    #[test]
+    fn point_in_l_shaped_polygon() {
+        let polygon = [(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)].map(|(x, y)| Point2D::new(x, y));
+        assert!(is_point_in_polygon(Point2D::new(0.5, 0.5), &polygon));
+        assert!(is_point_in_polygon(Point2D::new(0.5, 1.5), &polygon));
+        assert!(!is_point_in_polygon(Point2D::new(1.5, 1.5), &polygon));
+        assert!(!is_point_in_polygon(Point2D::new(3.0, 0.5), &polygon));
+    }
+
+    #[test]
+    fn circle_center_from_three_points() {
+        let center = get_circle_center(Point2D::new(3.0, 1.0), Point2D::new(1.0, 3.0), Point2D::new(-1.0, 1.0)).unwrap();
+        assert_close(center.x, 1.0);
+        assert_close(center.y, 1.0);
+
+        assert!(get_circle_center(Point2D::new(0.0, 0.0), Point2D::new(1.0, 1.0), Point2D::new(2.0, 2.0)).is_none());
+    }
+}
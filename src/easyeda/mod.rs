@@ -1,7 +1,8 @@
 pub mod symbol;
 pub mod footprint;
 mod json_reader;
-mod geometry;
+pub mod encoding;
+pub(crate) mod geometry;
 pub mod tests;
 pub mod api;
 pub mod errors;
\ No newline at end of file
@@ -1,9 +1,13 @@
+use crate::easyeda::encoding;
+use crate::easyeda::geometry;
 use crate::easyeda::geometry::Point2D;
 use crate::easyeda::json_reader::JsonArrayReader;
 use crate::easyeda::errors::{ParserError, ParserType, SymbolConverterError};
 use crate::kicad::model::common::{FontSize, Position, StrokeDefinition, TextEffect, TextJustifyHorizontal, TextJustifyVertical, TextPosition};
 use crate::kicad::model::symbol_library::{Color, FillDefinition, FillType, PinElectricalType, PinGraphicStyle, StrokeType, Symbol, SymbolArc, SymbolCircle, SymbolLib, SymbolLine, SymbolPin, SymbolRectangle, SymbolText};
+use crate::units::SYMBOL_UNIT_TO_MM;
 use itertools::Itertools;
+use log::warn;
 use num_derive::FromPrimitive;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -13,10 +17,43 @@ pub struct EasyEDASymbol {
     pub elements: Vec<SymbolElement>,
 
     pub part_number: Option<String>,
+    pub datasheet: Option<String>,
+    pub fill_style: SymbolFillStyle,
+    pub pin_names_offset: Option<f32>,
+}
+
+/// Controls which fill types are assigned to closed symbol shapes during conversion
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum SymbolFillStyle {
+    /// Follow the KiCad library conventions (body shapes are filled with the background color)
+    #[default]
+    #[value(name = "kicad")]
+    KiCad,
+    /// Keep the legacy EasyEDA-like look (circles are filled with the outline color)
+    #[value(name = "easyeda")]
+    EasyEDA,
+}
+
+impl SymbolFillStyle {
+    fn rectangle_fill(&self, line_style: &LineStyle) -> FillType {
+        match line_style.is_hollow() {
+            true => FillType::None,
+            false => FillType::Background,
+        }
+    }
+
+    fn circle_fill(&self, line_style: &LineStyle) -> FillType {
+        match (line_style.is_hollow(), self) {
+            (true, _) => FillType::None,
+            (false, SymbolFillStyle::KiCad) => FillType::Background,
+            (false, SymbolFillStyle::EasyEDA) => FillType::Outline,
+        }
+    }
 }
 
 impl EasyEDASymbol {
     pub fn parse(symbol_data: &str) -> anyhow::Result<EasyEDASymbol> {
+        let symbol_data = encoding::decode_data_str(symbol_data)?;
         let mut elements = Vec::new();
 
         for param in symbol_data.split_terminator(['\r', '\n']) {
@@ -35,6 +72,9 @@ impl EasyEDASymbol {
 
         Ok(Self {
             part_number: None,
+            datasheet: None,
+            fill_style: SymbolFillStyle::default(),
+            pin_names_offset: None,
             elements,
         })
     }
@@ -66,6 +106,8 @@ impl TryInto<SymbolLib> for EasyEDASymbol {
             generator: "easyeda-to-kicad".into(),
             generator_version: None,
             symbols: vec![symbol],
+            embedded_files: None,
+            unknown_items: vec![],
         })
     }
 }
@@ -76,12 +118,13 @@ impl TryInto<Symbol> for EasyEDASymbol {
     fn try_into(self) -> Result<Symbol, Self::Error> {
         let default_text_effect = TextEffect::default();
 
-        let scale_factor = 0.254;
+        let scale_factor = SYMBOL_UNIT_TO_MM;
 
         let mut line_styles = HashMap::new();
         let mut text_styles = HashMap::new();
         let mut current_part = Part::default();
         let mut attributes = Vec::new();
+        let mut root_attributes = Vec::new();
 
         let all_parts = self.elements.iter()
             .filter_map(|e| match e {
@@ -90,6 +133,12 @@ impl TryInto<Symbol> for EasyEDASymbol {
             }).collect_vec();
         let is_complex_symbol = all_parts.len() > 1;
 
+        let (origin_x, origin_y) = self.elements.iter()
+            .find_map(|e| match e {
+                SymbolElement::HEAD(head) => Some((head.origin_x, head.origin_y)),
+                _ => None,
+            }).unwrap_or((0.0, 0.0));
+
         let mut all_symbols = Vec::new();
         let mut current_symbol_index = usize::MAX;
 
@@ -99,6 +148,8 @@ impl TryInto<Symbol> for EasyEDASymbol {
                 SymbolElement::ATTR(attribute) => {
                     if let Some(_) = &attribute.parent_id {
                         attributes.push(attribute.clone());
+                    } else {
+                        root_attributes.push(attribute.clone());
                     }
                 }
                 _ => {}
@@ -154,10 +205,10 @@ impl TryInto<Symbol> for EasyEDASymbol {
                         stroke: StrokeDefinition {
                             width: line_style.stroke_width.unwrap_or(0.254),
                             color: line_style.stroke_color.clone().and_then(|s| Some(Color::from_hex(&s))),
-                            dash: Some(StrokeType::Solid),
+                            dash: Some(line_style.stroke_type()),
                         },
                         fill: FillDefinition {
-                            fill_type: FillType::Background,
+                            fill_type: self.fill_style.rectangle_fill(line_style),
                         },
                     });
                 }
@@ -170,10 +221,10 @@ impl TryInto<Symbol> for EasyEDASymbol {
                         stroke: StrokeDefinition {
                             width: line_style.stroke_width.unwrap_or(0.254),
                             color: line_style.stroke_color.clone().and_then(|s| Some(Color::from_hex(&s))),
-                            dash: Some(StrokeType::Solid),
+                            dash: Some(line_style.stroke_type()),
                         },
                         fill: FillDefinition {
-                            fill_type: FillType::Outline,
+                            fill_type: self.fill_style.circle_fill(line_style),
                         },
                     });
                 }
@@ -187,10 +238,10 @@ impl TryInto<Symbol> for EasyEDASymbol {
                             stroke: StrokeDefinition {
                                 width: line_style.stroke_width.unwrap_or(0.254),
                                 color: line_style.stroke_color.clone().and_then(|s| Some(Color::from_hex(&s))),
-                                dash: Some(StrokeType::Solid),
+                                dash: Some(line_style.stroke_type()),
                             },
                             fill: FillDefinition {
-                                fill_type: FillType::Outline,
+                                fill_type: self.fill_style.circle_fill(line_style),
                             },
                         });
                     } else {
@@ -200,34 +251,67 @@ impl TryInto<Symbol> for EasyEDASymbol {
                 SymbolElement::POLYLINE(line) => {
                     let current_symbol = all_symbols.get_mut(current_symbol_index).unwrap();
                     let line_style = line_styles.get(&line.style_id.unwrap()).unwrap();
+                    let mut points = line.points.iter().map(|p| Position { x: p.0 * scale_factor, y: p.1 * scale_factor, angle: None }).collect::<Vec<_>>();
+
+                    // Closed polylines are drawn back to their first point and filled like rectangles
+                    if line.is_closed && points.len() > 2 {
+                        let (first, last) = (&points[0], &points[points.len() - 1]);
+                        if first.x != last.x || first.y != last.y {
+                            points.push(Position { x: first.x, y: first.y, angle: None });
+                        }
+                    }
+                    let fill_type = match line.is_closed {
+                        true => self.fill_style.rectangle_fill(line_style),
+                        false => FillType::None,
+                    };
+
                     current_symbol.lines.push(SymbolLine {
-                        points: line.points.iter().map(|p| Position { x: p.0 * scale_factor, y: p.1 * scale_factor, angle: None }).collect(),
+                        points,
                         stroke: StrokeDefinition {
                             width: line_style.stroke_width.unwrap_or(0.254),
                             color: line_style.stroke_color.clone().and_then(|s| Some(Color::from_hex(&s))),
-                            dash: Some(StrokeType::Solid),
+                            dash: Some(line_style.stroke_type()),
                         },
                         fill: Some(FillDefinition {
-                            fill_type: FillType::None,
+                            fill_type,
                         }),
                     });
                 }
                 SymbolElement::ARC(arc) => {
                     let current_symbol = all_symbols.get_mut(current_symbol_index).unwrap();
                     let line_style = line_styles.get(&arc.style_id.unwrap()).unwrap();
-                    current_symbol.arcs.push(SymbolArc {
-                        start: Position { x: arc.x1 * scale_factor, y: arc.y1 * scale_factor, angle: None },
-                        mid: Position { x: arc.x2 * scale_factor, y: arc.y2 * scale_factor, angle: None },
-                        end: Position { x: arc.x3 * scale_factor, y: arc.y3 * scale_factor, angle: None },
-                        stroke: StrokeDefinition {
-                            width: line_style.stroke_width.unwrap_or(0.254),
-                            color: line_style.stroke_color.clone().and_then(|s| Some(Color::from_hex(&s))),
-                            dash: Some(StrokeType::Solid),
-                        },
-                        fill: FillDefinition {
-                            fill_type: FillType::None,
-                        },
-                    });
+                    let start = Point2D::new(arc.x1 * scale_factor, arc.y1 * scale_factor);
+                    let mid = Point2D::new(arc.x2 * scale_factor, arc.y2 * scale_factor);
+                    let end = Point2D::new(arc.x3 * scale_factor, arc.y3 * scale_factor);
+                    let stroke = StrokeDefinition {
+                        width: line_style.stroke_width.unwrap_or(0.254),
+                        color: line_style.stroke_color.clone().and_then(|s| Some(Color::from_hex(&s))),
+                        dash: Some(line_style.stroke_type()),
+                    };
+
+                    // KiCad can't render arcs with collinear points, emit a straight line instead
+                    if geometry::get_arc_angle(start, mid, end).abs() < 0.01 {
+                        current_symbol.lines.push(SymbolLine {
+                            points: vec![
+                                Position { x: start.x, y: start.y, angle: None },
+                                Position { x: end.x, y: end.y, angle: None },
+                            ],
+                            stroke,
+                            fill: Some(FillDefinition {
+                                fill_type: FillType::None,
+                            }),
+                        });
+                    } else {
+                        current_symbol.arcs.push(SymbolArc {
+                            start: Position { x: start.x, y: start.y, angle: None },
+                            mid: Position { x: mid.x, y: mid.y, angle: None },
+                            end: Position { x: end.x, y: end.y, angle: None },
+                            stroke,
+                            fill: FillDefinition {
+                                fill_type: FillType::None,
+                            },
+                        });
+                    }
                 }
                 SymbolElement::BEZIER(_bezier) => {
                     // todo implement bezier
@@ -259,7 +343,7 @@ impl TryInto<Symbol> for EasyEDASymbol {
                     }
 
                     current_symbol.texts.push(SymbolText {
-                        text: text.text,
+                        text: convert_text_variables(&text.text),
                         position: TextPosition { x: text.x * scale_factor, y: text.y * scale_factor, angle: Some(text.rotation) },
                         effects: text_style,
                     });
@@ -275,13 +359,25 @@ impl TryInto<Symbol> for EasyEDASymbol {
                         name = "~".into();
                     }
 
+                    let rotation = snap_pin_rotation(pin.rotation);
+                    if get_angle_difference(rotation, pin.rotation) > PIN_ROTATION_SNAP_THRESHOLD {
+                        warn!("Pin {} is rotated by {}°, snapping it to {}°", number, pin.rotation, rotation);
+                    }
+
                     current_symbol.pins.push(SymbolPin {
-                        position: Position { x: pin.x * scale_factor, y: pin.y * scale_factor, angle: Some(pin.rotation) },
+                        position: Position { x: pin.x * scale_factor, y: pin.y * scale_factor, angle: Some(rotation) },
                         length: pin.length * scale_factor,
                         number: Some(number),
                         name: Some(name),
-                        name_effects: default_text_effect.clone(),
-                        number_effects: default_text_effect.clone(),
+                        name_effects: TextEffect {
+                            hide: name_attr.value_visible == Some(false),
+                            ..default_text_effect.clone()
+                        },
+                        number_effects: TextEffect {
+                            hide: number_attr.value_visible == Some(false),
+                            ..default_text_effect.clone()
+                        },
+                        hidden: !pin.display,
                         graphic_style: match pin.pin_shape {
                             PinShape::None => PinGraphicStyle::Line,
                             PinShape::Clock => PinGraphicStyle::Clock,
@@ -295,11 +391,36 @@ impl TryInto<Symbol> for EasyEDASymbol {
                     let current_symbol = all_symbols.get_mut(current_symbol_index).unwrap();
                     current_symbol.objects.push(obj);
                 }
+                SymbolElement::SVGNODE(svg_node) => {
+                    let current_symbol = all_symbols.get_mut(current_symbol_index).unwrap();
+                    for polyline in svg_node.get_polylines() {
+                        current_symbol.lines.push(SymbolLine {
+                            points: polyline.iter().map(|p| Position { x: p.x * scale_factor, y: p.y * scale_factor, angle: None }).collect(),
+                            stroke: StrokeDefinition {
+                                width: 0.254,
+                                color: None,
+                                dash: Some(StrokeType::Solid),
+                            },
+                            fill: Some(FillDefinition {
+                                fill_type: FillType::None,
+                            }),
+                        });
+                    }
+                }
 
                 SymbolElement::DOCTYPE(_) | SymbolElement::HEAD(_) => {}
             }
         }
 
+        // KiCad symbols have no way to embed images, so objects are dropped with a warning
+        let dropped_objects = all_symbols.iter()
+            .flat_map(|s| s.objects.iter())
+            .map(|o| format!("'{}' ({})", o.file_name, o.id))
+            .collect_vec();
+        if !dropped_objects.is_empty() {
+            warn!("Embedded objects are not supported in KiCad symbols, dropping {} object(s): {}", dropped_objects.len(), dropped_objects.join(", "));
+        }
+
         let mut root_symbol = Symbol {
             in_bom: Some(true),
             on_board: Some(true),
@@ -354,12 +475,141 @@ impl TryInto<Symbol> for EasyEDASymbol {
             root_symbol = symbol;
         }
 
+        // Place the fields where the matching EasyEDA attributes are shown
+        for (kicad_key, easyeda_keys) in [("Reference", &["Designator"][..]), ("Value", &["Value", "Name"][..])] {
+            let attribute = easyeda_keys.iter()
+                .find_map(|key| root_attributes.iter().find(|a| a.key == *key && a.x.is_some() && a.y.is_some()));
+            if let Some(attribute) = attribute {
+                root_symbol.add_property(kicad_key, attribute.value.as_deref().unwrap_or_default(), attribute.x.unwrap() * scale_factor, attribute.y.unwrap() * scale_factor);
+                let property = root_symbol.properties.last_mut().unwrap();
+                property.position.angle = Some(attribute.rotation.unwrap_or(0.0));
+                property.text_effects.hide = attribute.value_visible == Some(false);
+            }
+        }
+
+        // EasyEDA coordinates are relative to the canvas, KiCad expects them relative to the symbol origin
+        root_symbol.translate(-origin_x * scale_factor, -origin_y * scale_factor);
+
         // todo add basic properties to root
+        if let Some(part_number) = &self.part_number {
+            root_symbol.add_hidden_property("LCSC", part_number);
+        }
+        if let Some(datasheet) = &self.datasheet {
+            root_symbol.add_hidden_property("Datasheet", datasheet);
+        }
+        root_symbol.pin_names_offset = self.pin_names_offset.or_else(|| get_default_pin_names_offset(&root_symbol));
+        hide_pin_texts_symbol_wide(&mut root_symbol);
+
+        for unit in std::iter::once(&root_symbol).chain(root_symbol.units.iter()) {
+            let duplicates = find_duplicate_pin_numbers(unit);
+            if !duplicates.is_empty() {
+                warn!("Symbol '{}' has duplicate pin numbers: {}", unit.symbol_id, duplicates.join(", "));
+            }
+        }
 
         Ok(root_symbol)
     }
 }
 
+/// KiCad only reliably hides pin names and numbers for the whole symbol, so when every pin hides
+/// its name or number the per-pin flags are replaced by the symbol-wide one
+fn hide_pin_texts_symbol_wide(symbol: &mut Symbol) {
+    let pins = symbol.pins.iter().chain(symbol.units.iter().flat_map(|u| u.pins.iter())).collect_vec();
+    if pins.is_empty() {
+        return;
+    }
+
+    symbol.pin_names_hidden = pins.iter().all(|p| p.name_effects.hide);
+    symbol.pin_numbers_hidden = pins.iter().all(|p| p.number_effects.hide);
+
+    let (pin_names_hidden, pin_numbers_hidden) = (symbol.pin_names_hidden, symbol.pin_numbers_hidden);
+    for pin in symbol.pins.iter_mut().chain(symbol.units.iter_mut().flat_map(|u| u.pins.iter_mut())) {
+        pin.name_effects.hide &= !pin_names_hidden;
+        pin.number_effects.hide &= !pin_numbers_hidden;
+    }
+}
+
+/// Rewrites EasyEDA `{attribute}` references into the `${VARIABLE}` form expanded by KiCad.
+/// Unrecognized attributes are left untouched.
+fn convert_text_variables(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut remaining = text;
+    while let Some(start) = remaining.find('{') {
+        let (before, after) = remaining.split_at(start);
+        result.push_str(before);
+
+        let variable = after[1..].find('}').and_then(|end| {
+            let name = &after[1..end + 1];
+            let kicad_name = match name.to_lowercase().as_str() {
+                "designator" | "reference" => "REFERENCE",
+                "value" => "VALUE",
+                "footprint" => "FOOTPRINT",
+                "datasheet" => "DATASHEET",
+                _ => return None,
+            };
+            Some((kicad_name, end + 2))
+        });
+
+        match variable {
+            Some((kicad_name, length)) if !before.ends_with('$') => {
+                result.push_str(&format!("${{{kicad_name}}}"));
+                remaining = &after[length..];
+            }
+            _ => {
+                result.push('{');
+                remaining = &after[1..];
+            }
+        }
+    }
+    result.push_str(remaining);
+    result
+}
+
+const PIN_ROTATION_SNAP_THRESHOLD: f32 = 1.0;
+
+/// KiCad only supports pins pointing in one of the four cardinal directions
+fn snap_pin_rotation(rotation: f32) -> f32 {
+    ((rotation.rem_euclid(360.0) / 90.0).round() * 90.0) % 360.0
+}
+
+fn get_angle_difference(a: f32, b: f32) -> f32 {
+    let difference = (a - b).rem_euclid(360.0);
+    difference.min(360.0 - difference)
+}
+
+/// Returns the pin numbers that are used by more than one pin of the same unit.
+/// Pins stacked on the same position are allowed to share a number.
+fn find_duplicate_pin_numbers(symbol: &Symbol) -> Vec<String> {
+    symbol.pins.iter()
+        .filter_map(|p| p.number.as_ref().map(|n| (n, p)))
+        .into_group_map()
+        .into_iter()
+        .filter(|(_, pins)| pins.iter().any(|p| p.position.x != pins[0].position.x || p.position.y != pins[0].position.y))
+        .map(|(number, _)| number.clone())
+        .sorted()
+        .collect()
+}
+
+/// Derives the pin name offset from the smallest spacing between pins on the same side of the symbol
+fn get_default_pin_names_offset(symbol: &Symbol) -> Option<f32> {
+    let pins = symbol.pins.iter()
+        .chain(symbol.units.iter().flat_map(|u| u.pins.iter()))
+        .collect_vec();
+    if pins.is_empty() {
+        return None;
+    }
+
+    let min_pitch = pins.iter()
+        .tuple_combinations()
+        .filter(|(a, b)| a.position.angle == b.position.angle)
+        .map(|(a, b)| ((a.position.x - b.position.x).powi(2) + (a.position.y - b.position.y).powi(2)).sqrt())
+        .filter(|distance| *distance > 0.0)
+        .reduce(f32::min)
+        .unwrap_or(2.54);
+
+    Some((min_pitch * 0.2).clamp(0.254, 1.016))
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DocType {
     pub kind: String,
@@ -384,6 +634,25 @@ pub struct LineStyle {
     pub fill_style: Option<String>,
 }
 
+impl LineStyle {
+    /// A missing fill color falls back to the default fill, only an explicit "no fill" makes the shape hollow
+    pub fn is_hollow(&self) -> bool {
+        let is_none = |value: &Option<String>| value.as_deref()
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "" | "none" | "transparent"))
+            .unwrap_or(false);
+        is_none(&self.fill_color) || is_none(&self.fill_style)
+    }
+
+    pub fn stroke_type(&self) -> StrokeType {
+        match self.stroke_style {
+            Some(1) => StrokeType::Dash,
+            Some(2) => StrokeType::Dot,
+            Some(3) => StrokeType::DashDot,
+            _ => StrokeType::Solid,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FontStyle {
     pub index_name: String,
@@ -538,6 +807,48 @@ pub struct Object {
     pub is_locked: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SvgNode {
+    pub id: String,
+    pub node: Value,
+}
+
+impl SvgNode {
+    /// Collects the outlines of all `path`, `polyline` and `polygon` nodes in the SVG tree
+    pub fn get_polylines(&self) -> Vec<Vec<Point2D>> {
+        fn collect(node: &Value, polylines: &mut Vec<Vec<Point2D>>) {
+            let attrs = &node["attrs"];
+            match node["nodeName"].as_str() {
+                Some("path") => {
+                    if let Some(d) = attrs["d"].as_str() {
+                        polylines.extend(geometry::parse_svg_path(d));
+                    }
+                }
+                Some(name @ ("polyline" | "polygon")) => {
+                    if let Some(points) = attrs["points"].as_str() {
+                        let path = format!("M {}{}", points, if name == "polygon" { " Z" } else { "" });
+                        polylines.extend(geometry::parse_svg_path(&path));
+                    }
+                }
+                _ => {}
+            }
+
+            if let Some(children) = node["childNodes"].as_array() {
+                for child in children {
+                    collect(child, polylines);
+                }
+            }
+        }
+
+        let mut polylines = Vec::new();
+        match &self.node {
+            Value::Array(nodes) => nodes.iter().for_each(|node| collect(node, &mut polylines)),
+            node => collect(node, &mut polylines),
+        }
+        polylines
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum SymbolElement {
     DOCTYPE(DocType),
@@ -555,6 +866,7 @@ pub enum SymbolElement {
     PIN(Pin),
     TEXT(Text),
     OBJ(Object),
+    SVGNODE(SvgNode),
 }
 
 impl SymbolElement {
@@ -572,7 +884,7 @@ impl SymbolElement {
         match property_type.as_str() {
             "DOCTYPE" => {
                 if reader.remaining() != 2 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "2", reader.remaining(), line));
                 }
 
                 Ok(Some(SymbolElement::DOCTYPE(DocType {
@@ -582,7 +894,7 @@ impl SymbolElement {
             }
             "HEAD" => {
                 if reader.remaining() != 1 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "1", reader.remaining(), line));
                 }
 
                 let parameters = reader.read_value().unwrap();
@@ -596,7 +908,7 @@ impl SymbolElement {
             }
             "LINESTYLE" => {
                 if reader.remaining() != 6 && reader.remaining() != 5 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "5 or 6", reader.remaining(), line));
                 }
 
                 Ok(Some(SymbolElement::LINESTYLE(LineStyle {
@@ -610,7 +922,7 @@ impl SymbolElement {
             }
             "FONTSTYLE" => {
                 if reader.remaining() != 11 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "11", reader.remaining(), line));
                 }
 
                 Ok(Some(SymbolElement::FONTSTYLE(FontStyle {
@@ -629,7 +941,7 @@ impl SymbolElement {
             }
             "PART" => {
                 if reader.remaining() != 2 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "2", reader.remaining(), line));
                 }
 
                 let id = reader.read_string().unwrap();
@@ -648,7 +960,7 @@ impl SymbolElement {
             }
             "ATTR" => {
                 if reader.remaining() != 11 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "11", reader.remaining(), line));
                 }
 
                 Ok(Some(SymbolElement::ATTR(Attribute {
@@ -667,7 +979,7 @@ impl SymbolElement {
             }
             "RECT" => {
                 if reader.remaining() != 10 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "10", reader.remaining(), line));
                 }
 
                 Ok(Some(SymbolElement::RECT(Rectangle {
@@ -685,7 +997,7 @@ impl SymbolElement {
             }
             "CIRCLE" => {
                 if reader.remaining() != 6 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "6", reader.remaining(), line));
                 }
 
                 Ok(Some(SymbolElement::CIRCLE(Circle {
@@ -699,7 +1011,7 @@ impl SymbolElement {
             }
             "ELLIPSE" => {
                 if reader.remaining() != 8 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "8", reader.remaining(), line));
                 }
 
                 Ok(Some(SymbolElement::ELLIPSE(Ellipse {
@@ -715,7 +1027,7 @@ impl SymbolElement {
             }
             "POLY" => {
                 if reader.remaining() != 5 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "5", reader.remaining(), line));
                 }
 
                 let id = reader.read_string().unwrap();
@@ -734,7 +1046,7 @@ impl SymbolElement {
             }
             "ARC" => {
                 if reader.remaining() != 9 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "9", reader.remaining(), line));
                 }
 
                 Ok(Some(SymbolElement::ARC(Arc {
@@ -751,7 +1063,7 @@ impl SymbolElement {
             }
             "BEZIER" => {
                 if reader.remaining() != 4 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "4", reader.remaining(), line));
                 }
 
                 Ok(Some(SymbolElement::BEZIER(Bezier {
@@ -764,7 +1076,7 @@ impl SymbolElement {
             }
             "TEXT" => {
                 if reader.remaining() != 6 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "6", reader.remaining(), line));
                 }
 
                 Ok(Some(SymbolElement::TEXT(Text {
@@ -780,7 +1092,7 @@ impl SymbolElement {
             "PIN" => {
                 let param_count = reader.remaining();
                 if param_count != 11 && param_count != 10 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "10 or 11", param_count, line));
                 }
 
                 Ok(Some(SymbolElement::PIN(Pin {
@@ -799,7 +1111,7 @@ impl SymbolElement {
             "OBJ" => {
                 let param_count = reader.remaining();
                 if param_count != 10 {
-                    return Err(ParserError::InvalidArrayLength(ParserType::Symbol, property_type.into()));
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "10", param_count, line));
                 }
 
                 Ok(Some(SymbolElement::OBJ(Object {
@@ -815,7 +1127,258 @@ impl SymbolElement {
                     is_locked: reader.read_bool().unwrap(),
                 })))
             }
+            "SVGNODE" => {
+                if reader.remaining() < 2 {
+                    return Err(ParserError::invalid_array_length(ParserType::Symbol, &property_type, "at least 2", reader.remaining(), line));
+                }
+
+                Ok(Some(SymbolElement::SVGNODE(SvgNode {
+                    id: reader.read_string().unwrap(),
+                    node: reader.read_value().unwrap(),
+                })))
+            }
             _ => Err(ParserError::InvalidPropertyType(ParserType::Symbol, property_type.to_string())),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kicad::syntax::{KiCadParser, SyntaxItemSerializable};
+
+    const CIRCLE_SYMBOL: &str = r#"["DOCTYPE","SYMBOL","1.1"]
+["HEAD",{"originX":0,"originY":0,"version":"2.2.32.3","symbolType":2}]
+["LINESTYLE","st1",null,null,null,null]
+["PART","TEST.1",{"BBOX":[-10,-10,10,10]}]
+["CIRCLE","e1",0,0,10,"st1",0]"#;
+
+    #[test]
+    fn body_circle_uses_background_fill() {
+        let easyeda_symbol = EasyEDASymbol::parse(CIRCLE_SYMBOL).unwrap();
+        assert_eq!(easyeda_symbol.fill_style, SymbolFillStyle::KiCad);
+
+        let symbol: Symbol = easyeda_symbol.try_into().unwrap();
+        assert_eq!(symbol.circles.len(), 1);
+        assert_eq!(symbol.circles[0].fill.fill_type, FillType::Background);
+    }
+
+    #[test]
+    fn svg_node_becomes_polylines() {
+        let data = format!("{}\n{}", CIRCLE_SYMBOL, r#"["SVGNODE","e2",{"nodeName":"svg","attrs":{},"childNodes":[{"nodeName":"path","attrs":{"d":"M 0 0 L 10 0 L 10 10 Z"}}]}]"#);
+        let easyeda_symbol = EasyEDASymbol::parse(&data).unwrap();
+
+        let symbol: Symbol = easyeda_symbol.try_into().unwrap();
+        assert_eq!(symbol.lines.len(), 1);
+        assert_eq!(symbol.lines[0].points.len(), 4);
+        assert!((symbol.lines[0].points[1].x - 2.54).abs() < 1e-4);
+    }
+
+    #[test]
+    fn easyeda_fill_style_uses_outline_fill() {
+        let mut easyeda_symbol = EasyEDASymbol::parse(CIRCLE_SYMBOL).unwrap();
+        easyeda_symbol.fill_style = SymbolFillStyle::EasyEDA;
+
+        let symbol: Symbol = easyeda_symbol.try_into().unwrap();
+        assert_eq!(symbol.circles[0].fill.fill_type, FillType::Outline);
+    }
+
+    #[test]
+    fn hollow_shapes_are_not_filled() {
+        let data = r##"["DOCTYPE","SYMBOL","1.1"]
+["HEAD",{"originX":0,"originY":0,"version":"2.2.32.3","symbolType":2}]
+["LINESTYLE","filled",null,null,"#FFFFFF",null]
+["LINESTYLE","hollow",null,null,"none",null]
+["PART","TEST.1",{"BBOX":[-10,-10,10,10]}]
+["RECT","e1",0,0,10,10,0,0,0,"filled",0]
+["RECT","e2",0,0,10,10,0,0,0,"hollow",0]
+["CIRCLE","e3",0,0,10,"filled",0]
+["CIRCLE","e4",0,0,10,"hollow",0]"##;
+        let mut easyeda_symbol = EasyEDASymbol::parse(data).unwrap();
+        easyeda_symbol.fill_style = SymbolFillStyle::EasyEDA;
+
+        let symbol: Symbol = easyeda_symbol.try_into().unwrap();
+        let rectangle_fills = symbol.rectangles.iter().map(|r| r.fill.fill_type.clone()).collect::<Vec<_>>();
+        let circle_fills = symbol.circles.iter().map(|c| c.fill.fill_type.clone()).collect::<Vec<_>>();
+        assert_eq!(rectangle_fills, vec![FillType::Background, FillType::None]);
+        assert_eq!(circle_fills, vec![FillType::Outline, FillType::None]);
+    }
+
+    #[test]
+    fn closed_polyline_is_closed_and_filled() {
+        let data = r##"["DOCTYPE","SYMBOL","1.1"]
+["HEAD",{"originX":0,"originY":0,"version":"2.2.32.3","symbolType":2}]
+["LINESTYLE","st1",null,null,"#FFFFFF",null]
+["PART","TEST.1",{"BBOX":[-10,-10,10,10]}]
+["POLY","e1",[0,0,10,0,10,10],1,"st1",0]
+["POLY","e2",[0,0,10,0,10,10],0,"st1",0]"##;
+        let symbol: Symbol = EasyEDASymbol::parse(data).unwrap().try_into().unwrap();
+
+        let closed = &symbol.lines[0];
+        assert_eq!(closed.points.len(), 4);
+        assert!(closed.points[3].x == closed.points[0].x && closed.points[3].y == closed.points[0].y);
+        assert_eq!(closed.fill.as_ref().unwrap().fill_type, FillType::Background);
+
+        let open = &symbol.lines[1];
+        assert_eq!(open.points.len(), 3);
+        assert_eq!(open.fill.as_ref().unwrap().fill_type, FillType::None);
+    }
+
+    fn pin_symbol(pitch: f32) -> String {
+        let mut data = CIRCLE_SYMBOL.to_string();
+        for index in 0..3 {
+            let y = index as f32 * pitch;
+            data += &format!("\n[\"PIN\",\"p{index}\",1,null,-20,{y},10,0,null,0,0]");
+            data += &format!("\n[\"ATTR\",\"a{index}n\",\"p{index}\",\"NAME\",\"IO{index}\",false,true,0,0,0,\"st1\",0]");
+            data += &format!("\n[\"ATTR\",\"a{index}m\",\"p{index}\",\"NUMBER\",\"{index}\",false,true,0,0,0,\"st1\",0]");
+        }
+        data
+    }
+
+    #[test]
+    fn fields_are_placed_at_their_attributes() {
+        let data = format!("{}\n{}\n{}", CIRCLE_SYMBOL.replace(r#""originX":0,"originY":0"#, r#""originX":10,"originY":0"#),
+            r#"["ATTR","a1",null,"Designator","U?",false,true,10,20,0,"st1",0]"#,
+            r#"["ATTR","a2",null,"Name","NE555",false,false,10,-20,90,"st1",0]"#);
+        let mut symbol: Symbol = EasyEDASymbol::parse(&data).unwrap().try_into().unwrap();
+
+        let reference = symbol.properties.iter().find(|p| p.key == "Reference").unwrap();
+        assert_eq!((reference.position.x, reference.position.y), (0.0, 20.0 * SYMBOL_UNIT_TO_MM));
+        assert!(!reference.text_effects.hide);
+        let value = symbol.properties.iter().find(|p| p.key == "Value").unwrap();
+        assert_eq!(value.position.angle, Some(90.0));
+        assert!(value.text_effects.hide);
+
+        // Setting the value later keeps the placement
+        symbol.set_property("Value", "NE555P");
+        let value = symbol.properties.iter().find(|p| p.key == "Value").unwrap();
+        assert_eq!(value.value, "NE555P");
+        assert!((value.position.y + 20.0 * SYMBOL_UNIT_TO_MM).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pin_display_flags_are_kept() {
+        let mut data = pin_symbol(10.0).replace(r#""PIN","p1",1,"#, r#""PIN","p1",0,"#);
+        for index in 0..3 {
+            data = data.replace(&format!(r#""NUMBER","{index}",false,true"#), &format!(r#""NUMBER","{index}",false,false"#));
+        }
+        data = data.replace(r#""NAME","IO2",false,true"#, r#""NAME","IO2",false,false"#);
+        let symbol: Symbol = EasyEDASymbol::parse(&data).unwrap().try_into().unwrap();
+
+        assert_eq!(symbol.pins.iter().map(|p| p.hidden).collect_vec(), vec![false, true, false]);
+        assert!(symbol.pin_numbers_hidden);
+        assert!(symbol.pins.iter().all(|p| !p.number_effects.hide));
+        assert!(!symbol.pin_names_hidden);
+        assert_eq!(symbol.pins.iter().map(|p| p.name_effects.hide).collect_vec(), vec![false, false, true]);
+
+        let output = KiCadParser::stringify_tokens::<SymbolLib>(&KiCadParser::generate_tokens(&symbol.pins[1].serialize()));
+        let reparsed = SymbolPin::deserialize(&KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&output)));
+        assert!(reparsed.hidden);
+    }
+
+    #[test]
+    fn pin_names_offset_follows_pin_pitch() {
+        let symbol: Symbol = EasyEDASymbol::parse(&pin_symbol(10.0)).unwrap().try_into().unwrap();
+        assert_eq!(symbol.pins.len(), 3);
+        let wide_offset = symbol.pin_names_offset.unwrap();
+        assert!((wide_offset - 0.508).abs() < 1e-4);
+
+        let symbol: Symbol = EasyEDASymbol::parse(&pin_symbol(5.0)).unwrap().try_into().unwrap();
+        let dense_offset = symbol.pin_names_offset.unwrap();
+        assert!((dense_offset - 0.254).abs() < 1e-4);
+    }
+
+    #[test]
+    fn pin_names_offset_override() {
+        let mut easyeda_symbol = EasyEDASymbol::parse(&pin_symbol(10.0)).unwrap();
+        easyeda_symbol.pin_names_offset = Some(1.0);
+
+        let symbol: Symbol = easyeda_symbol.try_into().unwrap();
+        assert_eq!(symbol.pin_names_offset, Some(1.0));
+    }
+
+    #[test]
+    fn base64_payload_parses_like_plain_text() {
+        use base64::Engine;
+
+        let encoded = base64::engine::general_purpose::STANDARD.encode(CIRCLE_SYMBOL);
+        let plain: Symbol = EasyEDASymbol::parse(CIRCLE_SYMBOL).unwrap().try_into().unwrap();
+        let decoded: Symbol = EasyEDASymbol::parse(&encoded).unwrap().try_into().unwrap();
+
+        assert_eq!(decoded.symbol_id, plain.symbol_id);
+        assert_eq!(decoded.circles.len(), plain.circles.len());
+        assert_eq!(decoded.circles[0].radius, plain.circles[0].radius);
+    }
+
+    #[test]
+    fn duplicate_pin_numbers() {
+        let data = pin_symbol(10.0).replace(r#""NUMBER","2""#, r#""NUMBER","1""#);
+        let symbol: Symbol = EasyEDASymbol::parse(&data).unwrap().try_into().unwrap();
+        assert_eq!(find_duplicate_pin_numbers(&symbol), vec!["1".to_string()]);
+
+        let symbol: Symbol = EasyEDASymbol::parse(&pin_symbol(10.0)).unwrap().try_into().unwrap();
+        assert!(find_duplicate_pin_numbers(&symbol).is_empty());
+    }
+
+    #[test]
+    fn stacked_pins_are_not_duplicates() {
+        let data = pin_symbol(0.0).replace(r#""NUMBER","2""#, r#""NUMBER","1""#);
+        let symbol: Symbol = EasyEDASymbol::parse(&data).unwrap().try_into().unwrap();
+        assert!(find_duplicate_pin_numbers(&symbol).is_empty());
+    }
+
+    #[test]
+    fn pin_rotation_snapping() {
+        assert_eq!(snap_pin_rotation(0.0), 0.0);
+        assert_eq!(snap_pin_rotation(180.0), 180.0);
+        assert_eq!(snap_pin_rotation(-90.0), 270.0);
+        assert_eq!(snap_pin_rotation(359.6), 0.0);
+        assert_eq!(snap_pin_rotation(91.0), 90.0);
+    }
+
+    #[test]
+    fn diagonal_pin_is_snapped_to_a_right_angle() {
+        let data = pin_symbol(10.0).replace(r#""PIN","p0",1,null,-20,0,10,0"#, r#""PIN","p0",1,null,-20,0,10,45"#);
+        let symbol: Symbol = EasyEDASymbol::parse(&data).unwrap().try_into().unwrap();
+
+        let pin = symbol.pins.iter().find(|p| p.number.as_deref() == Some("0")).unwrap();
+        let angle = pin.position.angle.unwrap();
+        assert!(angle == 0.0 || angle == 90.0);
+        // Far enough from the original angle that the conversion logs a warning about it
+        assert!(get_angle_difference(angle, 45.0) > PIN_ROTATION_SNAP_THRESHOLD);
+    }
+
+    #[test]
+    fn text_attribute_references_become_kicad_variables() {
+        let data = format!("{}\n{}", CIRCLE_SYMBOL, r#"["TEXT","e2",0,0,0,"{value} / {Designator} {custom}",null]"#);
+        let symbol: Symbol = EasyEDASymbol::parse(&data).unwrap().try_into().unwrap();
+
+        assert_eq!(symbol.texts.len(), 1);
+        assert_eq!(symbol.texts[0].text, "${VALUE} / ${REFERENCE} {custom}");
+        assert_eq!(convert_text_variables("${VALUE}"), "${VALUE}");
+    }
+
+    #[test]
+    fn line_style_maps_to_stroke_type() {
+        let data = CIRCLE_SYMBOL.replace(r#"["LINESTYLE","st1",null,null,null,null]"#, r#"["LINESTYLE","st1",null,1,null,null]"#);
+        let symbol: Symbol = EasyEDASymbol::parse(&data).unwrap().try_into().unwrap();
+        assert!(matches!(symbol.circles[0].stroke.dash, Some(StrokeType::Dash)));
+
+        let symbol: Symbol = EasyEDASymbol::parse(CIRCLE_SYMBOL).unwrap().try_into().unwrap();
+        assert!(matches!(symbol.circles[0].stroke.dash, Some(StrokeType::Solid)));
+    }
+
+    #[test]
+    fn head_origin_is_subtracted_from_coordinates() {
+        let data = pin_symbol(10.0).replace(r#""originX":0,"originY":0"#, r#""originX":100,"originY":-50"#);
+        let symbol: Symbol = EasyEDASymbol::parse(&data).unwrap().try_into().unwrap();
+
+        let center = &symbol.circles[0].center;
+        assert!((center.x - -100.0 * 0.254).abs() < 1e-4);
+        assert!((center.y - 50.0 * 0.254).abs() < 1e-4);
+
+        let pin = &symbol.pins[0].position;
+        assert!((pin.x - -120.0 * 0.254).abs() < 1e-4);
+        assert!((pin.y - 50.0 * 0.254).abs() < 1e-4);
+    }
+}
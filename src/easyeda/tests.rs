@@ -8,15 +8,15 @@ macro_rules! test_component {
         fn $test_name() -> anyhow::Result<()> {
             let (symbol, footprint) = easyeda::tests::download_component($lcsc_code)?;
 
-            let kicad_symbol_lib: SymbolLib = symbol.into();
+            let kicad_symbol_lib: SymbolLib = symbol.try_into()?;
             let item = kicad_symbol_lib.serialize();
             let tokens = KiCadParser::generate_tokens(&item);
-            let sym_string = KiCadParser::stringify_tokens::<SymbolLib>(&tokens);
+            let _sym_string = KiCadParser::stringify_tokens::<SymbolLib>(&tokens);
 
-            let kicad_footprint: FootprintLibrary = footprint.into();
+            let kicad_footprint: FootprintLibrary = footprint.try_into()?;
             let item = kicad_footprint.serialize();
             let tokens = KiCadParser::generate_tokens(&item);
-            let fp_string = KiCadParser::stringify_tokens::<SymbolLib>(&tokens);
+            let _fp_string = KiCadParser::stringify_tokens::<FootprintLibrary>(&tokens);
 
             Ok(())
         }
@@ -74,7 +74,7 @@ pub fn download_component(code: &str) -> anyhow::Result<(EasyEDASymbol, EasyEDAF
         format!("https://pro.easyeda.com/api/eda/product/search?keyword={}&currPage=1&pageSize=1", code)
     ).call()?;
 
-    let body_string = response.into_body().read_to_string()?;
+    let body_string = crate::read_response_body(response)?;
     let json = serde_json::from_str::<serde_json::Value>(&body_string)?;
     let data = &json["result"]["productList"][0]["device_info"];
     let mut symbol = EasyEDASymbol::parse(&data["symbol_info"]["dataStr"].as_str().unwrap())?;
@@ -1,2 +1,5 @@
 pub mod syntax;
-pub mod model;
\ No newline at end of file
+pub mod model;
+pub mod uuid;
+#[cfg(test)]
+mod tests;
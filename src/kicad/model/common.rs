@@ -1,6 +1,43 @@
 use crate::kicad::model::symbol_library::{Color, StrokeType};
 use crate::kicad::syntax::{PositionPreference, SyntaxArgument, SyntaxItem, SyntaxItemSerializable};
 
+/// The KiCad release targeted by the generated files
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, clap::ValueEnum)]
+pub enum KiCadVersion {
+    #[value(name = "7")]
+    V7,
+    #[default]
+    #[value(name = "8")]
+    V8,
+}
+
+impl KiCadVersion {
+    pub fn footprint_version(&self) -> usize {
+        match self {
+            KiCadVersion::V7 => 20221018,
+            KiCadVersion::V8 => 20240108,
+        }
+    }
+
+    /// Reference/Value became regular properties in KiCad 8, older versions use `fp_text`
+    pub fn has_footprint_field_properties(&self) -> bool {
+        *self >= KiCadVersion::V8
+    }
+
+    /// Pads carry their own teardrop settings since KiCad 8
+    pub fn has_pad_teardrops(&self) -> bool {
+        *self >= KiCadVersion::V8
+    }
+
+    /// Environment variable pointing to the global 3D model library of this KiCad version
+    pub fn model_dir_variable(&self) -> &'static str {
+        match self {
+            KiCadVersion::V7 => "KICAD7_3DMODEL_DIR",
+            KiCadVersion::V8 => "KICAD8_3DMODEL_DIR",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct StrokeDefinition {
     pub width: f32,
@@ -123,6 +160,15 @@ impl SyntaxItemSerializable for StrokeDefinition {
     }
 }
 
+impl Position {
+    /// Wraps an angle in degrees into the [0, 360) range
+    pub fn normalize_angle(angle: f32) -> f32 {
+        let angle = angle.rem_euclid(360.0);
+        // rem_euclid can round up to exactly 360 for tiny negative angles, and keeps the sign of -0
+        if angle >= 360.0 || angle == 0.0 { 0.0 } else { angle }
+    }
+}
+
 impl SyntaxItemSerializable for Position {
     fn serialize(&self) -> SyntaxItem {
         SyntaxItem {
@@ -139,7 +185,7 @@ impl SyntaxItemSerializable for Position {
     fn deserialize(syntax: &SyntaxItem) -> Self {
         let x = syntax.arguments.get(0).unwrap().get_number();
         let y = syntax.arguments.get(1).unwrap().get_number();
-        let rotation = syntax.arguments.get(2).and_then(|r| Some(r.get_number()));
+        let rotation = syntax.arguments.get(2).map(|r| Position::normalize_angle(r.get_number()));
 
         Self { x, y, angle: rotation }
     }
@@ -201,11 +247,7 @@ impl SyntaxItemSerializable for TextEffect {
     }
 
     fn deserialize(syntax: &SyntaxItem) -> Self {
-        let mut hide = if let Some(arg) = syntax.arguments.first() {
-            arg.get_string() == "hide"
-        } else {
-            false
-        };
+        let mut hide = syntax.arguments.iter().any(|arg| arg.get_string() == "hide");
 
         let mut font = Font::default();
         let mut justify = TextJustify::default();
@@ -228,15 +270,16 @@ impl SyntaxItemSerializable for TextJustify {
         SyntaxItem {
             name: "justify".into(),
             children: Vec::new(),
+            // Same order as KiCad writes them: horizontal, vertical, mirror
             arguments: vec![
-                self.justify_vertical.as_ref().and_then(|j| Some(SyntaxArgument::Identifier(match j {
-                    TextJustifyVertical::Top => "top".into(),
-                    TextJustifyVertical::Bottom => "bottom".into(),
-                }, PositionPreference::None))),
                 self.justify_horizontal.as_ref().and_then(|j| Some(SyntaxArgument::Identifier(match j {
                     TextJustifyHorizontal::Left => "left".into(),
                     TextJustifyHorizontal::Right => "right".into(),
                 }, PositionPreference::None))),
+                self.justify_vertical.as_ref().and_then(|j| Some(SyntaxArgument::Identifier(match j {
+                    TextJustifyVertical::Top => "top".into(),
+                    TextJustifyVertical::Bottom => "bottom".into(),
+                }, PositionPreference::None))),
                 match self.mirror {
                     true => Some(SyntaxArgument::Identifier("mirror".into(), PositionPreference::None)),
                     false => None,
@@ -274,8 +317,8 @@ impl SyntaxItemSerializable for Font {
         SyntaxItem {
             name: "font".into(),
             arguments: vec![
-                if self.italic { Some(SyntaxArgument::Identifier("italic".into(), PositionPreference::None)) } else { None },
                 if self.bold { Some(SyntaxArgument::Identifier("bold".into(), PositionPreference::None)) } else { None },
+                if self.italic { Some(SyntaxArgument::Identifier("italic".into(), PositionPreference::None)) } else { None },
             ].iter().filter(|&o| o.is_some()).map(|o| o.as_ref().unwrap().clone()).collect(),
             children: vec![
                 Some(self.size.serialize()),
@@ -332,3 +375,75 @@ impl SyntaxItemSerializable for FontSize {
         Self { width, height }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kicad::syntax::KiCadParser;
+
+    fn parse(input: &str) -> SyntaxItem {
+        KiCadParser::parse_syntax_item(&KiCadParser::tokenize(input))
+    }
+
+    #[test]
+    fn text_effect_flags_round_trip() {
+        let horizontal = [None, Some(TextJustifyHorizontal::Left), Some(TextJustifyHorizontal::Right)];
+        let vertical = [None, Some(TextJustifyVertical::Top), Some(TextJustifyVertical::Bottom)];
+        for flags in 0..16 {
+            for justify_horizontal in &horizontal {
+                for justify_vertical in &vertical {
+                    let mut effect = TextEffect::default();
+                    effect.font.bold = flags & 1 != 0;
+                    effect.font.italic = flags & 2 != 0;
+                    effect.justify.mirror = flags & 4 != 0;
+                    effect.hide = flags & 8 != 0;
+                    effect.justify.justify_horizontal = justify_horizontal.clone();
+                    effect.justify.justify_vertical = justify_vertical.clone();
+
+                    let serialized = effect.serialize();
+                    let reparsed = TextEffect::deserialize(&KiCadParser::parse_syntax_item(&KiCadParser::generate_tokens(&serialized)));
+
+                    assert_eq!(reparsed.font.bold, effect.font.bold);
+                    assert_eq!(reparsed.font.italic, effect.font.italic);
+                    assert_eq!(reparsed.justify.mirror, effect.justify.mirror);
+                    assert_eq!(reparsed.hide, effect.hide);
+                    assert_eq!(reparsed.serialize().to_string(), serialized.to_string());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn text_effect_keeps_kicad_flag_order() {
+        let input = "(effects (font (size 1 1) bold italic) (justify left bottom mirror) hide)";
+        let effect = TextEffect::deserialize(&parse(input));
+        assert_eq!(effect.serialize().to_string(), parse(input).to_string());
+
+        // KiCad 8 writes the flags as (name yes) children
+        let effect = TextEffect::deserialize(&parse("(effects (font (size 1 1) (bold yes) (italic yes)) (justify left bottom mirror) (hide yes))"));
+        assert!(effect.font.bold && effect.font.italic && effect.justify.mirror && effect.hide);
+    }
+
+    #[test]
+    fn angles_are_normalized() {
+        assert_eq!(Position::normalize_angle(-90.0), 270.0);
+        assert_eq!(Position::normalize_angle(450.0), 90.0);
+        assert_eq!(Position::normalize_angle(-270.0), 90.0);
+        assert_eq!(Position::normalize_angle(360.0), 0.0);
+        assert!(Position::normalize_angle(-0.0).is_sign_positive());
+    }
+
+    #[test]
+    fn deserialized_angle_is_normalized() {
+        let position = Position::deserialize(&SyntaxItem {
+            name: "at".into(),
+            arguments: vec![
+                SyntaxArgument::Number(1.0, PositionPreference::None),
+                SyntaxArgument::Number(2.0, PositionPreference::None),
+                SyntaxArgument::Number(-270.0, PositionPreference::None),
+            ],
+            children: vec![],
+        });
+        assert_eq!(position.angle, Some(90.0));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::kicad::model::lib_table::{self, LibTableEntry};
 use crate::kicad::syntax::{PositionPreference, SyntaxArgument, SyntaxItem, SyntaxItemSerializable, TopLevelSerializable};
 
 #[derive(Debug, Default)]
@@ -16,6 +17,24 @@ pub struct FootprintLibTableItem {
     pub disabled: bool,
 }
 
+impl FootprintLibTable {
+    /// Adds the library, or refreshes the uri and description of an existing entry with the same name.
+    /// Returns whether the table was changed.
+    pub fn upsert_library(&mut self, library: FootprintLibTableItem) -> bool {
+        lib_table::upsert_library(&mut self.libraries, library)
+    }
+}
+
+impl LibTableEntry for FootprintLibTableItem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn location_mut(&mut self) -> (&mut String, &mut String) {
+        (&mut self.uri, &mut self.description)
+    }
+}
+
 impl TopLevelSerializable for FootprintLibTable {
     fn get_same_line_identifiers() -> Vec<String> {
         Vec::from([
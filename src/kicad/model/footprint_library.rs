@@ -1,5 +1,5 @@
-use crate::kicad::model::common::{Position, StrokeDefinition, TextEffect};
-use crate::kicad::model::graphical::{GraphicAnnotationBox, GraphicArc, GraphicCircle, GraphicCurve, GraphicLine, GraphicPolygon, GraphicRectangle};
+use crate::kicad::model::common::{KiCadVersion, Position, StrokeDefinition, TextEffect};
+use crate::kicad::model::graphical::{GraphicAnnotationBox, GraphicArc, GraphicCircle, GraphicCurve, GraphicLine, GraphicPolygon, GraphicRectangle, GraphicText};
 use crate::kicad::syntax::{PositionPreference, SyntaxArgument, SyntaxItem, SyntaxItemSerializable, TopLevelSerializable};
 use chrono::{DateTime, TimeZone, Utc};
 use itertools::Itertools;
@@ -20,8 +20,11 @@ pub struct FootprintLibrary {
     pub edit_timestamp: Option<DateTime<Utc>>,
     pub model: Option<FootprintModel>,
     pub attributes: Option<FootprintAttributes>,
+    /// Groups of pads that are allowed to short their nets, each as a comma separated list of pad numbers
+    pub net_tie_pad_groups: Vec<String>,
     pub properties: Vec<FootprintProperty>,
     pub solder_mask_margin: Option<f32>,
+    pub clearance: Option<f32>,
 
     pub lines: Vec<FootprintLine>,
     pub polygons: Vec<FootprintPolygon>,
@@ -32,10 +35,35 @@ pub struct FootprintLibrary {
     pub texts: Vec<FootprintText>,
     pub pads: Vec<FootprintPad>,
     pub zones: Vec<FootprintZone>,
+    pub images: Vec<FootprintImage>,
 
     pub zone_connect: Option<ZoneConnectMode>,
 }
 
+/// The feature of a footprint that should be placed at its origin
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum FootprintAnchor {
+    /// Keep the origin used by EasyEDA
+    #[default]
+    AsIs,
+    /// Center of the bounding box of all pads
+    CenterOfPads,
+    /// Center of the component body on the fabrication layer
+    CenterOfBody,
+    /// Center of the pad numbered 1
+    Pad1,
+}
+
+/// Axis-aligned extents of a footprint, in millimeters
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox2D {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
 #[derive(Debug)]
 pub struct FootprintText {
     pub text_type: FootprintTextType,
@@ -114,6 +142,16 @@ pub struct FootprintRectangle {
     pub locked: bool,
 }
 
+/// An embedded bitmap, `data` holds the base64 encoded PNG
+#[derive(Debug)]
+pub struct FootprintImage {
+    pub position: Scalar2D,
+    pub layer: PcbLayer,
+    pub scale: Option<f32>,
+    pub data: String,
+    pub uuid: Option<String>,
+}
+
 #[allow(unused)]
 #[derive(Debug)]
 // https://dev-docs.kicad.org/en/file-formats/sexpr-intro/index.html#_footprint_pad
@@ -124,6 +162,7 @@ pub struct FootprintPad {
     pub position: Position,
     pub locked: bool,
     pub size: Scalar2D,
+    pub rect_delta: Option<Scalar2D>,
     pub drill: Option<DrillDefinition>,
     pub layers: Vec<PcbLayer>,
     pub property: Option<PadProperty>,
@@ -146,6 +185,37 @@ pub struct FootprintPad {
     // 26 - thermal_gap
     pub options: Option<FootprintPadOptions>,
     pub primitives: Option<FootprintPadPrimitives>,
+    pub teardrops: Option<PadTeardrops>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PadTeardrops {
+    pub best_length_ratio: f32,
+    pub max_length: f32,
+    pub best_width_ratio: f32,
+    pub max_width: f32,
+    pub curve_points: u32,
+    pub filter_ratio: f32,
+    pub enabled: bool,
+    pub allow_two_segments: bool,
+    pub prefer_zone_connections: bool,
+}
+
+impl Default for PadTeardrops {
+    /// The defaults KiCad uses for new teardrops
+    fn default() -> Self {
+        Self {
+            best_length_ratio: 0.5,
+            max_length: 1.0,
+            best_width_ratio: 1.0,
+            max_width: 2.0,
+            curve_points: 0,
+            filter_ratio: 0.9,
+            enabled: true,
+            allow_two_segments: true,
+            prefer_zone_connections: true,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -163,6 +233,7 @@ pub struct FootprintPadPrimitives {
     pub curves: Vec<GraphicCurve>,
     pub polygons: Vec<GraphicPolygon>,
     pub annotation_boxes: Vec<GraphicAnnotationBox>,
+    pub texts: Vec<GraphicText>,
     pub width: Option<f32>,
     pub fill: Option<bool>,
 }
@@ -211,8 +282,7 @@ pub enum PadProperty {
     Castellated,
 }
 
-#[allow(unused)]
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum PadChamfer {
     TopLeft,
     TopRight,
@@ -274,7 +344,7 @@ pub struct FootprintProperty {
     pub effects: TextEffect,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Scalar3D {
     identifier_name: String,
     pub x: f32,
@@ -282,7 +352,7 @@ pub struct Scalar3D {
     pub z: f32,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Scalar2D {
     identifier_name: String,
     pub x: f32,
@@ -305,11 +375,25 @@ pub struct FootprintZone {
     pub keepout_settings: Option<FootprintZoneKeepoutSettings>,
     pub fill_settings: FootprintZoneFillSettings,
     pub coordinate_points: GraphicPolygon,
-    // todo zone_fill_polygons - 14
-    // todo zone_fill_segments - 15
+    pub filled_polygons: Vec<FootprintZoneFilledPolygon>,
+    pub fill_segments: Vec<FootprintZoneFillSegments>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct FootprintZoneFilledPolygon {
+    pub layer: Option<PcbLayer>,
+    pub island: bool,
+    pub points: Vec<Scalar2D>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FootprintZoneFillSegments {
+    pub layer: Option<PcbLayer>,
+    // Each segment is stored as its own "pts" list of two points
+    pub segments: Vec<Vec<Scalar2D>>,
+}
+
+#[derive(Debug, Clone)]
 pub struct FootprintZoneKeepoutSettings {
     pub tracks_allowed: bool,
     pub vias_allowed: bool,
@@ -585,6 +669,25 @@ impl PcbLayer {
         Self::parse(&str)
     }
 
+    /// Returns the inner copper layer with the given 1-based index
+    pub fn inner_copper(index: usize) -> Option<PcbLayer> {
+        (1..=30).contains(&index).then(|| Self::parse(&format!("In{index}.Cu")))
+    }
+
+    /// Returns the back side counterpart of a front layer, other layers are returned unchanged
+    pub fn to_back(&self) -> PcbLayer {
+        match self {
+            PcbLayer::FCu => PcbLayer::BCu,
+            PcbLayer::FAdhes => PcbLayer::BAdhes,
+            PcbLayer::FPaste => PcbLayer::BPaste,
+            PcbLayer::FSilkS => PcbLayer::BSilkS,
+            PcbLayer::FMask => PcbLayer::BMask,
+            PcbLayer::FCrtYd => PcbLayer::BCrtYd,
+            PcbLayer::FFab => PcbLayer::BFab,
+            layer => *layer,
+        }
+    }
+
     pub fn all_copper() -> Vec<PcbLayer> {
         vec![
             PcbLayer::FCu,
@@ -725,6 +828,13 @@ impl SyntaxItemSerializable for FootprintLibrary {
         if let Some(attributes) = &self.attributes {
             children.push(attributes.serialize());
         }
+        if !self.net_tie_pad_groups.is_empty() {
+            children.push(SyntaxItem {
+                name: "net_tie_pad_groups".into(),
+                arguments: self.net_tie_pad_groups.iter().map(|g| SyntaxArgument::QuotedString(g.clone(), PositionPreference::None)).collect(),
+                children: vec![],
+            });
+        }
 
         if let Some(edit_timestamp) = &self.edit_timestamp {
             let ts_hex = format!("{:X}", edit_timestamp.timestamp());
@@ -741,7 +851,7 @@ impl SyntaxItemSerializable for FootprintLibrary {
             children.push(SyntaxItem::from_single_argument("generator", SyntaxArgument::Identifier(generator.clone(), PositionPreference::None)));
         }
         if let Some(generator_version) = &self.generator_version {
-            children.push(SyntaxItem::from_single_argument("generator_version", SyntaxArgument::Identifier(generator_version.clone(), PositionPreference::None)));
+            children.push(SyntaxItem::from_single_argument("generator_version", SyntaxArgument::QuotedString(generator_version.clone(), PositionPreference::None)));
         }
 
         children.extend(self.texts.iter().map(|item| item.serialize()));
@@ -752,11 +862,23 @@ impl SyntaxItemSerializable for FootprintLibrary {
         children.extend(self.rectangles.iter().map(|item| item.serialize()));
         children.extend(self.pads.iter().map(|item| item.serialize()));
         children.extend(self.zones.iter().map(|item| item.serialize()));
-        children.extend(self.properties.iter().map(|item| item.serialize()));
+        children.extend(self.images.iter().map(|item| item.serialize()));
+        // Before KiCad 8, footprint properties were plain key/value pairs without any placement
+        let has_field_properties = self.version.is_none_or(|v| v >= KiCadVersion::V8.footprint_version());
+        children.extend(self.properties.iter().map(|item| match has_field_properties {
+            true => item.serialize(),
+            false => SyntaxItem::from_arguments("property", vec![
+                SyntaxArgument::QuotedString(item.key.clone(), PositionPreference::None),
+                SyntaxArgument::QuotedString(item.value.clone().unwrap_or_default(), PositionPreference::None),
+            ]),
+        }));
 
         if let Some(solder_mask_margin) = &self.solder_mask_margin {
             children.push(SyntaxItem::from_single_argument("solder_mask_margin", SyntaxArgument::Number(*solder_mask_margin, PositionPreference::None)));
         }
+        if let Some(clearance) = &self.clearance {
+            children.push(SyntaxItem::from_single_argument("clearance", SyntaxArgument::Number(*clearance, PositionPreference::None)));
+        }
         if let Some(zone_connect) = &self.zone_connect {
             children.push(SyntaxItem::from_single_argument("zone_connect", SyntaxArgument::Number(match zone_connect {
                 ZoneConnectMode::NotConnected => 0,
@@ -787,6 +909,7 @@ impl SyntaxItemSerializable for FootprintLibrary {
             edit_timestamp: None,
             model: None,
             attributes: None,
+            net_tie_pad_groups: Vec::new(),
             lines: Vec::new(),
             arcs: Vec::new(),
             texts: Vec::new(),
@@ -795,8 +918,10 @@ impl SyntaxItemSerializable for FootprintLibrary {
             rectangles: Vec::new(),
             pads: Vec::new(),
             zones: Vec::new(),
+            images: Vec::new(),
             properties: Vec::new(),
             solder_mask_margin: None,
+            clearance: None,
             zone_connect: None,
         };
 
@@ -817,6 +942,7 @@ impl SyntaxItemSerializable for FootprintLibrary {
                 "fp_circle" => library.circles.push(FootprintCircle::deserialize(child)),
                 "fp_rect" => library.rectangles.push(FootprintRectangle::deserialize(child)),
                 "zone" => library.zones.push(FootprintZone::deserialize(child)),
+                "image" => library.images.push(FootprintImage::deserialize(child)),
                 "zone_connect" => library.zone_connect = Some(child.get_named_child("zone_connection").map(|s| match s.arguments.first().unwrap().get_number() as u8 {
                     0 => ZoneConnectMode::NotConnected,
                     1 => ZoneConnectMode::ThermalRelief,
@@ -826,9 +952,11 @@ impl SyntaxItemSerializable for FootprintLibrary {
                 "pad" => library.pads.push(FootprintPad::deserialize(child)),
                 "model" => { library.model.replace(FootprintModel::deserialize(child)); }
                 "attr" => { library.attributes.replace(FootprintAttributes::deserialize(child)); }
+                "net_tie_pad_groups" => library.net_tie_pad_groups = child.arguments.iter().map(|a| a.get_string()).collect(),
                 "property" => library.properties.push(FootprintProperty::deserialize(child)),
 
                 "solder_mask_margin" => library.solder_mask_margin = Some(child.arguments.first().unwrap().get_number()),
+                "clearance" => library.clearance = Some(child.arguments.first().unwrap().get_number()),
 
                 _ => panic!("Unsupported child item type in Footprint: {}", child.name),
             }
@@ -1028,17 +1156,53 @@ impl SyntaxItemSerializable for Scalar3D {
 }
 
 impl Scalar2D {
-    pub fn new(identifier: &str, x: f32, y: f32) -> Self {
+    fn new(identifier: &str, x: f32, y: f32) -> Self {
         Self {
             identifier_name: identifier.to_string(),
             x,
             y,
         }
     }
+
+    pub fn start(x: f32, y: f32) -> Self {
+        Self::new("start", x, y)
+    }
+
+    pub fn mid(x: f32, y: f32) -> Self {
+        Self::new("mid", x, y)
+    }
+
+    pub fn end(x: f32, y: f32) -> Self {
+        Self::new("end", x, y)
+    }
+
+    pub fn center(x: f32, y: f32) -> Self {
+        Self::new("center", x, y)
+    }
+
+    pub fn xy(x: f32, y: f32) -> Self {
+        Self::new("xy", x, y)
+    }
+
+    pub fn size(x: f32, y: f32) -> Self {
+        Self::new("size", x, y)
+    }
+
+    pub fn offset(x: f32, y: f32) -> Self {
+        Self::new("offset", x, y)
+    }
+
+    pub fn rect_delta(x: f32, y: f32) -> Self {
+        Self::new("rect_delta", x, y)
+    }
+
+    pub fn at(x: f32, y: f32) -> Self {
+        Self::new("at", x, y)
+    }
 }
 
 impl Scalar3D {
-    pub fn new(identifier: &str, x: f32, y: f32, z: f32) -> Self {
+    fn new(identifier: &str, x: f32, y: f32, z: f32) -> Self {
         Self {
             identifier_name: identifier.to_string(),
             x,
@@ -1046,6 +1210,14 @@ impl Scalar3D {
             z,
         }
     }
+
+    pub fn at(x: f32, y: f32, z: f32) -> Self {
+        Self::new("at", x, y, z)
+    }
+
+    pub fn xyz(x: f32, y: f32, z: f32) -> Self {
+        Self::new("xyz", x, y, z)
+    }
 }
 
 impl SyntaxItemSerializable for Scalar2D {
@@ -1091,7 +1263,7 @@ impl SyntaxItemSerializable for FootprintLine {
 
         SyntaxItem {
             name: "fp_line".into(),
-            arguments: Vec::new(),
+            arguments: locked_arguments(self.locked),
             children,
         }
     }
@@ -1104,7 +1276,7 @@ impl SyntaxItemSerializable for FootprintLine {
             width: None,
             stroke: syntax.get_named_child("stroke").and_then(|child| Some(StrokeDefinition::deserialize(child))),
             uuid: syntax.get_named_child("uuid").and_then(|child| Some(child.arguments.first().unwrap().get_string())),
-            locked: syntax.get_named_child("locked").is_some(),
+            locked: is_locked(syntax),
         };
 
         for child in &syntax.children {
@@ -1154,7 +1326,7 @@ impl SyntaxItemSerializable for FootprintPolygon {
 
         SyntaxItem {
             name: "fp_poly".into(),
-            arguments: Vec::new(),
+            arguments: locked_arguments(self.locked),
             children,
         }
     }
@@ -1167,7 +1339,7 @@ impl SyntaxItemSerializable for FootprintPolygon {
             fill: None,
             stroke: syntax.get_named_child("stroke").and_then(|child| Some(StrokeDefinition::deserialize(child))),
             uuid: syntax.get_named_child("uuid").and_then(|child| Some(child.arguments.first().unwrap().get_string())),
-            locked: syntax.get_named_child("locked").is_some(),
+            locked: is_locked(syntax),
         };
 
         for child in &syntax.children {
@@ -1213,7 +1385,7 @@ impl SyntaxItemSerializable for FootprintCircle {
 
         SyntaxItem {
             name: "fp_circle".into(),
-            arguments: Vec::new(),
+            arguments: locked_arguments(self.locked),
             children,
         }
     }
@@ -1227,7 +1399,7 @@ impl SyntaxItemSerializable for FootprintCircle {
             fill: None,
             stroke: syntax.get_named_child("stroke").and_then(|child| Some(StrokeDefinition::deserialize(child))),
             uuid: syntax.get_named_child("uuid").and_then(|child| Some(child.arguments.first().unwrap().get_string())),
-            locked: syntax.get_named_child("locked").is_some(),
+            locked: is_locked(syntax),
         };
 
         for child in &syntax.children {
@@ -1278,7 +1450,7 @@ impl SyntaxItemSerializable for FootprintArc {
 
         SyntaxItem {
             name: "fp_arc".into(),
-            arguments: Vec::new(),
+            arguments: locked_arguments(self.locked),
             children,
         }
     }
@@ -1293,7 +1465,7 @@ impl SyntaxItemSerializable for FootprintArc {
             angle: None,
             stroke: syntax.get_named_child("stroke").and_then(|child| Some(StrokeDefinition::deserialize(child))),
             uuid: syntax.get_named_child("uuid").and_then(|child| Some(child.arguments.first().unwrap().get_string())),
-            locked: syntax.get_named_child("locked").is_some(),
+            locked: is_locked(syntax),
         };
 
         for child in &syntax.children {
@@ -1341,7 +1513,7 @@ impl SyntaxItemSerializable for FootprintRectangle {
 
         SyntaxItem {
             name: "fp_rect".into(),
-            arguments: Vec::new(),
+            arguments: locked_arguments(self.locked),
             children,
         }
     }
@@ -1355,7 +1527,7 @@ impl SyntaxItemSerializable for FootprintRectangle {
             fill: None,
             stroke: syntax.get_named_child("stroke").and_then(|child| Some(StrokeDefinition::deserialize(child))),
             uuid: syntax.get_named_child("uuid").and_then(|child| Some(child.arguments.first().unwrap().get_string())),
-            locked: syntax.get_named_child("locked").is_some(),
+            locked: is_locked(syntax),
         };
 
         for child in &syntax.children {
@@ -1376,6 +1548,55 @@ impl SyntaxItemSerializable for FootprintRectangle {
     }
 }
 
+impl SyntaxItemSerializable for FootprintImage {
+    fn serialize(&self) -> SyntaxItem {
+        let mut children = vec![
+            self.position.serialize(),
+            SyntaxItem::from_single_argument("layer", SyntaxArgument::QuotedString(self.layer.to_string(), PositionPreference::None)),
+        ];
+
+        if let Some(scale) = self.scale {
+            children.push(SyntaxItem::from_single_argument("scale", SyntaxArgument::Number(scale, PositionPreference::None)));
+        }
+
+        if let Some(uuid) = &self.uuid {
+            children.push(SyntaxItem::from_single_argument("uuid", SyntaxArgument::QuotedString(uuid.clone(), PositionPreference::None)));
+        }
+
+        children.push(SyntaxItem::from_single_argument("data", SyntaxArgument::QuotedString(self.data.clone(), PositionPreference::None)));
+
+        SyntaxItem {
+            name: "image".into(),
+            arguments: Vec::new(),
+            children,
+        }
+    }
+
+    fn deserialize(syntax: &SyntaxItem) -> Self {
+        let mut image = Self {
+            position: Scalar2D::at(0.0, 0.0),
+            layer: PcbLayer::DwgsUser,
+            scale: None,
+            data: String::new(),
+            uuid: None,
+        };
+
+        for child in &syntax.children {
+            match child.name.as_str() {
+                "at" => image.position = Scalar2D::deserialize(child),
+                "layer" => image.layer = PcbLayer::from(child),
+                "scale" => image.scale = Some(child.arguments.first().unwrap().get_number()),
+                "uuid" => image.uuid = child.arguments.first().map(|a| a.get_string()),
+                // KiCad splits long bitmaps into several strings
+                "data" => image.data = child.arguments.iter().map(|a| a.get_string()).collect(),
+                _ => panic!("Unsupported child item type in FootprintImage: {}", child.name),
+            }
+        }
+
+        image
+    }
+}
+
 impl SyntaxItemSerializable for FootprintText {
     fn serialize(&self) -> SyntaxItem {
         let mut children = vec![
@@ -1454,6 +1675,9 @@ impl SyntaxItemSerializable for FootprintPad {
             self.size.serialize(),
         ];
 
+        if let Some(rect_delta) = &self.rect_delta {
+            children.push(rect_delta.serialize());
+        }
         if let Some(drill) = &self.drill {
             children.push(drill.serialize());
         }
@@ -1462,6 +1686,21 @@ impl SyntaxItemSerializable for FootprintPad {
         if let Some(round_rect_ratio) = self.round_rect_ratio {
             children.push(SyntaxItem::from_single_argument("roundrect_rratio", SyntaxArgument::Number(round_rect_ratio, PositionPreference::None)));
         }
+        if let Some(chamfer_ratio) = self.chamfer_ratio {
+            children.push(SyntaxItem::from_single_argument("chamfer_ratio", SyntaxArgument::Number(chamfer_ratio, PositionPreference::None)));
+        }
+        if !self.chamfer.is_empty() {
+            children.push(SyntaxItem {
+                name: "chamfer".into(),
+                arguments: self.chamfer.iter().map(|c| SyntaxArgument::Identifier(match c {
+                    PadChamfer::TopLeft => "top_left",
+                    PadChamfer::TopRight => "top_right",
+                    PadChamfer::BottomLeft => "bottom_left",
+                    PadChamfer::BottomRight => "bottom_right",
+                }.into(), PositionPreference::None)).collect(),
+                children: vec![],
+            });
+        }
         if let Some(solder_mask_margin) = self.solder_mask_margin {
             children.push(SyntaxItem::from_single_argument("solder_mask_margin", SyntaxArgument::Number(solder_mask_margin, PositionPreference::None)));
         }
@@ -1480,6 +1719,9 @@ impl SyntaxItemSerializable for FootprintPad {
         if let Some(primitives) = &self.primitives {
             children.push(primitives.serialize());
         }
+        if let Some(teardrops) = &self.teardrops {
+            children.push(teardrops.serialize());
+        }
         if let Some(zone_connect) = &self.zone_connection {
             children.push(SyntaxItem::from_single_argument("zone_connect", SyntaxArgument::Number(match zone_connect {
                 ZoneConnectMode::NotConnected => 0,
@@ -1504,7 +1746,7 @@ impl SyntaxItemSerializable for FootprintPad {
             }).into(), PositionPreference::None)));
         }
 
-        let arguments = vec![
+        let mut arguments = vec![
             match self.number.as_str() {
                 "" => SyntaxArgument::QuotedString("".into(), PositionPreference::Start),
                 str => SyntaxArgument::Identifier(str.into(), PositionPreference::Start),
@@ -1524,6 +1766,7 @@ impl SyntaxItemSerializable for FootprintPad {
                 PadShape::Custom => "custom",
             }.into(), PositionPreference::None),
         ];
+        arguments.extend(locked_arguments(self.locked));
 
         SyntaxItem {
             name: "pad".into(),
@@ -1553,6 +1796,7 @@ impl SyntaxItemSerializable for FootprintPad {
             },
             position: Position::deserialize(syntax.get_named_child("at").unwrap()),
             size: Scalar2D::deserialize(syntax.get_named_child("size").unwrap()),
+            rect_delta: syntax.get_named_child("rect_delta").map(Scalar2D::deserialize),
             drill: syntax.get_named_child("drill").map(|s| DrillDefinition::deserialize(s)),
             layers: syntax.get_named_child("layers").map(|s| Vec::<PcbLayer>::deserialize(s)).unwrap(),
             property: syntax.get_named_child("property").map(|s| match s.arguments.first().unwrap().get_string().as_str() {
@@ -1569,8 +1813,14 @@ impl SyntaxItemSerializable for FootprintPad {
                 .map(|s| s.arguments.first().unwrap().get_string() == "yes"),
             keep_end_layers: None,
             round_rect_ratio: syntax.get_named_child("roundrect_rratio").map(|s| s.arguments.get(0).unwrap().get_number()),
-            chamfer_ratio: None,
-            chamfer: vec![],
+            chamfer_ratio: syntax.get_named_child("chamfer_ratio").map(|s| s.arguments.get(0).unwrap().get_number()),
+            chamfer: syntax.get_named_child("chamfer").map(|s| s.arguments.iter().map(|a| match a.get_string().as_str() {
+                "top_left" => PadChamfer::TopLeft,
+                "top_right" => PadChamfer::TopRight,
+                "bottom_left" => PadChamfer::BottomLeft,
+                "bottom_right" => PadChamfer::BottomRight,
+                str => panic!("Unsupported pad chamfer corner: {}", str),
+            }).collect()).unwrap_or_default(),
             net: None,
             uuid: syntax.get_named_child("uuid").map(|s| s.arguments.get(0).unwrap().get_string()),
             pin_function: None,
@@ -1588,15 +1838,60 @@ impl SyntaxItemSerializable for FootprintPad {
             solder_paste_margin: syntax.get_named_child("solder_paste_margin").map(|s| s.arguments.get(0).unwrap().get_number()),
             solder_paste_margin_ratio: syntax.get_named_child("solder_paste_margin_ratio").map(|s| s.arguments.get(0).unwrap().get_number()),
             clearance: None,
-            locked: false,
+            locked: is_locked(syntax),
             options: syntax.get_named_child("options").map(|s| FootprintPadOptions::deserialize(s)),
             primitives: syntax.get_named_child("primitives").map(|s| FootprintPadPrimitives::deserialize(s)),
+            teardrops: syntax.get_named_child("teardrops").map(PadTeardrops::deserialize),
         };
 
         pad
     }
 }
 
+impl SyntaxItemSerializable for PadTeardrops {
+    fn serialize(&self) -> SyntaxItem {
+        let yes_no = |value: bool| SyntaxArgument::Identifier((if value { "yes" } else { "no" }).into(), PositionPreference::None);
+
+        SyntaxItem {
+            name: "teardrops".into(),
+            arguments: vec![],
+            children: vec![
+                SyntaxItem::from_single_argument("best_length_ratio", SyntaxArgument::Number(self.best_length_ratio, PositionPreference::None)),
+                SyntaxItem::from_single_argument("max_length", SyntaxArgument::Number(self.max_length, PositionPreference::None)),
+                SyntaxItem::from_single_argument("best_width_ratio", SyntaxArgument::Number(self.best_width_ratio, PositionPreference::None)),
+                SyntaxItem::from_single_argument("max_width", SyntaxArgument::Number(self.max_width, PositionPreference::None)),
+                SyntaxItem::from_single_argument("curve_points", SyntaxArgument::Number(self.curve_points as f32, PositionPreference::None)),
+                SyntaxItem::from_single_argument("filter_ratio", SyntaxArgument::Number(self.filter_ratio, PositionPreference::None)),
+                SyntaxItem::from_single_argument("enabled", yes_no(self.enabled)),
+                SyntaxItem::from_single_argument("allow_two_segments", yes_no(self.allow_two_segments)),
+                SyntaxItem::from_single_argument("prefer_zone_connections", yes_no(self.prefer_zone_connections)),
+            ],
+        }
+    }
+
+    fn deserialize(syntax: &SyntaxItem) -> Self {
+        let mut teardrops = Self::default();
+
+        for child in &syntax.children {
+            let argument = child.arguments.first().unwrap();
+            match child.name.as_str() {
+                "best_length_ratio" => teardrops.best_length_ratio = argument.get_number(),
+                "max_length" => teardrops.max_length = argument.get_number(),
+                "best_width_ratio" => teardrops.best_width_ratio = argument.get_number(),
+                "max_width" => teardrops.max_width = argument.get_number(),
+                "curve_points" => teardrops.curve_points = argument.get_number() as u32,
+                "filter_ratio" => teardrops.filter_ratio = argument.get_number(),
+                "enabled" => teardrops.enabled = argument.get_string() == "yes",
+                "allow_two_segments" => teardrops.allow_two_segments = argument.get_string() == "yes",
+                "prefer_zone_connections" => teardrops.prefer_zone_connections = argument.get_string() == "yes",
+                _ => panic!("Unsupported child item type in PadTeardrops: {}", child.name),
+            }
+        }
+
+        teardrops
+    }
+}
+
 impl SyntaxItemSerializable for FootprintZone {
     fn serialize(&self) -> SyntaxItem {
         let mut children = vec![
@@ -1650,6 +1945,9 @@ impl SyntaxItemSerializable for FootprintZone {
         polygon.name = "polygon".to_string();
         children.push(polygon);
 
+        children.extend(self.filled_polygons.iter().map(|p| p.serialize()));
+        children.extend(self.fill_segments.iter().map(|s| s.serialize()));
+
         SyntaxItem {
             name: "zone".into(),
             arguments: vec![],
@@ -1679,6 +1977,70 @@ impl SyntaxItemSerializable for FootprintZone {
             keepout_settings: syntax.get_named_child("keepout").map(|p| FootprintZoneKeepoutSettings::deserialize(p)),
             fill_settings: syntax.get_named_child("fill").map(|p| FootprintZoneFillSettings::deserialize(p)).unwrap(),
             coordinate_points: syntax.get_named_child("polygon").map(|p| GraphicPolygon::deserialize(p)).unwrap(),
+            filled_polygons: syntax.children.iter().filter(|c| c.name == "filled_polygon").map(FootprintZoneFilledPolygon::deserialize).collect(),
+            fill_segments: syntax.children.iter().filter(|c| c.name == "fill_segments").map(FootprintZoneFillSegments::deserialize).collect(),
+        }
+    }
+}
+
+impl SyntaxItemSerializable for FootprintZoneFilledPolygon {
+    fn serialize(&self) -> SyntaxItem {
+        let mut children = vec![];
+        if let Some(layer) = &self.layer {
+            children.push(layer.serialize());
+        }
+        if self.island {
+            children.push(SyntaxItem::from_arguments("island", vec![]));
+        }
+        children.push(SyntaxItem {
+            name: "pts".into(),
+            arguments: vec![],
+            children: self.points.iter().map(|point| point.serialize()).collect(),
+        });
+
+        SyntaxItem {
+            name: "filled_polygon".into(),
+            arguments: vec![],
+            children,
+        }
+    }
+
+    fn deserialize(syntax: &SyntaxItem) -> Self {
+        Self {
+            layer: syntax.get_named_child("layer").map(PcbLayer::deserialize),
+            island: syntax.get_named_child("island").is_some(),
+            points: syntax.get_named_child("pts")
+                .map(|pts| pts.children.iter().map(Scalar2D::deserialize).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl SyntaxItemSerializable for FootprintZoneFillSegments {
+    fn serialize(&self) -> SyntaxItem {
+        let mut children = vec![];
+        if let Some(layer) = &self.layer {
+            children.push(layer.serialize());
+        }
+        children.extend(self.segments.iter().map(|segment| SyntaxItem {
+            name: "pts".into(),
+            arguments: vec![],
+            children: segment.iter().map(|point| point.serialize()).collect(),
+        }));
+
+        SyntaxItem {
+            name: "fill_segments".into(),
+            arguments: vec![],
+            children,
+        }
+    }
+
+    fn deserialize(syntax: &SyntaxItem) -> Self {
+        Self {
+            layer: syntax.get_named_child("layer").map(PcbLayer::deserialize),
+            segments: syntax.children.iter().filter(|c| c.name == "pts")
+                .map(|pts| pts.children.iter().map(Scalar2D::deserialize).collect())
+                .collect(),
         }
     }
 }
@@ -2021,6 +2383,9 @@ impl SyntaxItemSerializable for FootprintPadPrimitives {
         for box_annotation in &self.annotation_boxes {
             children.push(box_annotation.serialize());
         }
+        for text in &self.texts {
+            children.push(text.serialize());
+        }
 
         SyntaxItem {
             name: "primitives".into(),
@@ -2038,6 +2403,7 @@ impl SyntaxItemSerializable for FootprintPadPrimitives {
             curves: Vec::new(),
             polygons: Vec::new(),
             annotation_boxes: Vec::new(),
+            texts: Vec::new(),
             width: None,
             fill: None,
         };
@@ -2056,6 +2422,7 @@ impl SyntaxItemSerializable for FootprintPadPrimitives {
                 "bezier" => primitives.curves.push(GraphicCurve::deserialize(child)),
                 "gr_poly" => primitives.polygons.push(GraphicPolygon::deserialize(child)),
                 "gr_bbox" => primitives.annotation_boxes.push(GraphicAnnotationBox::deserialize(child)),
+                "gr_text" => primitives.texts.push(GraphicText::deserialize(child)),
                 _ => panic!("Unsupported child item type in FootprintPadPrimitives: {}", child.name),
             }
         }
@@ -2207,6 +2574,20 @@ impl SyntaxItemSerializable for DrillDefinition {
     }
 }
 
+/// Locked items carry a bare `locked` token, which both KiCad 7 and 8 understand
+fn locked_arguments(locked: bool) -> Vec<SyntaxArgument> {
+    match locked {
+        true => vec![SyntaxArgument::Identifier("locked".into(), PositionPreference::None)],
+        false => vec![],
+    }
+}
+
+/// Accepts both the bare `locked` token and the `(locked yes)` form
+fn is_locked(syntax: &SyntaxItem) -> bool {
+    syntax.arguments.iter().any(|a| matches!(a, SyntaxArgument::Identifier(s, _) if s == "locked"))
+        || syntax.get_named_child("locked").is_some_and(|l| l.arguments.first().is_none_or(|a| a.get_string() == "yes"))
+}
+
 impl TopLevelSerializable for FootprintLibrary {
     fn get_same_line_identifiers() -> Vec<String> {
         Vec::from([
@@ -2216,4 +2597,24 @@ impl TopLevelSerializable for FootprintLibrary {
             "thermal_bridge_width", "tracks", "vias", "pads", "copperpour", "footprints"
         ]).iter().map(|s| s.to_string()).collect()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcb_layer_names_round_trip() {
+        for layer in PcbLayer::iter() {
+            assert_eq!(PcbLayer::parse(&layer.to_string()), layer, "'{}' does not parse back to {:?}", layer.to_string(), layer);
+        }
+    }
+
+    #[test]
+    fn pcb_layer_names_are_unique() {
+        let names = PcbLayer::iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        for (index, name) in names.iter().enumerate() {
+            assert!(!names[index + 1..].contains(name), "the layer name '{name}' is used by more than one layer");
+        }
+    }
+}
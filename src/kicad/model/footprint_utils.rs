@@ -1,5 +1,10 @@
+use crate::easyeda::geometry::{self, Point2D};
 use crate::kicad::model::common::TextEffect;
-use crate::kicad::model::footprint_library::{FootprintLibrary, FootprintProperty, PcbLayer, Scalar3D};
+use crate::kicad::model::footprint_library::{BoundingBox2D, FootprintAnchor, FootprintArc, FootprintLibrary, FootprintProperty, PcbLayer, Scalar3D};
+use crate::kicad::uuid::{format_uuid, uuid_v5, NAMESPACE_URL};
+use crate::model_3d::ANCHOR_OFFSET_PROPERTY;
+use crate::units::MM_TO_INCH;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
 
 impl FootprintLibrary {
     pub fn add_hidden_property(&mut self, key: &str, value: &str) {
@@ -9,7 +14,7 @@ impl FootprintLibrary {
         self.properties.push(FootprintProperty {
             key: key.into(),
             value: Some(value.into()),
-            position: Scalar3D::new("at", 0.0, 0.0, 0.0),
+            position: Scalar3D::at(0.0, 0.0, 0.0),
             hide: Some(true),
             layer: PcbLayer::FFab,
             uuid: None,
@@ -17,4 +22,228 @@ impl FootprintLibrary {
             unlocked: Some(true),
         });
     }
-}
\ No newline at end of file
+
+    pub fn get_property(&self, key: &str) -> Option<&str> {
+        self.properties.iter()
+            .find(|p| p.key == key)
+            .and_then(|p| p.value.as_deref())
+    }
+
+    /// Returns the (min_x, min_y, max_x, max_y) bounds of all graphic items and pads on the given layers
+    pub fn get_layer_bounds(&self, layers: &[PcbLayer]) -> Option<(f32, f32, f32, f32)> {
+        self.get_bounds(|layer| layers.contains(layer))
+    }
+
+    /// Returns the extents of all pads and graphic items, regardless of their layer
+    #[allow(unused)]
+    pub fn bounding_box(&self) -> Option<BoundingBox2D> {
+        self.get_bounds(|_| true)
+            .map(|(min_x, min_y, max_x, max_y)| BoundingBox2D { min_x, min_y, max_x, max_y })
+    }
+
+    fn get_bounds(&self, include_layer: impl Fn(&PcbLayer) -> bool) -> Option<(f32, f32, f32, f32)> {
+        let mut points = Vec::new();
+        for line in self.lines.iter().filter(|l| include_layer(&l.layer)) {
+            points.extend([(line.start.x, line.start.y), (line.end.x, line.end.y)]);
+        }
+        for arc in self.arcs.iter().filter(|a| include_layer(&a.layer)) {
+            points.extend(get_arc_extent_points(arc));
+        }
+        for circle in self.circles.iter().filter(|c| include_layer(&c.layer)) {
+            let radius = ((circle.end.x - circle.center.x).powi(2) + (circle.end.y - circle.center.y).powi(2)).sqrt();
+            points.extend([(circle.center.x - radius, circle.center.y - radius), (circle.center.x + radius, circle.center.y + radius)]);
+        }
+        for rectangle in self.rectangles.iter().filter(|r| include_layer(&r.layer)) {
+            points.extend([(rectangle.start.x, rectangle.start.y), (rectangle.end.x, rectangle.end.y)]);
+        }
+        for polygon in self.polygons.iter().filter(|p| include_layer(&p.layer)) {
+            points.extend(polygon.points.iter().map(|p| (p.x, p.y)));
+        }
+        for pad in self.pads.iter().filter(|p| p.layers.iter().any(&include_layer)) {
+            // Use the larger pad dimension so the bounds hold for any pad rotation
+            let half_size = pad.size.x.max(pad.size.y) / 2.0;
+            points.extend([(pad.position.x - half_size, pad.position.y - half_size), (pad.position.x + half_size, pad.position.y + half_size)]);
+        }
+
+        let min_x = points.iter().map(|p| p.0).reduce(f32::min)?;
+        let min_y = points.iter().map(|p| p.1).reduce(f32::min)?;
+        let max_x = points.iter().map(|p| p.0).reduce(f32::max)?;
+        let max_y = points.iter().map(|p| p.1).reduce(f32::max)?;
+        Some((min_x, min_y, max_x, max_y))
+    }
+
+    /// Returns the vertical (min, max) extents of all graphic items and pads on the given layers
+    pub fn get_layer_extents_y(&self, layers: &[PcbLayer]) -> Option<(f32, f32)> {
+        self.get_layer_bounds(layers).map(|(_, min_y, _, max_y)| (min_y, max_y))
+    }
+
+    /// Moves the footprint so that the chosen anchor lands on the origin
+    pub fn recenter(&mut self, anchor: FootprintAnchor) {
+        let origin = match anchor {
+            FootprintAnchor::AsIs => None,
+            FootprintAnchor::CenterOfPads => {
+                let min_x = self.pads.iter().map(|p| p.position.x).reduce(f32::min);
+                let min_y = self.pads.iter().map(|p| p.position.y).reduce(f32::min);
+                let max_x = self.pads.iter().map(|p| p.position.x).reduce(f32::max);
+                let max_y = self.pads.iter().map(|p| p.position.y).reduce(f32::max);
+                match (min_x, min_y, max_x, max_y) {
+                    (Some(min_x), Some(min_y), Some(max_x), Some(max_y)) => Some(((min_x + max_x) / 2.0, (min_y + max_y) / 2.0)),
+                    _ => None,
+                }
+            }
+            FootprintAnchor::CenterOfBody => self.get_layer_bounds(&[PcbLayer::FFab])
+                .map(|(min_x, min_y, max_x, max_y)| ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0)),
+            FootprintAnchor::Pad1 => self.pads.iter()
+                .find(|p| p.number == "1")
+                .map(|p| (p.position.x, p.position.y)),
+        };
+
+        if let Some((x, y)) = origin {
+            self.translate(-x, -y);
+
+            // Realigning the 3D model starts over from the EasyEDA transform, so the shift is recorded for it
+            let (dx, dy) = self.anchor_offset();
+            let value = format!("{},{}", dx - x, dy - y);
+            match self.properties.iter_mut().find(|p| p.key == ANCHOR_OFFSET_PROPERTY) {
+                Some(property) => property.value = Some(value),
+                None => self.add_hidden_property(ANCHOR_OFFSET_PROPERTY, &value),
+            }
+        }
+    }
+
+    /// Returns how far `recenter` has shifted the footprint, in millimeters
+    pub fn anchor_offset(&self) -> (f32, f32) {
+        self.get_property(ANCHOR_OFFSET_PROPERTY)
+            .and_then(|value| value.split_once(','))
+            .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+            .unwrap_or((0.0, 0.0))
+    }
+
+    /// Gives every element without a UUID a version 5 UUID derived from the LCSC code, the kind of the element
+    /// and its index among the elements of that kind, so that importing the same part twice produces identical files
+    pub fn assign_deterministic_uuids(&mut self, lcsc_code: &str) {
+        let namespace = uuid_v5(&NAMESPACE_URL, &format!("https://www.lcsc.com/product-detail/{lcsc_code}.html"));
+        let assign = |kind: &str, uuids: Vec<&mut Option<String>>| {
+            for (index, uuid) in uuids.into_iter().enumerate() {
+                if uuid.is_none() {
+                    *uuid = Some(format_uuid(&uuid_v5(&namespace, &format!("{kind}/{index}"))));
+                }
+            }
+        };
+
+        // Properties come and go with the available part data, so they are named by their key instead
+        for property in self.properties.iter_mut().filter(|p| p.uuid.is_none()) {
+            property.uuid = Some(format_uuid(&uuid_v5(&namespace, &format!("property/{}", property.key))));
+        }
+        assign("text", self.texts.iter_mut().map(|t| &mut t.uuid).collect());
+        assign("line", self.lines.iter_mut().map(|l| &mut l.uuid).collect());
+        assign("polygon", self.polygons.iter_mut().map(|p| &mut p.uuid).collect());
+        assign("circle", self.circles.iter_mut().map(|c| &mut c.uuid).collect());
+        assign("rectangle", self.rectangles.iter_mut().map(|r| &mut r.uuid).collect());
+        assign("arc", self.arcs.iter_mut().map(|a| &mut a.uuid).collect());
+        assign("pad", self.pads.iter_mut().map(|p| &mut p.uuid).collect());
+        assign("zone", self.zones.iter_mut().map(|z| &mut z.uuid).collect());
+        assign("image", self.images.iter_mut().map(|i| &mut i.uuid).collect());
+    }
+
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        for line in self.lines.iter_mut() {
+            line.start.x += dx;
+            line.start.y += dy;
+            line.end.x += dx;
+            line.end.y += dy;
+        }
+        for arc in self.arcs.iter_mut() {
+            arc.start.x += dx;
+            arc.start.y += dy;
+            arc.end.x += dx;
+            arc.end.y += dy;
+            if let Some(mid) = arc.mid.as_mut() {
+                mid.x += dx;
+                mid.y += dy;
+            }
+        }
+        for circle in self.circles.iter_mut() {
+            circle.center.x += dx;
+            circle.center.y += dy;
+            circle.end.x += dx;
+            circle.end.y += dy;
+        }
+        for rectangle in self.rectangles.iter_mut() {
+            rectangle.start.x += dx;
+            rectangle.start.y += dy;
+            rectangle.end.x += dx;
+            rectangle.end.y += dy;
+        }
+        for point in self.polygons.iter_mut().flat_map(|p| p.points.iter_mut()) {
+            point.x += dx;
+            point.y += dy;
+        }
+        for zone in self.zones.iter_mut() {
+            let filled_points = zone.filled_polygons.iter_mut().flat_map(|p| p.points.iter_mut());
+            let segment_points = zone.fill_segments.iter_mut().flat_map(|s| s.segments.iter_mut().flatten());
+            for point in zone.coordinate_points.points.iter_mut().chain(filled_points).chain(segment_points) {
+                point.x += dx;
+                point.y += dy;
+            }
+        }
+        for text in self.texts.iter_mut() {
+            text.position.x += dx;
+            text.position.y += dy;
+        }
+        for property in self.properties.iter_mut() {
+            property.position.x += dx;
+            property.position.y += dy;
+        }
+        for pad in self.pads.iter_mut() {
+            pad.position.x += dx;
+            pad.position.y += dy;
+        }
+        for image in self.images.iter_mut() {
+            image.position.x += dx;
+            image.position.y += dy;
+        }
+
+        // The 3D model placement is in inches with a Y axis that points up
+        if let Some(at) = self.model.as_mut().and_then(|m| m.at.as_mut()) {
+            at.x += dx * MM_TO_INCH;
+            at.y -= dy * MM_TO_INCH;
+        }
+    }
+}
+
+/// Returns the end points of the arc and the points where it crosses the horizontal or vertical axis through its center
+fn get_arc_extent_points(arc: &FootprintArc) -> Vec<(f32, f32)> {
+    // The legacy form stores the center in `start`, the start point in `end` and the sweep in `angle`
+    let (start, mid, end) = match (&arc.mid, arc.angle) {
+        (Some(mid), _) => (Point2D::new(arc.start.x, arc.start.y), Point2D::new(mid.x, mid.y), Point2D::new(arc.end.x, arc.end.y)),
+        (None, Some(angle)) => {
+            let center = Point2D::new(arc.start.x, arc.start.y);
+            let start = Point2D::new(arc.end.x, arc.end.y);
+            (start, geometry::rotate_point(start, center, angle / 2.0), geometry::rotate_point(start, center, angle))
+        }
+        (None, None) => return vec![(arc.start.x, arc.start.y), (arc.end.x, arc.end.y)],
+    };
+
+    let mut points = vec![(start.x, start.y), (mid.x, mid.y), (end.x, end.y)];
+    let Some(center) = geometry::get_circle_center(start, mid, end) else {
+        return points;
+    };
+    let radius = ((start.x - center.x).powi(2) + (start.y - center.y).powi(2)).sqrt();
+
+    // Walk the arc in the direction of increasing angle, starting from whichever end point makes it pass the mid point
+    let direction = |p: Point2D| (p.y - center.y).atan2(p.x - center.x);
+    let sweep = (direction(end) - direction(start)).rem_euclid(TAU);
+    let mid_sweep = (direction(mid) - direction(start)).rem_euclid(TAU);
+    let (from, sweep) = match mid_sweep < sweep {
+        true => (direction(start), sweep),
+        false => (direction(end), TAU - sweep),
+    };
+
+    for axis in [0.0, FRAC_PI_2, PI, PI + FRAC_PI_2] {
+        if (axis - from).rem_euclid(TAU) <= sweep {
+            points.push((center.x + radius * axis.cos(), center.y + radius * axis.sin()));
+        }
+    }
+    points
+}
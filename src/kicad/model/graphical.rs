@@ -1,3 +1,4 @@
+use crate::kicad::model::common::{Position, TextEffect};
 use crate::kicad::model::footprint_library::{PcbLayer, Scalar2D};
 use crate::kicad::syntax::{PositionPreference, SyntaxArgument, SyntaxItem, SyntaxItemSerializable};
 
@@ -58,6 +59,15 @@ pub struct GraphicCurve {
     pub uuid: Option<String>,
 }
 
+#[derive(Debug)]
+pub struct GraphicText {
+    pub text: String,
+    pub position: Position,
+    pub layer: Option<PcbLayer>,
+    pub effects: TextEffect,
+    pub uuid: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct GraphicAnnotationBox {
     pub start: Scalar2D,
@@ -483,4 +493,49 @@ impl SyntaxItemSerializable for GraphicAnnotationBox {
 
         box_annotation
     }
-}
\ No newline at end of file
+}
+
+impl SyntaxItemSerializable for GraphicText {
+    fn serialize(&self) -> SyntaxItem {
+        let mut children = vec![
+            self.position.serialize(),
+        ];
+
+        if let Some(layer) = &self.layer {
+            children.push(layer.serialize());
+        }
+        if let Some(uuid) = &self.uuid {
+            children.push(SyntaxItem::from_single_argument("uuid", SyntaxArgument::Identifier(uuid.clone(), PositionPreference::None)));
+        }
+        children.push(self.effects.serialize());
+
+        SyntaxItem {
+            name: "gr_text".into(),
+            arguments: vec![SyntaxArgument::QuotedString(self.text.clone(), PositionPreference::None)],
+            children,
+        }
+    }
+
+    fn deserialize(syntax: &SyntaxItem) -> Self {
+        let mut text = Self {
+            text: syntax.arguments.first().unwrap().get_string(),
+            position: Position::default(),
+            layer: None,
+            effects: TextEffect::default(),
+            uuid: None,
+        };
+
+        for child in &syntax.children {
+            match child.name.as_str() {
+                "at" => text.position = Position::deserialize(child),
+                "layer" | "layers" => text.layer = Some(PcbLayer::deserialize(child)),
+                "effects" => text.effects = TextEffect::deserialize(child),
+                "uuid" => text.uuid = Some(child.arguments.first().unwrap().get_string()),
+                "render_cache" => {}
+                _ => panic!("Unsupported child item type in GraphicText: {}", child.name),
+            }
+        }
+
+        text
+    }
+}
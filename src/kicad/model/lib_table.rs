@@ -0,0 +1,24 @@
+/// Common view of the `sym-lib-table` and `fp-lib-table` entries
+pub trait LibTableEntry {
+    fn name(&self) -> &str;
+    /// Returns the uri and the description of the entry
+    fn location_mut(&mut self) -> (&mut String, &mut String);
+}
+
+/// Adds the library, or refreshes the uri and description of an existing entry with the same name.
+/// Returns whether the table was changed.
+pub fn upsert_library<T: LibTableEntry>(libraries: &mut Vec<T>, mut library: T) -> bool {
+    let Some(existing) = libraries.iter_mut().find(|e| e.name() == library.name()) else {
+        libraries.push(library);
+        return true;
+    };
+
+    let (uri, description) = existing.location_mut();
+    let (new_uri, new_description) = library.location_mut();
+    if uri == new_uri && description == new_description {
+        return false;
+    }
+    std::mem::swap(uri, new_uri);
+    std::mem::swap(description, new_description);
+    true
+}
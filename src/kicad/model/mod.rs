@@ -1,8 +1,9 @@
 pub mod symbol_library;
 pub mod footprint_library;
 pub mod common;
-mod graphical;
+pub mod graphical;
 pub mod symbol_lib_table;
 pub mod footprint_lib_table;
+pub mod lib_table;
 pub mod symbol_utils;
 mod footprint_utils;
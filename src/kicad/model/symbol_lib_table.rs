@@ -1,3 +1,4 @@
+use crate::kicad::model::lib_table::{self, LibTableEntry};
 use crate::kicad::syntax::{PositionPreference, SyntaxArgument, SyntaxItem, SyntaxItemSerializable, TopLevelSerializable};
 
 #[derive(Debug, Default)]
@@ -17,6 +18,24 @@ pub struct SymbolLibTableItem {
     pub hidden: bool,
 }
 
+impl SymbolLibTable {
+    /// Adds the library, or refreshes the uri and description of an existing entry with the same name.
+    /// Returns whether the table was changed.
+    pub fn upsert_library(&mut self, library: SymbolLibTableItem) -> bool {
+        lib_table::upsert_library(&mut self.libraries, library)
+    }
+}
+
+impl LibTableEntry for SymbolLibTableItem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn location_mut(&mut self) -> (&mut String, &mut String) {
+        (&mut self.uri, &mut self.description)
+    }
+}
+
 impl TopLevelSerializable for SymbolLibTable {
     fn get_same_line_identifiers() -> Vec<String> {
         Vec::from([
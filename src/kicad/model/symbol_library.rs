@@ -1,6 +1,7 @@
 use crate::easyeda::symbol::Object;
 use crate::kicad::model::common::{Id, Position, StrokeDefinition, TextEffect, TextPosition};
 use crate::kicad::syntax::{PositionPreference, SyntaxArgument, SyntaxItem, SyntaxItemSerializable, TopLevelSerializable};
+use strum::EnumIter;
 
 #[derive(Debug)]
 pub struct SymbolLib {
@@ -8,6 +9,9 @@ pub struct SymbolLib {
     pub generator: String,
     pub generator_version: Option<String>,
     pub symbols: Vec<Symbol>,
+    pub embedded_files: Option<SyntaxItem>,
+    /// Top-level items that aren't understood, along with the number of symbols that came before them
+    pub unknown_items: Vec<(usize, SyntaxItem)>,
 }
 
 #[allow(unused)]
@@ -35,6 +39,8 @@ pub struct Symbol {
     pub units: Vec<Symbol>,
     pub objects: Vec<Object>,
     pub unit_name: Option<String>,
+    pub embedded_fonts: Option<SyntaxItem>,
+    pub embedded_files: Option<SyntaxItem>,
 }
 
 #[derive(Debug)]
@@ -149,7 +155,7 @@ pub enum StrokeType {
     Solid,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum FillType {
     None,
     Outline,
@@ -177,7 +183,7 @@ pub enum PinElectricalType {
     NoConnect,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter)]
 pub enum PinGraphicStyle {
     Line,
     Inverted,
@@ -200,6 +206,7 @@ pub struct SymbolPin {
     pub name_effects: TextEffect,
     pub number: Option<String>,
     pub number_effects: TextEffect,
+    pub hidden: bool,
 }
 
 impl SyntaxItemSerializable for SymbolLib {
@@ -211,7 +218,19 @@ impl SyntaxItemSerializable for SymbolLib {
         if let Some(generator_version) = &self.generator_version {
             children.push(SyntaxItem::from_single_argument("generator_version", SyntaxArgument::QuotedString(generator_version.clone(), PositionPreference::None)));
         }
-        children.extend(self.symbols.iter().map(|symbol| symbol.serialize()).collect::<Vec<_>>());
+        let unknown_items_at = |index: usize| self.unknown_items.iter()
+            .filter(move |(position, _)| *position == index)
+            .map(|(_, item)| item.clone());
+        for (index, symbol) in self.symbols.iter().enumerate() {
+            children.extend(unknown_items_at(index));
+            children.push(symbol.serialize());
+        }
+        children.extend(self.unknown_items.iter()
+            .filter(|(position, _)| *position >= self.symbols.len())
+            .map(|(_, item)| item.clone()));
+        if let Some(embedded_files) = &self.embedded_files {
+            children.push(embedded_files.clone());
+        }
         SyntaxItem {
             name: "kicad_symbol_lib".into(),
             arguments: Vec::new(),
@@ -225,6 +244,8 @@ impl SyntaxItemSerializable for SymbolLib {
             generator: "".into(),
             generator_version: None,
             symbols: Vec::new(),
+            embedded_files: None,
+            unknown_items: Vec::new(),
         };
 
         for child in syntax.children.iter() {
@@ -233,7 +254,8 @@ impl SyntaxItemSerializable for SymbolLib {
                 "version" => lib.version = child.arguments.first().unwrap().get_number() as usize,
                 "generator" => lib.generator = child.arguments.first().unwrap().get_string(),
                 "generator_version" => lib.generator_version = Some(child.arguments.first().unwrap().get_string()),
-                _ => panic!("Unsupported child item type in SymbolLib"),
+                "embedded_files" => lib.embedded_files = Some(child.clone()),
+                _ => lib.unknown_items.push((lib.symbols.len(), child.clone())),
             }
         }
 
@@ -260,7 +282,7 @@ impl SyntaxItemSerializable for Symbol {
             })
         }
         if self.pin_numbers_hidden {
-            children.push(SyntaxItem::from_single_argument("pin_numbers", SyntaxArgument::Identifier("hidden".into(), PositionPreference::None)))
+            children.push(SyntaxItem::from_single_argument("pin_numbers", SyntaxArgument::Identifier("hide".into(), PositionPreference::None)))
         }
         if let Some(in_bom) = self.in_bom {
             children.push(SyntaxItem::from_single_argument("in_bom", SyntaxArgument::Identifier(if in_bom { "yes".into() } else { "no".into() }, PositionPreference::None)));
@@ -291,6 +313,12 @@ impl SyntaxItemSerializable for Symbol {
         for child_symbol in &self.units {
             children.push(child_symbol.serialize());
         }
+        if let Some(embedded_fonts) = &self.embedded_fonts {
+            children.push(embedded_fonts.clone());
+        }
+        if let Some(embedded_files) = &self.embedded_files {
+            children.push(embedded_files.clone());
+        }
 
         SyntaxItem {
             name: "symbol".into(),
@@ -320,6 +348,8 @@ impl SyntaxItemSerializable for Symbol {
             units: Vec::new(),
             extends_id: None,
             unit_name: None,
+            embedded_fonts: None,
+            embedded_files: None,
             pin_numbers_hidden: false,
             pin_names_hidden: false,
             pin_names_offset: None,
@@ -340,14 +370,15 @@ impl SyntaxItemSerializable for Symbol {
                 "exclude_from_sim" => symbol.exclude_from_sim = Some(child.arguments.first().unwrap().get_string() == "yes"),
                 "extends" => symbol.extends_id = Some(child.arguments.first().unwrap().get_string()),
                 "unit_name" => symbol.unit_name = Some(child.arguments.first().unwrap().get_string()),
-                "pin_numbers" => symbol.pin_numbers_hidden = child.arguments.first().unwrap().get_string() == "hidden",
+                "pin_numbers" => symbol.pin_numbers_hidden = matches!(child.arguments.first().unwrap().get_string().as_str(), "hide" | "hidden"),
                 "pin_names" => {
                     symbol.pin_names_hidden = child.has_argument(SyntaxArgument::Identifier("hide".into(), PositionPreference::None));
                     symbol.pin_names_offset = child.get_named_child("offset".into())
                         .and_then(|c| Some(c.arguments.first().unwrap().get_number()))
                 }
                 "symbol" => symbol.units.push(Symbol::deserialize(&child)),
-                "embedded_fonts" => {},
+                "embedded_fonts" => symbol.embedded_fonts = Some(child.clone()),
+                "embedded_files" => symbol.embedded_files = Some(child.clone()),
                 _ => panic!("Unsupported child item type in Symbol: {}", child.name)
             }
         }
@@ -420,12 +451,14 @@ impl SyntaxItemSerializable for SymbolPin {
                     PinGraphicStyle::Clock => "clock".into(),
                     PinGraphicStyle::InvertedClock => "inverted_clock".into(),
                     PinGraphicStyle::InputLow => "input_low".into(),
-                    PinGraphicStyle::ClockLow => "output_low".into(),
-                    PinGraphicStyle::OutputLow => "clock_low".into(),
+                    PinGraphicStyle::ClockLow => "clock_low".into(),
+                    PinGraphicStyle::OutputLow => "output_low".into(),
                     PinGraphicStyle::EdgeClockHigh => "edge_clock_high".into(),
                     PinGraphicStyle::NonLogic => "non_logic".into(),
                 }, PositionPreference::None)
-            ],
+            ].into_iter()
+                .chain(self.hidden.then(|| SyntaxArgument::Identifier("hide".into(), PositionPreference::End)))
+                .collect(),
             children: vec![
                 Some(self.position.serialize()),
                 Some(SyntaxItem::from_single_argument("length", SyntaxArgument::Number(self.length, PositionPreference::None))),
@@ -453,6 +486,7 @@ impl SyntaxItemSerializable for SymbolPin {
             position: Position { x: 0.0, y: 0.0, angle: None },
             name_effects: TextEffect::default(),
             number_effects: TextEffect::default(),
+            hidden: syntax.has_argument(SyntaxArgument::Identifier("hide".into(), PositionPreference::None)),
         };
 
         pin.electrical_type = match syntax.arguments.get(0).unwrap().get_string().as_str() {
@@ -488,13 +522,14 @@ impl SyntaxItemSerializable for SymbolPin {
             match child.name.as_ref() {
                 "at" => pin.position = Position::deserialize(&child),
                 "length" => pin.length = child.arguments.first().unwrap().get_number(),
+                "hide" => pin.hidden = child.arguments.first().is_none_or(|a| a.get_string() == "yes"),
                 "number" => {
                     pin.number = Some(child.arguments.first().unwrap().get_string());
-                    pin.number_effects = TextEffect::deserialize(&child.children.first().unwrap());
+                    pin.number_effects = child.get_named_child("effects").map(TextEffect::deserialize).unwrap_or_default();
                 }
                 "name" => {
                     pin.name = Some(child.arguments.first().unwrap().get_string());
-                    pin.name_effects = TextEffect::deserialize(&child.children.first().unwrap());
+                    pin.name_effects = child.get_named_child("effects").map(TextEffect::deserialize).unwrap_or_default();
                 }
                 _ => panic!("Invalid child element for SymbolPin"),
             }
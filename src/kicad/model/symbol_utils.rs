@@ -1,5 +1,9 @@
 use crate::kicad::model::common::{Position, TextEffect};
-use crate::kicad::model::symbol_library::{Property, Symbol};
+use std::collections::BTreeSet;
+use crate::kicad::model::symbol_library::{Property, Symbol, SymbolLib};
+
+/// Hidden symbol property holding the UUID of the EasyEDA symbol it was converted from
+pub const EASYEDA_SYMBOL_PROPERTY: &str = "EasyEDA Symbol";
 
 impl Symbol {
     pub fn add_hidden_property(&mut self, key: &str, value: &str) {
@@ -34,4 +38,82 @@ impl Symbol {
             hide: false,
         });
     }
-}
\ No newline at end of file
+
+    /// Sets the value of a property, keeping the placement of an existing one
+    pub fn set_property(&mut self, key: &str, value: &str) {
+        match self.properties.iter_mut().find(|p| p.key == key) {
+            Some(property) => property.value = value.into(),
+            None => self.add_property(key, value, 0.0, 0.0),
+        }
+    }
+
+    pub fn get_property(&self, key: &str) -> Option<&str> {
+        self.properties.iter()
+            .find(|p| p.key == key)
+            .map(|p| p.value.as_str())
+    }
+
+    /// Turns this symbol into an alias of `base_id`, keeping only its own properties
+    pub fn derive_from(&mut self, base_id: &str) {
+        self.extends_id = Some(base_id.into());
+        self.pin_numbers_hidden = false;
+        self.pin_names_hidden = false;
+        self.pin_names_offset = None;
+        self.arcs.clear();
+        self.beziers.clear();
+        self.circles.clear();
+        self.rectangles.clear();
+        self.lines.clear();
+        self.curves.clear();
+        self.texts.clear();
+        self.pins.clear();
+        self.units.clear();
+        self.objects.clear();
+    }
+
+    /// Moves all graphic items and pins of this symbol and its units
+    /// Returns the numbers of the pins of the symbol and all of its units
+    pub fn pin_numbers(&self) -> BTreeSet<String> {
+        self.pins.iter()
+            .filter_map(|p| p.number.clone())
+            .chain(self.units.iter().flat_map(|u| u.pin_numbers()))
+            .collect()
+    }
+
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        let positions = self.arcs.iter_mut().chain(self.beziers.iter_mut()).flat_map(|a| [&mut a.start, &mut a.mid, &mut a.end])
+            .chain(self.circles.iter_mut().map(|c| &mut c.center))
+            .chain(self.rectangles.iter_mut().flat_map(|r| [&mut r.start, &mut r.end]))
+            .chain(self.lines.iter_mut().flat_map(|l| l.points.iter_mut()))
+            .chain(self.curves.iter_mut().flat_map(|c| c.points.iter_mut()))
+            .chain(self.pins.iter_mut().map(|p| &mut p.position));
+        for position in positions {
+            position.x += dx;
+            position.y += dy;
+        }
+        for text in self.texts.iter_mut() {
+            text.position.x += dx;
+            text.position.y += dy;
+        }
+        for property in self.properties.iter_mut() {
+            property.position.x += dx;
+            property.position.y += dy;
+        }
+        for unit in self.units.iter_mut() {
+            unit.translate(dx, dy);
+        }
+    }
+}
+
+impl SymbolLib {
+    pub fn contains_lcsc_code(&self, lcsc_code: &str) -> bool {
+        self.symbols.iter().any(|s| s.get_property("LCSC") == Some(lcsc_code))
+    }
+
+    /// Finds a non-derived symbol, other than `symbol_id`, that was converted from the same EasyEDA symbol
+    pub fn find_base_symbol(&self, easyeda_symbol_uuid: &str, symbol_id: &str) -> Option<&Symbol> {
+        self.symbols.iter()
+            .filter(|s| s.symbol_id != symbol_id && s.extends_id.is_none())
+            .find(|s| s.get_property(EASYEDA_SYMBOL_PROPERTY) == Some(easyeda_symbol_uuid))
+    }
+}
@@ -1,4 +1,10 @@
 use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::iter::Peekable;
+use std::path::Path;
+use anyhow::Context;
 use itertools::Itertools;
 
 #[derive(Debug, PartialEq)]
@@ -10,6 +16,121 @@ pub enum Token {
     Number(usize, f32),
 }
 
+/// Lazily produced tokens of S-expression data, see [`KiCadParser::tokenize_reader`]
+pub struct TokenStream<R: Read> {
+    chars: Peekable<Utf8Chars<BufReader<R>>>,
+    position: usize,
+}
+
+impl<R: Read> TokenStream<R> {
+    fn next_char(&mut self) -> io::Result<Option<char>> {
+        let ch = self.chars.next().transpose()?;
+        if ch.is_some() {
+            self.position += 1;
+        }
+        Ok(ch)
+    }
+
+    fn peek_char(&mut self) -> io::Result<Option<char>> {
+        match self.chars.peek() {
+            Some(Ok(ch)) => Ok(Some(*ch)),
+            Some(Err(_)) => Err(self.chars.next().unwrap().unwrap_err()),
+            None => Ok(None),
+        }
+    }
+
+    fn read_token(&mut self) -> io::Result<Option<Token>> {
+        while let Some(ch) = self.peek_char()? {
+            match ch {
+                '(' | ')' => {
+                    let position = self.position;
+                    self.next_char()?;
+                    return Ok(Some(match ch {
+                        '(' => Token::OpenParen(position),
+                        _ => Token::CloseParen(position),
+                    }));
+                }
+                '"' => {
+                    let position = self.position;
+                    self.next_char()?; // Skip opening quote
+                    let mut string = String::new();
+                    while let Some(ch) = self.next_char()? {
+                        if ch == '"' {
+                            break;
+                        }
+                        string.push(ch);
+                    }
+                    return Ok(Some(Token::QuotedString(position, string)));
+                }
+                _ if KiCadParser::is_char_identifier_or_numeric(ch) => {
+                    let position = self.position;
+                    let mut string = String::new();
+                    loop {
+                        match self.peek_char()? {
+                            Some(ch) if KiCadParser::is_char_identifier_or_numeric(ch) => {
+                                string.push(ch);
+                                self.next_char()?;
+                            }
+                            Some(' ' | ')' | '\r' | '\n') | None => break,
+                            Some(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Invalid identifier token at {}!", self.position))),
+                        }
+                    }
+                    return Ok(Some(match string.parse::<f32>() {
+                        Ok(number) => Token::Number(position, number),
+                        Err(_) => Token::Identifier(position, string),
+                    }));
+                }
+                _ => {
+                    self.next_char()?;
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<R: Read> Iterator for TokenStream<R> {
+    type Item = io::Result<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_token().transpose()
+    }
+}
+
+/// Decodes UTF-8 characters one at a time from a buffered reader
+struct Utf8Chars<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> Iterator for Utf8Chars<R> {
+    type Item = io::Result<char>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buffer = [0u8; 4];
+        match self.reader.read(&mut buffer[..1]) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(error) => return Some(Err(error)),
+        }
+
+        let length = match buffer[0] {
+            0x00..=0x7F => 1,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return Some(Err(io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8 lead byte"))),
+        };
+        if let Err(error) = self.reader.read_exact(&mut buffer[1..length]) {
+            return Some(Err(error));
+        }
+
+        Some(std::str::from_utf8(&buffer[..length])
+            .map(|s| s.chars().next().unwrap())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error)))
+    }
+}
+
 impl Token {
     #[allow(dead_code)]
     pub fn is_opening_paren(&self) -> bool {
@@ -136,6 +257,43 @@ pub enum PositionPreference {
     End,
 }
 
+/// Renders the tree as an indented s-expression with one child per line, in the order the items are stored.
+/// Meant for inspecting trees while debugging, files are written with [`KiCadParser::stringify_tokens`].
+impl fmt::Display for SyntaxItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+impl SyntaxItem {
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        write!(f, "{:indent$}({}", "", self.name, indent = indent * 2)?;
+        for argument in &self.arguments {
+            write!(f, " {argument}")?;
+        }
+
+        if self.children.is_empty() {
+            return write!(f, ")");
+        }
+
+        for child in &self.children {
+            writeln!(f)?;
+            child.fmt_indented(f, indent + 1)?;
+        }
+        write!(f, "\n{:indent$})", "", indent = indent * 2)
+    }
+}
+
+impl fmt::Display for SyntaxArgument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxArgument::Number(number, _) => write!(f, "{}", format_number(*number)),
+            SyntaxArgument::Identifier(identifier, _) => write!(f, "{identifier}"),
+            SyntaxArgument::QuotedString(string, _) => write!(f, "\"{string}\""),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum SyntaxArgument {
     Number(f32, PositionPreference),
@@ -158,133 +316,129 @@ impl SyntaxArgument {
         } else if let SyntaxArgument::QuotedString(str, _) = self {
             str.clone()
         } else if let SyntaxArgument::Number(num, _) = self {
-            num.to_string()
+            format_number(*num)
         } else {
             unreachable!()
         }
     }
 }
 
+/// Writes the file through a temporary file next to it, so an interrupted write leaves the old file intact
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut temp_file_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_file_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_file_name);
+
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, path)
+}
+
+/// Maximum number of decimals written for numbers, KiCad's internal resolution is 1nm
+const MAX_NUMBER_DECIMALS: usize = 6;
+
+/// Formats a number the way KiCad expects it: fixed-point with a `.` separator, never in scientific notation
+pub fn format_number(value: f32) -> String {
+    let formatted = format!("{:.*}", MAX_NUMBER_DECIMALS, value);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    match formatted {
+        "-0" => "0".into(),
+        _ => formatted.into(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FormatOptions {
+    /// Emit everything on a single line with minimal whitespace instead of KiCad's indented layout
+    pub compact: bool,
+    /// Start every list on its own line, instead of keeping short lists on their parent's line like KiCad
+    pub expanded: bool,
+}
+
 pub struct KiCadParser;
 
 impl KiCadParser {
     pub fn tokenize(input: &str) -> Vec<Token> {
-        let mut tokens = Vec::new();
-        let mut chars = input.chars().peekable();
-        let mut position: usize = 0;
+        Self::tokenize_reader(input.as_bytes())
+            .map(|token| token.unwrap_or_else(|error| panic!("{error}")))
+            .collect()
+    }
 
-        while let Some(&ch) = chars.peek() {
-            match ch {
-                '\r' | '\n' | ' ' => {
-                    chars.next();
-                    position += 1;
-                    continue;
-                }
-                '(' => {
-                    tokens.push(Token::OpenParen(position));
-                    chars.next();
-                    position += 1;
-                }
-                ')' => {
-                    tokens.push(Token::CloseParen(position));
-                    chars.next();
-                    position += 1;
-                }
-                '"' => {
-                    chars.next(); // Skip opening code
-                    position += 1;
-                    let mut string = String::new();
-                    while let Some(ch) = chars.next() {
-                        position += 1;
-                        if ch == '"' {
-                            break;
-                        }
-                        string.push(ch);
-                    }
-                    tokens.push(Token::QuotedString(position, string));
-                }
-                _ if Self::is_char_identifier_or_numeric(ch) => {
-                    let mut string = String::new();
-                    while let Some(ch) = chars.peek() {
-                        if Self::is_char_identifier_or_numeric(*ch) {
-                            string.push(*ch);
-                            chars.next();
-                            position += 1;
-                        } else if *ch == ' ' || *ch == ')' || *ch == '\r' || *ch == '\n' {
-                            break;
-                        } else {
-                            panic!("Invalid identifier token at {}!", position);
-                        }
-                    }
-                    if let Ok(number) = string.parse::<f32>() {
-                        tokens.push(Token::Number(position, number));
-                    } else {
-                        tokens.push(Token::Identifier(position, string));
-                    }
-                }
-                _ => {
-                    chars.next();
-                    position += 1;
-                }
-            }
+    /// Tokenizes the data lazily while it is being read, so large files never have to be fully in memory
+    pub fn tokenize_reader<R: Read>(reader: R) -> TokenStream<R> {
+        TokenStream {
+            chars: Utf8Chars { reader: BufReader::new(reader) }.peekable(),
+            position: 0,
         }
-
-        tokens
     }
 
     pub fn parse_syntax_item(tokens: &Vec<Token>) -> SyntaxItem {
         let mut items = VecDeque::<SyntaxItem>::new();
 
-        for token in tokens.iter().peekable() {
-            match token {
-                Token::OpenParen(_) => {
-                    items.push_front(SyntaxItem {
-                        name: "".into(),
-                        arguments: Vec::new(),
-                        children: Vec::new(),
-                    });
-                }
-                Token::CloseParen(_) => {
-                    let current_element = items.pop_front().unwrap();
-                    if let Some(parent_element) = items.front_mut() {
-                        parent_element.children.push(current_element);
-                    } else {
-                        items.push_front(current_element);
-                    }
+        for token in tokens {
+            Self::apply_token(&mut items, token);
+        }
+
+        items.pop_front().unwrap()
+    }
+
+    /// Parses the syntax tree directly from a stream, without collecting its tokens first
+    pub fn parse_syntax_item_from_reader<R: Read>(reader: R) -> io::Result<SyntaxItem> {
+        let mut items = VecDeque::<SyntaxItem>::new();
+
+        for token in Self::tokenize_reader(reader) {
+            Self::apply_token(&mut items, &token?);
+        }
+
+        items.pop_front().ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "the input contains no syntax item"))
+    }
+
+    fn apply_token(items: &mut VecDeque<SyntaxItem>, token: &Token) {
+        match token {
+            Token::OpenParen(_) => {
+                items.push_front(SyntaxItem {
+                    name: "".into(),
+                    arguments: Vec::new(),
+                    children: Vec::new(),
+                });
+            }
+            Token::CloseParen(_) => {
+                let current_element = items.pop_front().unwrap();
+                if let Some(parent_element) = items.front_mut() {
+                    parent_element.children.push(current_element);
+                } else {
+                    items.push_front(current_element);
                 }
-                Token::Identifier(offset, str) => {
-                    if let Some(top_item) = items.front_mut() {
-                        if top_item.name.is_empty() {
-                            top_item.name = str.clone();
-                        } else {
-                            top_item
-                                .arguments
-                                .push(SyntaxArgument::Identifier(str.clone(), PositionPreference::None));
-                        }
+            }
+            Token::Identifier(offset, str) => {
+                if let Some(top_item) = items.front_mut() {
+                    if top_item.name.is_empty() {
+                        top_item.name = str.clone();
                     } else {
-                        panic!("It is invalid to have an identifier with no parent node (at offset {})", offset)
-                    }
-                }
-                Token::QuotedString(offset, str) => {
-                    if let Some(top_item) = items.front_mut() {
                         top_item
                             .arguments
-                            .push(SyntaxArgument::QuotedString(str.clone(), PositionPreference::None));
-                    } else {
-                        panic!("It is invalid to have a string value with no parent node (at offset {})", offset)
+                            .push(SyntaxArgument::Identifier(str.clone(), PositionPreference::None));
                     }
+                } else {
+                    panic!("It is invalid to have an identifier with no parent node (at offset {})", offset)
                 }
-                Token::Number(offset, val) => {
-                    if let Some(top_item) = items.front_mut() {
-                        top_item.arguments.push(SyntaxArgument::Number(*val, PositionPreference::None));
-                    } else {
-                        panic!("It is invalid to have a numeric value with no parent node (at offset {})", offset)
-                    }
+            }
+            Token::QuotedString(offset, str) => {
+                if let Some(top_item) = items.front_mut() {
+                    top_item
+                        .arguments
+                        .push(SyntaxArgument::QuotedString(str.clone(), PositionPreference::None));
+                } else {
+                    panic!("It is invalid to have a string value with no parent node (at offset {})", offset)
+                }
+            }
+            Token::Number(offset, val) => {
+                if let Some(top_item) = items.front_mut() {
+                    top_item.arguments.push(SyntaxArgument::Number(*val, PositionPreference::None));
+                } else {
+                    panic!("It is invalid to have a numeric value with no parent node (at offset {})", offset)
                 }
             }
         }
-
-        items.pop_front().unwrap()
     }
 
     pub fn generate_tokens(item: &SyntaxItem) -> Vec<Token> {
@@ -317,10 +471,41 @@ impl KiCadParser {
         tokens
     }
 
+    /// Reads the file and deserializes its top level item
+    pub fn parse_file<T: SyntaxItemSerializable>(path: impl AsRef<Path>) -> anyhow::Result<T> {
+        let path = path.as_ref();
+        let file = fs::File::open(path).with_context(|| format!("Failed to open '{}'", path.display()))?;
+        let item = Self::parse_syntax_item_from_reader(file).with_context(|| format!("Failed to parse '{}'", path.display()))?;
+        Ok(T::deserialize(&item))
+    }
+
+    pub fn write_file<T: TopLevelSerializable>(path: impl AsRef<Path>, item: &T) -> anyhow::Result<()> {
+        Self::write_file_with_options(path, item, &FormatOptions::default())
+    }
+
+    /// Serializes the item and replaces the file, readers never see a partially written file
+    pub fn write_file_with_options<T: TopLevelSerializable>(path: impl AsRef<Path>, item: &T, options: &FormatOptions) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let tokens = Self::generate_tokens(&item.serialize());
+        let data = Self::stringify_tokens_with_options::<T>(&tokens, options);
+        write_atomic(path, data).with_context(|| format!("Failed to write '{}'", path.display()))
+    }
+
     pub fn stringify_tokens<S>(tokens: &Vec<Token>) -> String
     where
         S: TopLevelSerializable,
     {
+        Self::stringify_tokens_with_options::<S>(tokens, &FormatOptions::default())
+    }
+
+    pub fn stringify_tokens_with_options<S>(tokens: &Vec<Token>, options: &FormatOptions) -> String
+    where
+        S: TopLevelSerializable,
+    {
+        if options.compact {
+            return Self::stringify_tokens_compact(tokens);
+        }
+
         let mut string = String::new();
         let mut tokens = tokens.iter().peekable();
         let mut indent = 0;
@@ -328,8 +513,10 @@ impl KiCadParser {
         let mut identifier_stack = VecDeque::new();
         let mut last_popped_item_name: Option<String> = None;
 
-        let same_line_identifiers = S::get_same_line_identifiers();
-        let effects_same_line_after = ["name", "number"];
+        let (same_line_identifiers, effects_same_line_after) = match options.expanded {
+            true => (Vec::new(), &[][..]),
+            false => (S::get_same_line_identifiers(), &["name", "number"][..]),
+        };
         while let Some(token) = tokens.next() {
             let same_line = match (token, tokens.peek()) {
                 (Token::OpenParen(_), Some(Token::Identifier(_, str))) => {
@@ -409,7 +596,7 @@ impl KiCadParser {
                     }
                 }
                 Token::Number(_, val) => {
-                    string.push_str(format!("{}", val).as_str());
+                    string.push_str(&format_number(*val));
                     if tokens.peek().is_some_and(|&t| !t.is_closing_paren()) {
                         string.push(' ');
                     }
@@ -425,8 +612,29 @@ impl KiCadParser {
         string
     }
 
+    fn stringify_tokens_compact(tokens: &Vec<Token>) -> String {
+        let mut string = String::new();
+        let mut tokens = tokens.iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                Token::OpenParen(_) => string.push('('),
+                Token::CloseParen(_) => string.push(')'),
+                Token::QuotedString(_, str) => string.push_str(format!("\"{}\"", str).as_str()),
+                Token::Identifier(_, str) => string.push_str(str.as_str()),
+                Token::Number(_, val) => string.push_str(&format_number(*val)),
+            }
+
+            if !matches!(token, Token::OpenParen(_)) && tokens.peek().is_some_and(|&t| !t.is_closing_paren()) {
+                string.push(' ');
+            }
+        }
+
+        string
+    }
+
     fn is_char_identifier_or_numeric(ch: char) -> bool {
-        ch.is_alphanumeric() || ch == '_' || ch == '-' || ch == '.' || ch == '*' || ch == '%'
+        // '|', '+', '/' and '=' appear in the base64 payload of embedded files
+        ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.' | '*' | '%' | '|' | '+' | '/' | '=')
     }
 }
 
@@ -0,0 +1,400 @@
+use crate::kicad::model::footprint_lib_table::{FootprintLibTable, FootprintLibTableItem};
+use crate::kicad::model::footprint_library::{BoundingBox2D, FootprintArc, FootprintLibrary, PadChamfer, PcbLayer, Scalar2D};
+use crate::kicad::model::symbol_library::{PinGraphicStyle, Symbol, SymbolLib, SymbolPin};
+use crate::kicad::model::symbol_utils::EASYEDA_SYMBOL_PROPERTY;
+use crate::kicad::syntax::{format_number, FormatOptions, KiCadParser, PositionPreference, SyntaxArgument, SyntaxItem, SyntaxItemSerializable, Token, TopLevelSerializable};
+use std::fs;
+use std::path::{Path, PathBuf};
+use strum::IntoEnumIterator;
+
+fn test_data_files(extension: &str) -> Vec<PathBuf> {
+    let data_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data").join("kicad");
+    let mut files = fs::read_dir(data_dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+fn assert_round_trip<T: TopLevelSerializable>(path: &Path) {
+    let input = fs::read_to_string(path).unwrap();
+    let tokens = KiCadParser::tokenize(&input);
+    let original = KiCadParser::parse_syntax_item(&tokens);
+    let model: T = SyntaxItemSerializable::deserialize(&original);
+
+    let tokens = KiCadParser::generate_tokens(&model.serialize());
+    let output = KiCadParser::stringify_tokens::<T>(&tokens);
+    let tokens = KiCadParser::tokenize(&output);
+    let reparsed = KiCadParser::parse_syntax_item(&tokens);
+
+    assert!(reparsed.deep_equals(&original), "Round-trip mismatch for {}:\n{}", path.display(), output);
+}
+
+#[test]
+fn symbol_libraries_round_trip() {
+    let files = test_data_files("kicad_sym");
+    assert!(!files.is_empty());
+    for path in files {
+        assert_round_trip::<SymbolLib>(&path);
+    }
+}
+
+#[test]
+fn footprints_round_trip() {
+    let files = test_data_files("kicad_mod");
+    assert!(!files.is_empty());
+    for path in files {
+        assert_round_trip::<FootprintLibrary>(&path);
+    }
+}
+
+#[test]
+fn symbol_library_preserves_unknown_items() {
+    let input = r#"(kicad_symbol_lib
+        (version 20231120)
+        (generator "kicad_symbol_editor")
+        (future_token "value" (nested 1))
+        (symbol "R" (in_bom yes) (on_board yes))
+        (trailing_token "value")
+    )"#;
+    let tokens = KiCadParser::tokenize(input);
+    let original = KiCadParser::parse_syntax_item(&tokens);
+    let lib: SymbolLib = SyntaxItemSerializable::deserialize(&original);
+    assert_eq!(lib.unknown_items.len(), 2);
+    assert_eq!((lib.unknown_items[0].0, lib.unknown_items[0].1.name.as_str()), (0, "future_token"));
+    assert_eq!((lib.unknown_items[1].0, lib.unknown_items[1].1.name.as_str()), (1, "trailing_token"));
+
+    // The unknown items keep their place relative to the symbols
+    let names = |item: &SyntaxItem| item.children.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names(&lib.serialize()), names(&original));
+
+    let tokens = KiCadParser::generate_tokens(&lib.serialize());
+    let output = KiCadParser::stringify_tokens::<SymbolLib>(&tokens);
+    let tokens = KiCadParser::tokenize(&output);
+    let reparsed = KiCadParser::parse_syntax_item(&tokens);
+    assert!(reparsed.deep_equals(&original), "Round-trip mismatch:\n{}", output);
+}
+
+#[test]
+fn footprint_pad_chamfers() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data").join("kicad").join("Chamfered_Pads.kicad_mod");
+    let input = fs::read_to_string(path).unwrap();
+    let tokens = KiCadParser::tokenize(&input);
+    let footprint: FootprintLibrary = SyntaxItemSerializable::deserialize(&KiCadParser::parse_syntax_item(&tokens));
+
+    let pad = footprint.pads.first().unwrap();
+    assert_eq!(pad.chamfer_ratio, Some(0.25));
+    assert_eq!(pad.chamfer, vec![PadChamfer::TopLeft, PadChamfer::BottomLeft]);
+}
+
+#[test]
+fn compact_and_pretty_footprints_reparse_identically() {
+    for path in test_data_files("kicad_mod") {
+        let input = fs::read_to_string(&path).unwrap();
+        let original = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&input));
+        let model: FootprintLibrary = SyntaxItemSerializable::deserialize(&original);
+        let tokens = KiCadParser::generate_tokens(&model.serialize());
+
+        let pretty = KiCadParser::stringify_tokens_with_options::<FootprintLibrary>(&tokens, &FormatOptions::default());
+        let compact = KiCadParser::stringify_tokens_with_options::<FootprintLibrary>(&tokens, &FormatOptions { compact: true, ..Default::default() });
+        assert!(pretty.lines().count() > 1);
+        assert_eq!(compact.lines().count(), 1);
+        assert!(compact.len() < pretty.len());
+
+        let pretty = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&pretty));
+        let compact = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&compact));
+        assert!(compact.deep_equals(&pretty), "Compact output mismatch for {}", path.display());
+        assert!(compact.deep_equals(&original), "Compact round-trip mismatch for {}", path.display());
+    }
+}
+
+#[test]
+fn expanded_footprints_reparse_identically() {
+    for path in test_data_files("kicad_mod") {
+        let input = fs::read_to_string(&path).unwrap();
+        let original = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&input));
+        let model: FootprintLibrary = SyntaxItemSerializable::deserialize(&original);
+        let tokens = KiCadParser::generate_tokens(&model.serialize());
+
+        let pretty = KiCadParser::stringify_tokens_with_options::<FootprintLibrary>(&tokens, &FormatOptions::default());
+        let expanded = KiCadParser::stringify_tokens_with_options::<FootprintLibrary>(&tokens, &FormatOptions { expanded: true, ..Default::default() });
+        assert!(expanded.lines().count() > pretty.lines().count());
+        // Every list opens its own line, so the number of lines matches the number of lists
+        let list_count = tokens.iter().filter(|t| matches!(t, Token::OpenParen(_))).count();
+        assert_eq!(expanded.lines().filter(|l| l.trim_start().starts_with('(')).count(), list_count, "Lists share a line in {}", path.display());
+
+        let expanded = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&expanded));
+        assert!(expanded.deep_equals(&original), "Expanded round-trip mismatch for {}", path.display());
+    }
+}
+
+#[test]
+fn footprint_custom_pad_text_primitive() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data").join("kicad").join("Custom_Pad_Text.kicad_mod");
+    let input = fs::read_to_string(path).unwrap();
+    let tokens = KiCadParser::tokenize(&input);
+    let footprint: FootprintLibrary = SyntaxItemSerializable::deserialize(&KiCadParser::parse_syntax_item(&tokens));
+
+    let primitives = footprint.pads.first().and_then(|p| p.primitives.as_ref()).unwrap();
+    assert_eq!(primitives.polygons.len(), 1);
+    assert_eq!(primitives.texts.len(), 1);
+    assert_eq!(primitives.texts[0].text, "GND");
+    assert_eq!(primitives.texts[0].position.y, 0.5);
+}
+
+#[test]
+fn symbols_sharing_an_easyeda_symbol_become_aliases() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data").join("kicad").join("resistor.kicad_sym");
+    let input = fs::read_to_string(path).unwrap();
+    let mut symbol_lib: SymbolLib = SyntaxItemSerializable::deserialize(&KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&input)));
+    let mut alias = symbol_lib.symbols.remove(0);
+    let mut base = Symbol::deserialize(&alias.serialize());
+    base.add_hidden_property(EASYEDA_SYMBOL_PROPERTY, "1234");
+    symbol_lib.symbols = vec![base];
+
+    alias.symbol_id = "Alias".into();
+    assert!(symbol_lib.find_base_symbol("5678", &alias.symbol_id).is_none());
+    let base_id = symbol_lib.find_base_symbol("1234", &alias.symbol_id).unwrap().symbol_id.clone();
+    alias.derive_from(&base_id);
+
+    assert_eq!(alias.extends_id.as_deref(), Some(base_id.as_str()));
+    assert!(alias.pins.is_empty() && alias.units.is_empty());
+    assert!(!alias.properties.is_empty());
+    assert!(alias.serialize().get_named_child("extends").is_some());
+}
+
+#[test]
+fn numbers_are_written_in_fixed_point() {
+    assert_eq!(format_number(0.0001), "0.0001");
+    assert_eq!(format_number(1e-7), "0");
+    assert_eq!(format_number(-0.00000001), "0");
+    assert_eq!(format_number(12.5), "12.5");
+    assert_eq!(format_number(-3.0), "-3");
+    assert_eq!(format_number(1e7), "10000000");
+
+    let item = SyntaxItem::from_single_argument("width", SyntaxArgument::Number(0.0001, PositionPreference::None));
+    let tokens = KiCadParser::generate_tokens(&item);
+    assert_eq!(KiCadParser::stringify_tokens::<FootprintLibrary>(&tokens).trim(), "(width 0.0001)");
+}
+
+#[test]
+fn pin_name_and_number_effects_round_trip() {
+    let input = r#"(pin input line (at 0 0 0) (length 2.54)
+        (name "IN" (uuid "a1") (effects (font (size 1.5 1.5)) (justify left)))
+        (number "1" (effects (font (size 0.75 0.75) bold) hide)))"#;
+    let original = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(input));
+    let pin = SymbolPin::deserialize(&original);
+
+    assert_eq!(pin.name_effects.font.size.height, 1.5);
+    assert!(!pin.name_effects.hide);
+    assert_eq!(pin.number_effects.font.size.height, 0.75);
+    assert!(pin.number_effects.font.bold);
+    assert!(pin.number_effects.hide);
+
+    let reparsed = SymbolPin::deserialize(&pin.serialize());
+    assert_eq!(reparsed.name_effects.font.size.height, 1.5);
+    assert_eq!(reparsed.number_effects.font.size.height, 0.75);
+    assert!(reparsed.number_effects.font.bold && reparsed.number_effects.hide);
+}
+
+#[test]
+fn pin_graphic_styles_round_trip() {
+    let input = r#"(pin input line (at 0 0 0) (length 2.54) (name "IN" (effects (font (size 1.27 1.27)))) (number "1" (effects (font (size 1.27 1.27)))))"#;
+    let mut pin = SymbolPin::deserialize(&KiCadParser::parse_syntax_item(&KiCadParser::tokenize(input)));
+
+    for style in PinGraphicStyle::iter() {
+        pin.graphic_style = style;
+        assert_eq!(SymbolPin::deserialize(&pin.serialize()).graphic_style, style);
+    }
+}
+
+#[test]
+fn symbol_preserves_embedded_fonts() {
+    let input = r#"(symbol "Test" (property "Reference" "U" (at 0 0 0) (effects (font (size 1.27 1.27)))) (embedded_fonts no))"#;
+    let original = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(input));
+    let symbol = Symbol::deserialize(&original);
+
+    assert!(symbol.embedded_fonts.is_some());
+    assert!(symbol.serialize().get_named_child("embedded_fonts").is_some_and(|f| f.deep_equals(original.get_named_child("embedded_fonts").unwrap())));
+}
+
+#[test]
+fn symbol_library_preserves_embedded_files() {
+    let input = r#"(kicad_symbol_lib (version 20241209) (generator "kicad_symbol_editor")
+        (symbol "Test" (property "Reference" "U" (at 0 0 0) (effects (font (size 1.27 1.27))))
+            (embedded_fonts no)
+            (embedded_files (file (name "ds.pdf") (type datasheet) (data |KLUv/WBcA+1=|) (checksum "ABC123"))))
+        (embedded_files (file (name "model.step") (type model) (data |KLUv/QBY|) (checksum "DEF456"))))"#;
+    let original = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(input));
+    let symbol_lib = SymbolLib::deserialize(&original);
+
+    assert!(symbol_lib.embedded_files.is_some());
+    assert!(symbol_lib.symbols[0].embedded_files.is_some());
+    assert!(symbol_lib.serialize().deep_equals(&original));
+}
+
+#[test]
+fn net_tie_pad_groups_round_trip() {
+    let input = r#"(footprint "NetTie" (layer "F.Cu") (attr smd) (net_tie_pad_groups "1, 2" "3, 4"))"#;
+    let original = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(input));
+    let footprint = FootprintLibrary::deserialize(&original);
+
+    assert_eq!(footprint.net_tie_pad_groups, vec!["1, 2".to_string(), "3, 4".to_string()]);
+    assert!(footprint.serialize().deep_equals(&original));
+}
+
+#[test]
+fn zone_fill_polygons_round_trip() {
+    let input = r#"(footprint "Zone" (layer "F.Cu") (attr smd)
+        (zone (net 0) (net_name "") (layer "F.Cu") (hatch edge 0.5) (connect_pads (clearance 0.5)) (min_thickness 0.25)
+            (fill yes (thermal_gap 0.5) (thermal_bridge_width 0.5))
+            (polygon (pts (xy 0 0) (xy 1 0) (xy 1 1) (xy 0 1)))
+            (filled_polygon (layer "F.Cu") (island) (pts (xy 0.1 0.1) (xy 0.9 0.1) (xy 0.9 0.9) (xy 0.1 0.9)))
+            (fill_segments (layer "F.Cu") (pts (xy 0.1 0.1) (xy 0.9 0.1)) (pts (xy 0.1 0.9) (xy 0.9 0.9)))))"#;
+    let original = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(input));
+    let footprint = FootprintLibrary::deserialize(&original);
+
+    let zone = footprint.zones.first().unwrap();
+    assert_eq!(zone.filled_polygons.len(), 1);
+    assert!(zone.filled_polygons[0].island);
+    assert_eq!(zone.filled_polygons[0].points.len(), 4);
+    assert_eq!(zone.fill_segments[0].segments.len(), 2);
+    assert!(footprint.serialize().deep_equals(&original));
+}
+
+#[test]
+fn deterministic_uuids_depend_only_on_the_part() {
+    let input = r#"(footprint "Uuids" (layer "F.Cu") (attr smd)
+        (fp_line (start 0 0) (end 1 0) (layer "F.SilkS") (width 0.12))
+        (fp_line (start 0 1) (end 1 1) (layer "F.SilkS") (width 0.12) (uuid "0f3a1c4e-0000-4000-8000-000000000000"))
+        (pad "1" smd rect (at 0 0) (size 1 1) (layers "F.Cu")))"#;
+    let import = |lcsc_code: &str| {
+        let mut footprint = FootprintLibrary::deserialize(&KiCadParser::parse_syntax_item(&KiCadParser::tokenize(input)));
+        footprint.assign_deterministic_uuids(lcsc_code);
+        footprint
+    };
+
+    let (first, second, other) = (import("C25804"), import("C25804"), import("C25805"));
+    assert_eq!(first.lines[0].uuid, second.lines[0].uuid);
+    assert_eq!(first.pads[0].uuid, second.pads[0].uuid);
+    assert_ne!(first.lines[0].uuid, first.pads[0].uuid);
+    assert_ne!(first.lines[0].uuid, other.lines[0].uuid);
+    assert_eq!(first.lines[1].uuid.as_deref(), Some("0f3a1c4e-0000-4000-8000-000000000000"));
+}
+
+#[test]
+fn footprint_bounding_box() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data").join("kicad").join("R_0603_1608Metric.kicad_mod");
+    let input = fs::read_to_string(path).unwrap();
+    let tokens = KiCadParser::tokenize(&input);
+    let mut footprint: FootprintLibrary = SyntaxItemSerializable::deserialize(&KiCadParser::parse_syntax_item(&tokens));
+
+    // The courtyard encloses everything else
+    assert_eq!(footprint.bounding_box(), Some(BoundingBox2D { min_x: -1.48, min_y: -0.73, max_x: 1.48, max_y: 0.73 }));
+
+    // A half circle bulging out to the left is bounded by its leftmost point, not just its end points
+    footprint.arcs.push(FootprintArc {
+        start: Scalar2D::start(-1.5, -0.5),
+        mid: Some(Scalar2D::mid(-2.0, 0.0)),
+        end: Scalar2D::end(-1.5, 0.5),
+        layer: PcbLayer::FSilkS,
+        width: Some(0.12),
+        angle: None,
+        stroke: None,
+        uuid: None,
+        locked: false,
+    });
+    let bounding_box = footprint.bounding_box().unwrap();
+    assert!((bounding_box.min_x - -2.0).abs() < 1e-5);
+    assert_eq!((bounding_box.min_y, bounding_box.max_x, bounding_box.max_y), (-0.73, 1.48, 0.73));
+
+    // The same half circle in the legacy center/angle form
+    footprint.arcs.pop();
+    footprint.arcs.push(FootprintArc {
+        start: Scalar2D::start(-1.5, 0.0),
+        mid: None,
+        end: Scalar2D::end(-1.5, -0.5),
+        layer: PcbLayer::FSilkS,
+        width: Some(0.12),
+        angle: Some(-180.0),
+        stroke: None,
+        uuid: None,
+        locked: false,
+    });
+    let bounding_box = footprint.bounding_box().unwrap();
+    assert!((bounding_box.min_x - -2.0).abs() < 1e-5);
+    assert_eq!((bounding_box.min_y, bounding_box.max_x, bounding_box.max_y), (-0.73, 1.48, 0.73));
+
+    assert!(FootprintLibrary::deserialize(&KiCadParser::parse_syntax_item(&KiCadParser::tokenize(r#"(footprint "Empty")"#))).bounding_box().is_none());
+}
+
+#[test]
+fn streaming_tokenizer_matches_eager_tokenizer() {
+    for path in test_data_files("kicad_mod").into_iter().chain(test_data_files("kicad_sym")) {
+        let input = fs::read_to_string(&path).unwrap();
+        let streamed = KiCadParser::tokenize_reader(fs::File::open(&path).unwrap())
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(streamed, KiCadParser::tokenize(&input), "Token mismatch for {}", path.display());
+
+        let parsed = KiCadParser::parse_syntax_item_from_reader(fs::File::open(&path).unwrap()).unwrap();
+        assert!(parsed.deep_equals(&KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&input))));
+    }
+}
+
+#[test]
+fn streaming_tokenizer_decodes_utf8() {
+    let tokens = KiCadParser::tokenize("(descr \"µ\" 1)");
+    assert_eq!(tokens[1], Token::Identifier(1, "descr".into()));
+    assert_eq!(tokens[2], Token::QuotedString(7, "µ".into()));
+    assert_eq!(tokens[3], Token::Number(11, 1.0));
+
+    let invalid = KiCadParser::tokenize_reader(&b"(descr \"\xFF\")"[..]).collect::<std::io::Result<Vec<_>>>();
+    assert!(invalid.is_err());
+
+    let malformed = KiCadParser::tokenize_reader(&b"(descr abc\"def\")"[..]).collect::<std::io::Result<Vec<_>>>();
+    assert_eq!(malformed.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn syntax_item_is_displayed_indented() {
+    let item = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(r#"(pad "1" smd (at 1.5 -0.25) (layers "F.Cu" "F.Mask") (net (id 1)))"#));
+    assert_eq!(item.to_string(), "(pad \"1\" smd\n  (at 1.5 -0.25)\n  (layers \"F.Cu\" \"F.Mask\")\n  (net\n    (id 1)\n  )\n)");
+}
+
+#[test]
+fn lib_table_entries_are_updated_in_place() {
+    let item = |uri: &str| FootprintLibTableItem {
+        name: "jlcrs".into(),
+        uri: uri.into(),
+        lib_type: "KiCad".into(),
+        description: "JLCPCB parts".into(),
+        ..Default::default()
+    };
+    let mut table = FootprintLibTable { version: 7, libraries: vec![] };
+
+    assert!(table.upsert_library(item("${KIPRJMOD}/old/jlcrs.pretty")));
+    assert!(!table.upsert_library(item("${KIPRJMOD}/old/jlcrs.pretty")));
+    table.libraries[0].disabled = true;
+
+    assert!(table.upsert_library(item("${KIPRJMOD}/new/jlcrs.pretty")));
+    assert_eq!(table.libraries.len(), 1);
+    assert_eq!(table.libraries[0].uri, "${KIPRJMOD}/new/jlcrs.pretty");
+    assert!(table.libraries[0].disabled);
+}
+
+#[test]
+fn files_are_parsed_and_written() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data").join("kicad").join("R_0603_1608Metric.kicad_mod");
+    let footprint: FootprintLibrary = KiCadParser::parse_file(&path).unwrap();
+
+    let output_path = std::env::temp_dir().join(format!("jlcrs-parse-file-{}.kicad_mod", std::process::id()));
+    KiCadParser::write_file(&output_path, &footprint).unwrap();
+    let written: FootprintLibrary = KiCadParser::parse_file(&output_path).unwrap();
+    fs::remove_file(&output_path).unwrap();
+    assert!(written.serialize().deep_equals(&footprint.serialize()));
+
+    let error = KiCadParser::parse_file::<FootprintLibrary>(path.with_extension("missing")).unwrap_err();
+    assert!(error.to_string().contains("R_0603_1608Metric.missing"));
+}
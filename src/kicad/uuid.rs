@@ -0,0 +1,42 @@
+//! Name based (version 5) UUIDs, used to give generated elements stable identifiers.
+
+use sha1::{Digest, Sha1};
+
+/// The RFC 4122 namespace for names that are URLs
+pub const NAMESPACE_URL: [u8; 16] = [
+    0x6b, 0xa7, 0xb8, 0x11, 0x9d, 0xad, 0x11, 0xd1, 0x80, 0xb4, 0x00, 0xc0, 0x4f, 0xd4, 0x30, 0xc8,
+];
+
+/// Returns the version 5 UUID of the name within the namespace
+pub fn uuid_v5(namespace: &[u8; 16], name: &str) -> [u8; 16] {
+    let mut hasher = Sha1::new();
+    hasher.update(namespace);
+    hasher.update(name.as_bytes());
+    let hash = hasher.finalize();
+
+    let mut uuid = [0u8; 16];
+    uuid.copy_from_slice(&hash[..16]);
+    uuid[6] = (uuid[6] & 0x0f) | 0x50;
+    uuid[8] = (uuid[8] & 0x3f) | 0x80;
+    uuid
+}
+
+/// Formats the UUID in its hyphenated lowercase form, as KiCad writes it
+pub fn format_uuid(uuid: &[u8; 16]) -> String {
+    let hex = uuid.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMESPACE_DNS: [u8; 16] = [
+        0x6b, 0xa7, 0xb8, 0x10, 0x9d, 0xad, 0x11, 0xd1, 0x80, 0xb4, 0x00, 0xc0, 0x4f, 0xd4, 0x30, 0xc8,
+    ];
+
+    #[test]
+    fn v5_uuid_matches_reference_value() {
+        assert_eq!(format_uuid(&uuid_v5(&NAMESPACE_DNS, "python.org")), "886313e1-3b8a-5372-9b90-0c9aee199e5d");
+    }
+}
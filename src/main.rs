@@ -1,327 +1,933 @@
-use crate::args::{CliArguments, Command};
+use crate::args::{CliArguments, Command, LibraryOptions, PartNameSource};
 use crate::easyeda::api::component_data::ComponentDataResponse;
-use crate::easyeda::api::product_data::ProductDataResponse;
+use crate::easyeda::api::product_data::{format_product_table, ProductDataResponse, ProductInfo};
+use crate::easyeda::encoding::decode_response_body;
+use crate::easyeda::errors::{FootprintConverterError, SymbolConverterError};
 use crate::easyeda::footprint::EasyEDAFootprint;
 use crate::easyeda::symbol::EasyEDASymbol;
 use crate::kicad::model::footprint_lib_table::{FootprintLibTable, FootprintLibTableItem};
-use crate::kicad::model::footprint_library::{FootprintLibrary, FootprintModel, Scalar3D};
+use crate::kicad::model::footprint_library::{FootprintLibrary, FootprintModel};
 use crate::kicad::model::symbol_lib_table::{SymbolLibTable, SymbolLibTableItem};
 use crate::kicad::model::symbol_library::{Symbol, SymbolLib};
-use crate::kicad::syntax::{KiCadParser, SyntaxItemSerializable};
+use crate::kicad::model::symbol_utils::EASYEDA_SYMBOL_PROPERTY;
+use crate::kicad::syntax::{write_atomic, FormatOptions, KiCadParser};
+use crate::model_3d::{compute_model_placement, model_file_reference, realign_footprint_model, resolve_model_file, HasBoundingBox, MODEL_TRANSFORM_PROPERTY};
+use anyhow::Context;
 use clap::Parser;
+use log::{debug, info, warn, LevelFilter};
 use itertools::Itertools;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::Write;
 use opencascade::primitives::Shape;
 use std::fs;
+use std::path::{Component, Path, PathBuf};
 
 mod kicad;
 mod easyeda;
 mod dev;
 mod args;
+mod model_3d;
+mod validate;
+mod units;
 
 fn main() -> anyhow::Result<()> {
     let cli = CliArguments::parse();
+    init_logger(cli.verbose, cli.quiet);
     match cli.command {
-        Command::Import { code, update, name, description, root } => {
-            let project_root_dir = std::env::current_dir()?;
-
-            let mut library_root_dir = std::env::current_dir()?;
-            let library_name = sanitize_filename::sanitize(&name);
-            let library_name = library_name.as_str();
-            if let Some(root) = root {
-                library_root_dir = library_root_dir.join(root);
-                if !library_root_dir.exists() {
-                    fs::create_dir_all(&library_root_dir)?;
+        Command::Import { codes, validate_only, only_missing, resume, options } => {
+            if validate_only {
+                return validate_generated_library(&options);
+            }
+
+            let codes = match only_missing && !options.update {
+                true => {
+                    let symbol_lib = load_symbol_library(&symbol_library_path(&options)?)?;
+                    let (missing, skipped) = split_missing_codes(codes, &symbol_lib);
+                    for code in skipped {
+                        info!("Skipped '{}', it has already been imported", code);
+                    }
+                    missing
                 }
+                false => codes,
+            };
+
+            let progress_path = library_root_dir(&options)?.join(PROGRESS_FILE_NAME);
+            let mut completed_codes = match resume {
+                true => load_progress(&progress_path)?,
+                false => vec![],
+            };
+            let (codes, skipped) = split_completed_codes(codes, &completed_codes);
+            for code in skipped {
+                info!("Skipped '{}', it was completed by a previous run", code);
             }
 
-            let library_path_relative = library_root_dir.to_str().unwrap().replace(project_root_dir.to_str().unwrap(), "${KIPRJMOD}");
+            let mut renamed_parts = Vec::new();
+            let mut skipped_parts = Vec::new();
+            for code in codes {
+                let summary = match import_lcsc_component(&code, &options) {
+                    Ok(summary) => summary,
+                    Err(error) if is_unsupported_part(&error) => {
+                        warn!("Skipped '{}', it uses a feature that can't be converted: {:#}", code, error);
+                        skipped_parts.push(SkippedPart { code, reason: format!("{error:#}") });
+                        continue;
+                    }
+                    Err(error) => return Err(error),
+                };
+                print_summary(&summary, &options)?;
+                renamed_parts.extend(summary.renamed_part);
+                completed_codes.push(code);
+                save_progress(&progress_path, &completed_codes)?;
+            }
+            report_renamed_parts(&renamed_parts);
+            report_skipped_parts(&skipped_parts);
+
+            // Every code has been imported, there is nothing left to resume
+            if fs::exists(&progress_path)? {
+                fs::remove_file(&progress_path)?;
+            }
+        }
+        Command::Convert { symbol, footprint, part_name, code, datasheet, options } => {
+            let part_name = match part_name {
+                Some(part_name) => part_name,
+                None => Path::new(&symbol).file_stem().unwrap().to_string_lossy().to_string(),
+            };
+
+            info!("Converting '{}'...", part_name);
 
-            let lcsc_code = code[1..].parse::<u32>();
-            if !code.starts_with("C") || lcsc_code.is_err() {
-                return Err(anyhow::anyhow!("The provided LCSC code is in an invalid format."));
+            let mut component = ProductInfo::default();
+            component.mpn = part_name;
+            component.number = code.unwrap_or_default();
+            if let Some(datasheet) = datasheet {
+                component.device_info.attributes.insert("Datasheet".into(), datasheet);
             }
-            let lcsc_code = format!("C{}", lcsc_code?).clone();
-            let lcsc_code = lcsc_code.as_str();
-
-            println!("Importing '{}'...", lcsc_code);
-
-            // Download component data
-            let response = ureq::get(
-                format!("https://pro.easyeda.com/api/eda/product/search?keyword={code}&currPage=1&pageSize=1")
-            ).call()?;
-            let body_string = response.into_body().read_to_string()?;
-            let response = serde_json::from_str::<ProductDataResponse>(&body_string)?;
-            let result = response.result.product_list.iter().find(|p| p.number == code);
-            if let None = result {
-                return Err(anyhow::anyhow!("Product code not found: '{}'", lcsc_code));
+            component.device_info.symbol_info.data_str = fs::read_to_string(&symbol)?;
+            component.device_info.footprint_info.data_str = fs::read_to_string(&footprint)?;
+            let summary = import_component(&component, &options)?;
+            print_summary(&summary, &options)?;
+            report_renamed_parts(summary.renamed_part.as_slice());
+        }
+        Command::Search { keyword, limit } => {
+            let response = search_products(&keyword, limit)?;
+            match response.result.product_list.is_empty() {
+                true => info!("No parts were found for '{}'", keyword),
+                false => print!("{}", format_product_table(&response.result.product_list)),
             }
-            let component_result = result.unwrap();
-            let device_name = component_result.mpn.clone();
-            let safe_part_name = sanitize_filename::sanitize(&device_name);
+        }
+        Command::Realign { footprint, project_dir } => {
+            let project_root_dir = project_root_dir(project_dir.as_ref())?;
 
-            let mut symbol = EasyEDASymbol::parse(&component_result.device_info.symbol_info.data_str)?;
-            let mut footprint = EasyEDAFootprint::parse(&component_result.device_info.footprint_info.data_str)?;
+            let mut kicad_footprint = load_footprint(Path::new(&footprint))?;
+
+            let Some(model) = &kicad_footprint.model else {
+                return Err(anyhow::anyhow!("The footprint '{}' has no 3D model", footprint));
+            };
+            let model_path = resolve_model_file(&model.model_file, &project_root_dir);
 
-            symbol.part_number = Some(lcsc_code.into());
-            footprint.part_number = Some(lcsc_code.into());
+            let shape = Shape::read_step(&model_path)?;
+            realign_footprint_model(&mut kicad_footprint, &shape.bounding_box())?;
 
-            let designator = symbol.get_designator().clone();
+            KiCadParser::write_file(&footprint, &kicad_footprint)?;
+
+            info!("The 3D model of '{}' has been realigned.", kicad_footprint.footprint_id);
+        }
+    }
+    Ok(())
+}
 
-            let mut kicad_symbol: Symbol = symbol.try_into()?;
-            let mut kicad_footprint: FootprintLibrary = footprint.try_into()?;
 
-            kicad_symbol.symbol_id = device_name.clone();
-            kicad_footprint.footprint_id = device_name.clone();
+fn import_lcsc_component(code: &str, options: &LibraryOptions) -> anyhow::Result<ImportSummary> {
+    let lcsc_code = code[1..].parse::<u32>();
+    if !code.starts_with("C") || lcsc_code.is_err() {
+        return Err(anyhow::anyhow!("The provided LCSC code is in an invalid format: '{}'", code));
+    }
+    let lcsc_code = format!("C{}", lcsc_code?).clone();
+    let lcsc_code = lcsc_code.as_str();
 
-            // Add component properties
-            kicad_symbol.add_hidden_property("Part Number", device_name.as_str());
-            kicad_symbol.add_hidden_property("LCSC", lcsc_code);
-            kicad_symbol.add_hidden_property("Footprint", format!("{library_name}:{device_name}").as_str());
-            kicad_footprint.add_hidden_property("LCSC", lcsc_code);
+    info!("Importing '{}'...", lcsc_code);
 
-            if let Some(datasheet) = component_result.device_info.attributes.get("Datasheet") {
-                kicad_symbol.add_hidden_property("Datasheet", datasheet);
-                kicad_footprint.add_hidden_property("Datasheet", datasheet);
-            }
-            if let Some(description) = component_result.device_info.attributes.get("Description").cloned().or_else(|| Some(component_result.device_info.description.clone())) {
-                kicad_symbol.add_hidden_property("Description", &description);
-                kicad_footprint.add_hidden_property("Description", &description);
-                kicad_footprint.description = Some(description.clone());
-            }
-            if let Some(jlc_part_class) = component_result.device_info.attributes.get("JLCPCB Part Class") {
-                kicad_symbol.add_hidden_property("JLCPCB Part Class", jlc_part_class);
-                kicad_footprint.add_hidden_property("JLCPCB Part Class", jlc_part_class);
-            }
-            if let Some(value) = component_result.device_info.attributes.get("Value") {
-                kicad_symbol.add_property("Value", value.as_str(), 0.0, 0.0);
-            } else {
-                kicad_symbol.add_property("Value", device_name.as_str(), 0.0, 0.0);
+    // Download component data
+    debug!("Downloading component data...");
+    let response = search_products(lcsc_code, 5)?;
+    let component_result = response.find_product(lcsc_code, options.allow_fuzzy_match)?;
+    import_component(component_result, options)
+}
+
+fn search_products(keyword: &str, page_size: u32) -> anyhow::Result<ProductDataResponse> {
+    let response = ureq::get("https://pro.easyeda.com/api/eda/product/search")
+        .query("keyword", keyword)
+        .query("currPage", "1")
+        .query("pageSize", page_size.to_string())
+        .call()?;
+    let body_string = read_response_body(response)?;
+    Ok(serde_json::from_str::<ProductDataResponse>(&body_string)?)
+}
+
+fn project_root_dir(project_dir: Option<&String>) -> anyhow::Result<PathBuf> {
+    let current_dir = std::env::current_dir()?;
+    Ok(match project_dir {
+        Some(project_dir) => std::path::absolute(current_dir.join(project_dir))?,
+        None => current_dir,
+    })
+}
+
+fn library_root_dir(options: &LibraryOptions) -> anyhow::Result<PathBuf> {
+    let mut library_root_dir = project_root_dir(options.project_dir.as_ref())?;
+    if let Some(root) = &options.root {
+        library_root_dir = library_root_dir.join(root);
+    }
+    Ok(library_root_dir)
+}
+
+/// Returns the path as a `${KIPRJMOD}` based URI, or as an absolute path if it is outside the project directory
+fn project_relative_uri(path: &Path, project_root_dir: &Path) -> String {
+    let path = normalize_path(path);
+    let project_root_dir = normalize_path(project_root_dir);
+
+    // `${KIPRJMOD}/..` paths break as soon as the project is moved or opened from another location
+    let Ok(relative_path) = path.strip_prefix(&project_root_dir) else {
+        warn!("The library directory '{}' is outside the project directory, referencing it by its absolute path", path.display());
+        return path.to_str().unwrap().into();
+    };
+
+    std::iter::once("${KIPRJMOD}".to_string())
+        .chain(relative_path.components().map(|c| c.as_os_str().to_string_lossy().to_string()))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Resolves `.` and `..` components without touching the file system
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => { normalized.pop(); }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn symbol_library_path(options: &LibraryOptions) -> anyhow::Result<PathBuf> {
+    let library_name = sanitize_filename::sanitize(&options.name);
+    Ok(library_root_dir(options)?.join(format!("{library_name}.kicad_sym")))
+}
+
+fn load_symbol_library(symbol_lib_path: &Path) -> anyhow::Result<SymbolLib> {
+    if !fs::exists(symbol_lib_path)? {
+        return Ok(SymbolLib {
+            version: 20211014,
+            generator: "jlcrs".into(),
+            generator_version: None,
+            symbols: vec![],
+            embedded_files: None,
+            unknown_items: vec![],
+        });
+    }
+
+    KiCadParser::parse_file(symbol_lib_path)
+}
+
+fn load_footprint(footprint_path: &Path) -> anyhow::Result<FootprintLibrary> {
+    KiCadParser::parse_file(footprint_path)
+}
+
+/// Returns a warning if the footprint has no pads but is allowed to be imported anyway
+fn check_footprint_pads(footprint: &FootprintLibrary, device_name: &str, allow_empty: bool) -> anyhow::Result<Option<String>> {
+    if !footprint.pads.is_empty() {
+        return Ok(None);
+    }
+
+    if !allow_empty {
+        return Err(anyhow::anyhow!("The footprint of '{}' has no pads, use --allow-empty to import it anyway", device_name));
+    }
+
+    Ok(Some(format!("The footprint of '{}' has no pads", device_name)))
+}
+
+/// Returns a warning listing the symbol pins without a footprint pad and the pads without a symbol pin.
+/// Unnumbered pads, such as mounting holes, are not expected to have a pin.
+fn check_pin_pad_numbers(symbol: &Symbol, footprint: &FootprintLibrary) -> Option<String> {
+    let pin_numbers = symbol.pin_numbers();
+    let pad_numbers = footprint.pads.iter()
+        .map(|p| p.number.clone())
+        .filter(|n| !n.is_empty())
+        .collect::<BTreeSet<_>>();
+
+    let sorted = |numbers: Vec<&String>| numbers.into_iter()
+        .sorted_by_key(|n| (n.parse::<u32>().ok(), n.to_string()))
+        .join(", ");
+    let unmatched_pins = sorted(pin_numbers.difference(&pad_numbers).collect());
+    let unmatched_pads = sorted(pad_numbers.difference(&pin_numbers).collect());
+    match (unmatched_pins.is_empty(), unmatched_pads.is_empty()) {
+        (true, true) => None,
+        (false, true) => Some(format!("The symbol pins {unmatched_pins} have no matching footprint pad")),
+        (true, false) => Some(format!("The footprint pads {unmatched_pads} have no matching symbol pin")),
+        (false, false) => Some(format!("The symbol pins {unmatched_pins} have no matching footprint pad, and the footprint pads {unmatched_pads} have no matching symbol pin")),
+    }
+}
+
+fn footprint_belongs_to_other_part(existing_footprint: &FootprintLibrary, lcsc_code: Option<&str>) -> bool {
+    existing_footprint.get_property("LCSC") != lcsc_code
+}
+
+fn validate_generated_library(options: &LibraryOptions) -> anyhow::Result<()> {
+    let library_name = sanitize_filename::sanitize(&options.name);
+    let symbol_lib_path = symbol_library_path(options)?;
+    let footprint_lib_root = library_root_dir(options)?.join(format!("{library_name}.pretty"));
+
+    // Parser panics are reported as validation failures, keep them from being printed as crashes
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let failures = validate::validate_library(&symbol_lib_path, &footprint_lib_root);
+    std::panic::set_hook(default_hook);
+
+    let failures = failures?;
+    for failure in &failures {
+        warn!("'{}' is invalid: {}", failure.path.display(), failure.reason);
+    }
+
+    match failures.len() {
+        0 => {
+            info!("The library '{}' is valid.", library_name);
+            Ok(())
+        }
+        count => Err(anyhow::anyhow!("{} file(s) of the library '{}' failed validation", count, library_name)),
+    }
+}
+
+/// Lists the LCSC codes completed by an import run, so an interrupted run can be resumed
+const PROGRESS_FILE_NAME: &str = ".jlcrs-progress";
+
+fn load_progress(progress_path: &Path) -> anyhow::Result<Vec<String>> {
+    if !fs::exists(progress_path)? {
+        return Ok(vec![]);
+    }
+
+    Ok(fs::read_to_string(progress_path)?
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+fn save_progress(progress_path: &Path, completed_codes: &[String]) -> anyhow::Result<()> {
+    if let Some(parent) = progress_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(write_atomic(progress_path, completed_codes.join("\n"))?)
+}
+
+/// Splits the given LCSC codes into ones still to be imported and ones completed by a previous run
+fn split_completed_codes(codes: Vec<String>, completed_codes: &[String]) -> (Vec<String>, Vec<String>) {
+    codes.into_iter().partition(|code| !completed_codes.contains(code))
+}
+
+/// Splits the given LCSC codes into ones missing from the library and ones that are already imported
+fn split_missing_codes(codes: Vec<String>, symbol_lib: &SymbolLib) -> (Vec<String>, Vec<String>) {
+    codes.into_iter().partition(|code| !symbol_lib.contains_lcsc_code(code))
+}
+
+/// A part that had to be saved under a different file name than its MPN
+#[derive(Debug)]
+struct RenamedPart {
+    original: String,
+    file_name: String,
+}
+
+/// A part of a batch import that was skipped because it can't be converted
+struct SkippedPart {
+    code: String,
+    reason: String,
+}
+
+/// Returns true if the import failed because the part uses EasyEDA features that can't be converted,
+/// as opposed to problems that would affect every other part too (network, file access, ...)
+fn is_unsupported_part(error: &anyhow::Error) -> bool {
+    error.chain().any(|e| e.is::<SymbolConverterError>() || e.is::<FootprintConverterError>())
+}
+
+/// The outcome of importing a single part, printed as JSON with `--json`
+#[derive(Debug, Default, Serialize)]
+struct ImportSummary {
+    lcsc_code: Option<String>,
+    mpn: String,
+    files_written: Vec<PathBuf>,
+    step_model_found: bool,
+    warnings: Vec<String>,
+    #[serde(skip)]
+    renamed_part: Option<RenamedPart>,
+}
+
+impl ImportSummary {
+    fn warn(&mut self, message: String) {
+        warn!("{}", message);
+        self.warnings.push(message);
+    }
+}
+
+fn print_summary(summary: &ImportSummary, options: &LibraryOptions) -> anyhow::Result<()> {
+    if options.json {
+        println!("{}", serde_json::to_string(summary)?);
+    }
+    Ok(())
+}
+
+/// Returns the name the part is saved under in the KiCad libraries, and a warning if it can't be named as requested
+fn part_name(mpn: &str, lcsc_code: Option<&str>, name_from: PartNameSource) -> (String, Option<String>) {
+    match (name_from, lcsc_code) {
+        (PartNameSource::Mpn, _) => (mpn.to_string(), None),
+        (PartNameSource::Lcsc, Some(lcsc_code)) => (lcsc_code.to_string(), None),
+        (PartNameSource::Lcsc, None) => (mpn.to_string(), Some(format!("The part '{mpn}' has no LCSC code, naming it after its MPN instead"))),
+    }
+}
+
+/// Returns the file name to save the part under, and a warning if it differs from the part name
+/// or contains characters that are unsafe on some platforms.
+fn part_file_name(part_name: &str, keep_original_names: bool) -> (String, Option<String>) {
+    let sanitized = sanitize_filename::sanitize(part_name);
+    if sanitized == part_name {
+        return (sanitized, None);
+    }
+
+    if keep_original_names && !part_name.contains(['/', '\\']) && !part_name.chars().any(char::is_control) {
+        let warning = format!("The part name '{part_name}' contains characters that are not allowed in file names on some platforms");
+        return (part_name.to_string(), Some(warning));
+    }
+
+    let warning = match keep_original_names {
+        true => format!("The part name '{part_name}' contains a path separator and can't be kept, saving it as '{sanitized}'"),
+        false => format!("The part name '{part_name}' contains characters that are unsafe in file names, saving it as '{sanitized}'. Use --keep-original-names to keep it."),
+    };
+    (sanitized, Some(warning))
+}
+
+fn report_renamed_parts(renamed_parts: &[RenamedPart]) {
+    if renamed_parts.is_empty() {
+        return;
+    }
+
+    info!("The following parts were saved under a different name:");
+    for part in renamed_parts {
+        info!("  {} -> {}", part.original, part.file_name);
+    }
+}
+
+fn report_skipped_parts(skipped_parts: &[SkippedPart]) {
+    if skipped_parts.is_empty() {
+        return;
+    }
+
+    warn!("The following parts were skipped:");
+    for part in skipped_parts {
+        warn!("  {}: {}", part.code, part.reason);
+    }
+}
+
+fn import_component(component_result: &ProductInfo, options: &LibraryOptions) -> anyhow::Result<ImportSummary> {
+    let project_root_dir = project_root_dir(options.project_dir.as_ref())?;
+
+    let library_root_dir = library_root_dir(options)?;
+    let library_name = sanitize_filename::sanitize(&options.name);
+    let library_name = library_name.as_str();
+    if !library_root_dir.exists() {
+        fs::create_dir_all(&library_root_dir)?;
+    }
+
+    let library_path_relative = project_relative_uri(&library_root_dir, &project_root_dir);
+
+    let lcsc_code = Some(component_result.number.clone()).filter(|c| !c.is_empty());
+    let device_name = component_result.mpn.clone();
+    let mut summary = ImportSummary {
+        lcsc_code: lcsc_code.clone(),
+        mpn: device_name.clone(),
+        ..Default::default()
+    };
+    let (part_name, name_warning) = part_name(&device_name, lcsc_code.as_deref(), options.name_from);
+    if let Some(name_warning) = name_warning {
+        summary.warn(name_warning);
+    }
+    let (safe_part_name, name_warning) = part_file_name(&part_name, options.keep_original_names);
+    if let Some(name_warning) = name_warning {
+        summary.warn(name_warning);
+    }
+
+    debug!("Parsing EasyEDA data for '{}'...", device_name);
+    let mut symbol = EasyEDASymbol::parse(&component_result.device_info.symbol_info.data_str)?;
+    let mut footprint = match options.symbol_only {
+        true => None,
+        false => Some(EasyEDAFootprint::parse(&component_result.device_info.footprint_info.data_str)
+            .context("Failed to parse the footprint, use --symbol-only to import just the symbol")?),
+    };
+
+    let datasheet = component_result.device_info.attributes.get("Datasheet").cloned();
+    symbol.part_number = lcsc_code.clone();
+    symbol.datasheet = datasheet.clone();
+    symbol.fill_style = options.fill_style;
+    symbol.pin_names_offset = options.pin_name_offset;
+    if let Some(footprint) = footprint.as_mut() {
+        footprint.part_number = lcsc_code.clone();
+        footprint.datasheet = datasheet;
+        footprint.package = Some(component_result.package.clone()).filter(|p| !p.is_empty());
+        footprint.kicad_version = options.kicad_version;
+        footprint.raster_images = options.raster_images;
+        footprint.paste_ratio = options.paste_ratio;
+        footprint.arc_max_error = options.arc_max_error;
+    }
+
+    let designator = symbol.get_designator().clone();
+    let easyeda_source = footprint.as_ref().and_then(|f| f.head.as_ref()).and_then(|h| h.source.clone());
+    let unmapped_rules = footprint.as_ref().and_then(|f| f.describe_unmapped_rules());
+
+    debug!("Converting to KiCad format...");
+    let mut kicad_symbol: Symbol = symbol.try_into()?;
+    let mut kicad_footprint: Option<FootprintLibrary> = footprint.map(|f| f.try_into()).transpose()?;
+    if let Some(kicad_footprint) = &kicad_footprint {
+        if let Some(pads_warning) = check_footprint_pads(kicad_footprint, &device_name, options.allow_empty)? {
+            summary.warn(pads_warning);
+        }
+        if let Some(numbers_warning) = check_pin_pad_numbers(&kicad_symbol, kicad_footprint) {
+            summary.warn(numbers_warning);
+        }
+    }
+
+    kicad_symbol.symbol_id = part_name.clone();
+
+    // Add component properties
+    kicad_symbol.add_hidden_property("Part Number", device_name.as_str());
+    if let Some(kicad_footprint) = kicad_footprint.as_mut() {
+        kicad_footprint.footprint_id = safe_part_name.clone();
+        if let Some(tags) = &options.tags {
+            kicad_footprint.tags = Some(tags.clone());
+        }
+        if let Some(generator) = &options.generator {
+            kicad_footprint.generator = Some(generator.clone());
+        }
+        for net_tie_group in &options.net_tie {
+            let missing_pads = net_tie_group.split(", ").filter(|n| !kicad_footprint.pads.iter().any(|p| p.number == *n)).join(", ");
+            if !missing_pads.is_empty() {
+                summary.warn(format!("The net tie pads {missing_pads} don't exist in the footprint of '{device_name}'"));
             }
-            if let Some(designator) = designator {
-                kicad_symbol.add_property("Reference", &designator, 0.0, 0.0);
+        }
+        kicad_footprint.net_tie_pad_groups = options.net_tie.clone();
+        kicad_symbol.add_hidden_property("Footprint", format!("{library_name}:{safe_part_name}").as_str());
+    }
+    if let Some(description) = component_result.device_info.attributes.get("Description").cloned().or_else(|| Some(component_result.device_info.description.clone())).filter(|d| !d.is_empty()) {
+        kicad_symbol.add_hidden_property("Description", &description);
+        if let Some(kicad_footprint) = kicad_footprint.as_mut() {
+            kicad_footprint.add_hidden_property("Description", &description);
+            kicad_footprint.description = Some(description.clone());
+        }
+    }
+    if let Some(easyeda_source) = easyeda_source.filter(|s| !s.is_empty()) {
+        kicad_symbol.add_hidden_property("EasyEDA Source", &easyeda_source);
+        if let Some(kicad_footprint) = kicad_footprint.as_mut() {
+            kicad_footprint.add_hidden_property("EasyEDA Source", &easyeda_source);
+        }
+    }
+    if let Some(jlc_part_class) = component_result.device_info.attributes.get("JLCPCB Part Class") {
+        kicad_symbol.add_hidden_property("JLCPCB Part Class", jlc_part_class);
+        if let Some(kicad_footprint) = kicad_footprint.as_mut() {
+            kicad_footprint.add_hidden_property("JLCPCB Part Class", jlc_part_class);
+        }
+    }
+    if let Some(value) = component_result.device_info.attributes.get("Value") {
+        kicad_symbol.set_property("Value", value.as_str());
+    } else {
+        kicad_symbol.set_property("Value", device_name.as_str());
+    }
+    if let Some(designator) = designator {
+        kicad_symbol.set_property("Reference", &designator);
+    }
+
+    // Check if symbol lib exists, create if it doesn't
+    let symbol_lib_path = symbol_library_path(options)?;
+    let mut symbol_lib = load_symbol_library(&symbol_lib_path)?;
+    if let Some(generator) = &options.generator {
+        symbol_lib.generator = generator.clone();
+    }
+
+    // Parts sharing an EasyEDA symbol with an already imported part are added as aliases of it
+    let easyeda_symbol_uuid = &component_result.device_info.symbol_info.uuid;
+    if !easyeda_symbol_uuid.is_empty() {
+        kicad_symbol.add_hidden_property(EASYEDA_SYMBOL_PROPERTY, easyeda_symbol_uuid);
+        if let Some(base_symbol) = symbol_lib.find_base_symbol(easyeda_symbol_uuid, &kicad_symbol.symbol_id) {
+            debug!("Deriving '{}' from '{}'...", device_name, base_symbol.symbol_id);
+            kicad_symbol.derive_from(&base_symbol.symbol_id);
+        }
+    }
+    let existing_component = symbol_lib.symbols.iter_mut().find(|s| s.symbol_id == kicad_symbol.symbol_id);
+    if !options.update && existing_component.is_some() {
+        return Err(anyhow::anyhow!("This component has already been imported into the project, aborting. Use the --update flag to overwrite an existing component."));
+    }
+
+    // Make sure we don't overwrite a footprint that belongs to a different part
+    let footprint_lib_root = library_root_dir.join(format!("{library_name}.pretty").as_str());
+    let footprint_path = footprint_lib_root.join(format!("{safe_part_name}.kicad_mod").as_str());
+    if kicad_footprint.is_some() && !options.update && fs::exists(&footprint_path)? {
+        let existing_footprint = load_footprint(&footprint_path)?;
+        if footprint_belongs_to_other_part(&existing_footprint, lcsc_code.as_deref()) {
+            return Err(anyhow::anyhow!("The footprint '{}' already exists and belongs to a different part, aborting. Use the --update flag to overwrite it.", footprint_path.display()));
+        }
+    }
+
+    if existing_component.is_none() {
+        debug!("Adding device '{}'...", device_name);
+        symbol_lib.symbols.push(kicad_symbol);
+    } else if let Some(existing_symbol) = existing_component {
+        *existing_symbol = kicad_symbol;
+    }
+
+    let format_options = FormatOptions { compact: options.compact, expanded: options.expanded };
+
+    debug!("Writing symbol library '{}'...", symbol_lib_path.display());
+    KiCadParser::write_file_with_options(&symbol_lib_path, &symbol_lib, &format_options)?;
+    summary.files_written.push(symbol_lib_path);
+
+    // Check if the sym-lib-table file exists, create it if it doesn't
+    let sym_lib_table_path = project_root_dir.join("sym-lib-table");
+    let mut sym_lib_table = match fs::exists(&sym_lib_table_path)? {
+        true => KiCadParser::parse_file(&sym_lib_table_path)?,
+        false => {
+            SymbolLibTable {
+                version: 7,
+                libraries: vec![],
             }
+        }
+    };
+    let sym_lib_table_changed = sym_lib_table.upsert_library(SymbolLibTableItem {
+        name: library_name.into(),
+        description: options.description.clone(),
+        hidden: false,
+        disabled: false,
+        lib_type: "KiCad".into(),
+        options: String::new(),
+        uri: format!("{library_path_relative}/{library_name}.kicad_sym").into(),
+    });
+    if sym_lib_table_changed {
+        debug!("Updating '{}' in the symbol library table...", library_name);
+        KiCadParser::write_file(&sym_lib_table_path, &sym_lib_table)?;
+        summary.files_written.push(sym_lib_table_path);
+    }
 
-            // Check if symbol lib exists, create if it doesn't
-            let symbol_lib_path = library_root_dir.join(format!("{library_name}.kicad_sym").as_str());
-            let mut symbol_lib = match fs::exists(&symbol_lib_path)? {
-                true => {
-                    let lib_data = fs::read_to_string(&symbol_lib_path)?;
-                    let tokens = KiCadParser::tokenize(&lib_data);
-                    let item = KiCadParser::parse_syntax_item(&tokens);
-                    let model: SymbolLib = SyntaxItemSerializable::deserialize(&item);
-                    model
+    if let Some(mut kicad_footprint) = kicad_footprint {
+        // Download STEP model data
+        let mut existing_model = None;
+        if let Some(model_3d) = &component_result.device_info.footprint_info.model_3d {
+            let model_directory = match &options.model_dir {
+                Some(model_dir) => std::path::absolute(model_dir)?,
+                None => library_root_dir
+                    .join(format!("{library_name}.pretty").as_str())
+                    .join("models"),
+            };
+
+            let model_path = model_directory.join(format!("{safe_part_name}.step"));
+
+            if !options.overwrite_model && fs::exists(&model_path)? {
+                debug!("The STEP model '{}' already exists, skipping the download", model_path.display());
+                existing_model = match fs::exists(&footprint_path)? {
+                    true => load_footprint(&footprint_path)?.model,
+                    false => None,
+                };
+                if existing_model.is_none() {
+                    let shape = Shape::read_step(&model_path)
+                        .with_context(|| format!("Failed to read the STEP model '{}'", model_path.display()))?;
+                    kicad_footprint.model = Some(place_model(&model_path, &shape, &model_3d.transform, &project_root_dir, options)?);
                 }
-                false => {
-                    SymbolLib {
-                        version: 20211014,
-                        generator: "jlcrs".into(),
-                        generator_version: None,
-                        symbols: vec![],
+                kicad_footprint.add_hidden_property(MODEL_TRANSFORM_PROPERTY, &model_3d.transform);
+                summary.step_model_found = true;
+            } else {
+                match try_fetch_step(&model_3d.uri, &model_path) {
+                    Ok(Some(shape)) => {
+                        kicad_footprint.model = Some(place_model(&model_path, &shape, &model_3d.transform, &project_root_dir, options)?);
+                        kicad_footprint.add_hidden_property(MODEL_TRANSFORM_PROPERTY, &model_3d.transform);
+                        summary.step_model_found = true;
+                        summary.files_written.push(model_path);
                     }
+                    Ok(None) => summary.warn("No STEP model was found for this component".into()),
+                    Err(error) => summary.warn(format!("The STEP model could not be downloaded, importing without it: {error:#}")),
                 }
-            };
-            let existing_component = symbol_lib.symbols.iter_mut().find(|s| s.symbol_id == kicad_symbol.symbol_id);
-            if !update && existing_component.is_some() {
-                return Err(anyhow::anyhow!("This component has already been imported into the project, aborting. Use the --update flag to overwrite an existing component."));
-            }
-            if existing_component.is_none() {
-                println!("Adding device '{}'...", device_name);
-                symbol_lib.symbols.push(kicad_symbol);
-            } else if let Some(existing_symbol) = existing_component {
-                *existing_symbol = kicad_symbol;
             }
+        }
 
-            // Download STEP model data
-            if let Some(model_3d) = &component_result.device_info.footprint_info.model_3d {
-                let model_id = &model_3d.uri;
-
-                let response = ureq::get(format!("https://pro.easyeda.com/api/v2/components/{model_id}")).call();
-                if let Ok(model_response) = response {
-                    let body_string = model_response.into_body().read_to_string()?;
-                    let component_data = serde_json::from_str::<ComponentDataResponse>(&body_string)?;
-                    if let Some(product_result) = component_data.result {
-                        let model_id = product_result.n3d_model_uuid;
-                        let response = ureq::get(format!("https://modules.easyeda.com/qAxj6KHrDKw4blvCG8QJPs7Y/{model_id}")).call();
-                        if let Ok(model_response) = response {
-                            let body_string = model_response.into_body().read_to_string()?;
-                            println!("Found STEP model, downloading...");
-                            let model_directory = library_root_dir
-                                .join(format!("{library_name}.pretty").as_str())
-                                .join("models");
-                            if !model_directory.exists() {
-                                fs::create_dir_all(&model_directory)?;
-                            }
-                            let model_path = model_directory.join(format!("{safe_part_name}.step"));
-                            fs::write(&model_path, body_string)?;
-
-                            let shape = Shape::read_step(&model_path)?;
-                            let bounding_box = shape.bounding_box();
-
-                            let center_x = (bounding_box.max_x + bounding_box.min_x) / 2.0;
-                            let center_y = (bounding_box.max_y + bounding_box.min_y) / 2.0;
-                            let min_z = bounding_box.min_z;
-
-                            let model_transform = model_3d.transform
-                                .split(',')
-                                .map(|f| f.parse::<f32>().unwrap())
-                                .collect_vec();
-                            let transform_offset = &model_transform[6..9].iter().map(|v| v * 0.0254).collect_vec();
-                            let rotation = &model_transform[3..6].iter().rev().collect_vec();
-
-                            //println!("origin: [{}, {}, {}]", center_x, center_y, min_z);
-                            //println!("rotation: {:?}", &rotation);
-                            //println!("offset: {:?}", &transform_offset);
-
-                            let rotation_z = (*rotation[2]).to_radians();
-                            let mul_y = rotation_z.cos();
-
-                            let offset_x = -mul_y * center_x * 0.0393701 + transform_offset[0] * 0.0393701;
-                            let offset_y = -mul_y * center_y * 0.0393701 + transform_offset[1] * 0.0393701;
-                            let offset_z = -min_z * 0.0393701 + transform_offset[2] * 0.0393701;
-
-                            kicad_footprint.model = Some(FootprintModel {
-                                model_file: model_path.to_str().unwrap().replace(project_root_dir.to_str().unwrap(), "${KIPRJMOD}"),
-                                opacity: None,
-                                at: Some(Scalar3D::new("xyz", offset_x, offset_y, offset_z)),
-                                rotate: Some(Scalar3D::new("xyz", -*rotation[0], -*rotation[1], -*rotation[2])),
-                                scale: None,
-                                offset: None,
-                            });
-                        }
-                    } else {
-                        println!("No STEP model was found for this component");
-                    }
-                } else {
-                    println!("No STEP model was found for this component");
-                }
+        kicad_footprint.recenter(options.anchor);
+        if options.deterministic_uuids {
+            match lcsc_code.as_deref() {
+                Some(lcsc_code) => kicad_footprint.assign_deterministic_uuids(lcsc_code),
+                None => summary.warn(format!("'{device_name}' has no LCSC code, its footprint UUIDs are left to KiCad")),
             }
+        }
 
-            let item_ser = symbol_lib.serialize();
-            let tokens = KiCadParser::generate_tokens(&item_ser);
-            let symbol_lib_data = KiCadParser::stringify_tokens::<SymbolLib>(&tokens);
-            fs::write(symbol_lib_path, symbol_lib_data)?;
+        // The placement in the existing footprint was saved after recentering it
+        if let Some(existing_model) = existing_model {
+            kicad_footprint.model = Some(existing_model);
+        }
 
-            // Save footprint to .pretty directory
-            let footprint_lib_root = library_root_dir.join(format!("{library_name}.pretty").as_str());
-            if !fs::exists(&footprint_lib_root)? {
-                fs::create_dir(&footprint_lib_root)?;
-            }
-            let footprint_path = footprint_lib_root.join(format!("{safe_part_name}.kicad_mod").as_str());
-            let item = kicad_footprint.serialize();
-            let tokens = KiCadParser::generate_tokens(&item);
-            let footprint_data = KiCadParser::stringify_tokens::<FootprintLibrary>(&tokens);
-            fs::write(footprint_path, footprint_data)?;
-
-            // Check if the sym-lib-table/fp-lib-table files exist, create them if they don't
-            let sym_lib_table_path = project_root_dir.join("sym-lib-table");
-            let mut sym_lib_table = match fs::exists(&sym_lib_table_path)? {
-                true => {
-                    let sym_lib_table_data = fs::read_to_string(&sym_lib_table_path.to_str().unwrap())?;
-                    let tokens = KiCadParser::tokenize(&sym_lib_table_data);
-                    let item = KiCadParser::parse_syntax_item(&tokens);
-                    let model: SymbolLibTable = SyntaxItemSerializable::deserialize(&item);
-                    model
-                }
-                false => {
-                    SymbolLibTable {
-                        version: 7,
-                        libraries: vec![],
-                    }
-                }
-            };
-            if !sym_lib_table.libraries.iter().any(|e| e.name == library_name) {
-                sym_lib_table.libraries.push(SymbolLibTableItem {
-                    name: library_name.into(),
-                    description,
-                    hidden: false,
-                    disabled: false,
-                    lib_type: "KiCad".into(),
-                    options: String::new(),
-                    uri: format!("{library_path_relative}/{library_name}.kicad_sym").into(),
-                });
-                let items_ser = sym_lib_table.serialize();
-                let tokens = KiCadParser::generate_tokens(&items_ser);
-                let sym_lib_table_data = KiCadParser::stringify_tokens::<SymbolLibTable>(&tokens);
-                fs::write(sym_lib_table_path, sym_lib_table_data)?;
-            }
+        // Save footprint to .pretty directory
+        if !fs::exists(&footprint_lib_root)? {
+            fs::create_dir(&footprint_lib_root)?;
+        }
+        debug!("Writing footprint '{}'...", footprint_path.display());
+        KiCadParser::write_file_with_options(&footprint_path, &kicad_footprint, &format_options)?;
+        summary.files_written.push(footprint_path);
+
+        // Rules without a KiCad equivalent are kept next to the footprint for manual review
+        if let Some(unmapped_rules) = unmapped_rules {
+            let rules_path = footprint_lib_root.join(format!("{safe_part_name}.rules.txt"));
+            summary.warn(format!("Some design rules of the footprint could not be converted, see '{}'", rules_path.display()));
+            fs::write(&rules_path, unmapped_rules)?;
+            summary.files_written.push(rules_path);
+        }
 
-            let fp_lib_table_path = project_root_dir.join("fp-lib-table");
-            let mut fp_lib_table = match fs::exists(&fp_lib_table_path)? {
-                true => {
-                    let fp_lib_table_data = fs::read_to_string(&fp_lib_table_path.to_str().unwrap())?;
-                    let tokens = KiCadParser::tokenize(&fp_lib_table_data);
-                    let item = KiCadParser::parse_syntax_item(&tokens);
-                    let model: FootprintLibTable = SyntaxItemSerializable::deserialize(&item);
-                    model
-                }
-                false => {
-                    FootprintLibTable {
-                        version: 7,
-                        libraries: vec![],
-                    }
+        // Check if the fp-lib-table file exists, create it if it doesn't
+        let fp_lib_table_path = project_root_dir.join("fp-lib-table");
+        let mut fp_lib_table = match fs::exists(&fp_lib_table_path)? {
+            true => KiCadParser::parse_file(&fp_lib_table_path)?,
+            false => {
+                FootprintLibTable {
+                    version: 7,
+                    libraries: vec![],
                 }
-            };
-            if !fp_lib_table.libraries.iter().any(|e| e.name == library_name) {
-                fp_lib_table.libraries.push(FootprintLibTableItem {
-                    name: library_name.into(),
-                    description: "Components downloaded and converted directly from JLCPCB".into(),
-                    disabled: false,
-                    lib_type: "KiCad".into(),
-                    options: String::new(),
-                    uri: format!("{library_path_relative}/{library_name}.pretty").into(),
-                });
-                let items_ser = fp_lib_table.serialize();
-                let tokens = KiCadParser::generate_tokens(&items_ser);
-                let fp_lib_table_data = KiCadParser::stringify_tokens::<FootprintLibTable>(&tokens);
-                fs::write(fp_lib_table_path, fp_lib_table_data)?;
             }
-
-            println!("The component has been imported.");
+        };
+        let fp_lib_table_changed = fp_lib_table.upsert_library(FootprintLibTableItem {
+            name: library_name.into(),
+            description: options.description.clone(),
+            disabled: false,
+            lib_type: "KiCad".into(),
+            options: String::new(),
+            uri: format!("{library_path_relative}/{library_name}.pretty").into(),
+        });
+        if fp_lib_table_changed {
+            debug!("Updating '{}' in the footprint library table...", library_name);
+            KiCadParser::write_file(&fp_lib_table_path, &fp_lib_table)?;
+            summary.files_written.push(fp_lib_table_path);
         }
     }
-    Ok(())
+
+    info!("The component has been imported.");
+
+    summary.renamed_part = Some(RenamedPart { original: part_name.clone(), file_name: safe_part_name.clone() })
+        .filter(|p| p.original != p.file_name);
+    Ok(summary)
+}
+
+/// Downloads the STEP model of a component to the given path and loads it.
+/// Returns `None` if EasyEDA has no STEP model for the component.
+fn try_fetch_step(model_uuid: &str, model_path: &Path) -> anyhow::Result<Option<Shape>> {
+    let Some(body_string) = http_get_optional(&format!("https://pro.easyeda.com/api/v2/components/{model_uuid}"))? else {
+        return Ok(None);
+    };
+    let component_data = serde_json::from_str::<ComponentDataResponse>(&body_string)
+        .context("Failed to parse the 3D model information")?;
+    let Some(step_uuid) = component_data.result.map(|r| r.n3d_model_uuid).filter(|u| !u.is_empty()) else {
+        return Ok(None);
+    };
+
+    debug!("Found STEP model, downloading...");
+    let Some(step_data) = http_get_optional(&format!("https://modules.easyeda.com/qAxj6KHrDKw4blvCG8QJPs7Y/{step_uuid}"))? else {
+        return Ok(None);
+    };
+
+    if let Some(model_directory) = model_path.parent() {
+        fs::create_dir_all(model_directory)?;
+    }
+    fs::write(model_path, step_data)?;
+
+    let shape = Shape::read_step(model_path)
+        .with_context(|| format!("Failed to read the STEP model '{}'", model_path.display()))?;
+    Ok(Some(shape))
 }
 
-#[allow(unused)]
-struct BoundingBox {
-    min_x: f32,
-    min_y: f32,
-    min_z: f32,
+/// Places the STEP model on the footprint based on its bounding box and the EasyEDA transform
+fn place_model(model_path: &Path, shape: &Shape, transform: &str, project_root_dir: &Path, options: &LibraryOptions) -> anyhow::Result<FootprintModel> {
+    let model_dir_variable = options.kicad_version.model_dir_variable();
+    let global_model_dir = std::env::var_os(model_dir_variable).map(PathBuf::from);
+    let model_file = model_file_reference(model_path, project_root_dir, global_model_dir.as_deref().map(|d| (model_dir_variable, d)));
 
-    max_x: f32,
-    max_y: f32,
-    max_z: f32,
+    compute_model_placement(&shape.bounding_box(), transform, model_file)
 }
 
-trait HasBoundingBox {
-    fn bounding_box(&self) -> BoundingBox;
+/// Downloads the given URL, returning `None` if the server responds with 404 Not Found
+fn http_get_optional(url: &str) -> anyhow::Result<Option<String>> {
+    match ureq::get(url).call() {
+        Ok(response) => Ok(Some(read_response_body(response)?)),
+        Err(ureq::Error::StatusCode(404)) => Ok(None),
+        Err(error) => Err(anyhow::Error::new(error).context(format!("Failed to download '{url}'"))),
+    }
 }
 
-impl HasBoundingBox for Shape {
-    fn bounding_box(&self) -> BoundingBox {
-        let mut min_x = f32::INFINITY;
-        let mut min_y = f32::INFINITY;
-        let mut min_z = f32::INFINITY;
-        let mut max_x = f32::NEG_INFINITY;
-        let mut max_y = f32::NEG_INFINITY;
-        let mut max_z = f32::NEG_INFINITY;
+/// Upper limit for downloaded response bodies, large footprints easily exceed ureq's default of 10 MB
+const MAX_RESPONSE_SIZE: u64 = 64 * 1024 * 1024;
 
-        for vert in self.mesh().vertices {
-            min_x = min_x.min(vert.x as f32);
-            min_y = min_y.min(vert.y as f32);
-            min_z = min_z.min(vert.z as f32);
-            max_x = max_x.max(vert.x as f32);
-            max_y = max_y.max(vert.y as f32);
-            max_z = max_z.max(vert.z as f32);
-        }
+/// Reads the response body as text. ureq only decompresses bodies sent with exactly `Content-Encoding: gzip`,
+/// so responses that are still gzip compressed (e.g. `x-gzip` or a missing header) are decompressed here.
+fn read_response_body(response: ureq::http::Response<ureq::Body>) -> anyhow::Result<String> {
+    let bytes = response.into_body().into_with_config().limit(MAX_RESPONSE_SIZE).read_to_vec()?;
+    Ok(decode_response_body(bytes)?)
+}
 
-        BoundingBox {
-            min_x,
-            min_y,
-            min_z,
-            max_x,
-            max_y,
-            max_z,
-        }
+fn init_logger(verbose: bool, quiet: bool) {
+    let level = match (verbose, quiet) {
+        (true, _) => LevelFilter::Debug,
+        (_, true) => LevelFilter::Warn,
+        _ => LevelFilter::Info,
+    };
+
+    env_logger::Builder::new()
+        .filter_level(LevelFilter::Warn)
+        .filter_module("jlcrs", level)
+        .format(|buf, record| match record.level() {
+            log::Level::Error | log::Level::Warn => writeln!(buf, "{}: {}", record.level().as_str().to_lowercase(), record.args()),
+            _ => writeln!(buf, "{}", record.args()),
+        })
+        .init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kicad::syntax::SyntaxItemSerializable;
+
+    #[test]
+    fn only_missing_skips_imported_codes() {
+        let mut symbol = Symbol::default();
+        symbol.add_hidden_property("LCSC", "C25804");
+        let symbol_lib = SymbolLib {
+            version: 20211014,
+            generator: "jlcrs".into(),
+            generator_version: None,
+            symbols: vec![symbol],
+            embedded_files: None,
+            unknown_items: vec![],
+        };
+
+        let (missing, skipped) = split_missing_codes(vec!["C25804".into(), "C35879".into()], &symbol_lib);
+        assert_eq!(missing, vec!["C35879".to_string()]);
+        assert_eq!(skipped, vec!["C25804".to_string()]);
+    }
+
+    #[test]
+    fn footprint_of_other_part_is_not_overwritten() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data").join("kicad").join("R_0603_1608Metric.kicad_mod");
+        let mut footprint = load_footprint(&path).unwrap();
+        footprint.add_hidden_property("LCSC", "C25804");
+
+        assert!(!footprint_belongs_to_other_part(&footprint, Some("C25804")));
+        assert!(footprint_belongs_to_other_part(&footprint, Some("C35879")));
+        assert!(footprint_belongs_to_other_part(&footprint, None));
+    }
+
+    #[test]
+    fn part_name_with_slash_is_sanitized() {
+        let (file_name, warning) = part_file_name("LM358/TR", false);
+        assert_eq!(file_name, "LM358TR");
+        assert!(warning.is_some_and(|w| w.contains("'LM358/TR'") && w.contains("'LM358TR'")));
+
+        // A path separator can never be part of a file name
+        let (file_name, warning) = part_file_name("LM358/TR", true);
+        assert_eq!(file_name, "LM358TR");
+        assert!(warning.is_some());
+    }
+
+    #[test]
+    fn part_can_be_named_after_lcsc_code() {
+        assert_eq!(part_name("LM358 DR/TR", Some("C7950"), PartNameSource::Mpn), ("LM358 DR/TR".to_string(), None));
+        assert_eq!(part_name("LM358 DR/TR", Some("C7950"), PartNameSource::Lcsc), ("C7950".to_string(), None));
+        let (name, warning) = part_name("LM358 DR/TR", None, PartNameSource::Lcsc);
+        assert_eq!(name, "LM358 DR/TR");
+        assert!(warning.is_some());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn original_part_names_are_kept_on_request() {
+        assert_eq!(part_file_name("NE555P", false), ("NE555P".to_string(), None));
+
+        let (file_name, warning) = part_file_name("AB:CD", true);
+        assert_eq!(file_name, "AB:CD");
+        assert!(warning.is_some());
+    }
+
+    #[test]
+    fn resume_skips_completed_codes() {
+        let progress_path = std::env::temp_dir().join(format!("jlcrs-progress-{}", std::process::id()));
+        save_progress(&progress_path, &["C25804".into(), "C35879".into()]).unwrap();
+        let completed_codes = load_progress(&progress_path).unwrap();
+        fs::remove_file(&progress_path).unwrap();
+
+        let codes = vec!["C25804".into(), "C1525".into(), "C35879".into(), "C2040".into()];
+        let (remaining, skipped) = split_completed_codes(codes, &completed_codes);
+        assert_eq!(remaining, vec!["C1525".to_string(), "C2040".to_string()]);
+        assert_eq!(skipped, vec!["C25804".to_string(), "C35879".to_string()]);
+    }
+
+    #[test]
+    fn import_summary_is_serialized_as_json() {
+        let summary = ImportSummary {
+            lcsc_code: Some("C25804".into()),
+            mpn: "0603WAF1002T5E".into(),
+            files_written: vec![PathBuf::from("lib/JLCPCB_Components.kicad_sym")],
+            step_model_found: false,
+            warnings: vec!["No STEP model was found for this component".into()],
+            renamed_part: None,
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&serde_json::to_string(&summary).unwrap()).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "lcsc_code": "C25804",
+            "mpn": "0603WAF1002T5E",
+            "files_written": ["lib/JLCPCB_Components.kicad_sym"],
+            "step_model_found": false,
+            "warnings": ["No STEP model was found for this component"],
+        }));
+    }
+
+    #[test]
+    fn unsupported_parts_are_told_apart_from_other_errors() {
+        let error = anyhow::Error::new(FootprintConverterError::UnsupportedPadShape("POLYGON".into())).context("Failed to convert 'C25804'");
+        assert!(is_unsupported_part(&error));
+        assert!(is_unsupported_part(&SymbolConverterError::UnsupportedElement("Bezier".into()).into()));
+        assert!(!is_unsupported_part(&anyhow::anyhow!("Failed to download 'C25804'")));
+    }
+
+    #[test]
+    fn unmatched_pin_and_pad_numbers_are_reported() {
+        let pins = ["1", "2", "3", "10"].map(|n| format!(r#"(pin passive line (at 0 0 0) (length 2.54) (name "~" (effects (font (size 1.27 1.27)))) (number "{n}" (effects (font (size 1.27 1.27)))))"#));
+        let symbol = Symbol::deserialize(&KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&format!(r#"(symbol "TEST" {})"#, pins.join(" ")))));
+
+        let data = r##"["CANVAS",0,0,"mil",5,5,5,5,0.5,0.5]
+["LAYER",1,"TOP","Top Layer",3,"#ff0000",1,"#7f0000",1]
+["PAD","e1",0,"",1,"1",-100,0,0,null,["RECT",40,36,0],[],0,0,0,1,1,null,null,null,null,0]
+["PAD","e2",0,"",1,"2",0,0,0,null,["RECT",40,36,0],[],0,0,0,1,1,null,null,null,null,0]
+["PAD","e3",0,"",1,"EP",100,0,0,null,["RECT",40,36,0],[],0,0,0,1,1,null,null,null,null,0]"##;
+        let footprint: FootprintLibrary = EasyEDAFootprint::parse(data).unwrap().try_into().unwrap();
+
+        let warning = check_pin_pad_numbers(&symbol, &footprint).unwrap();
+        assert!(warning.contains("pins 3, 10 have"), "{warning}");
+        assert!(warning.contains("pads EP have"), "{warning}");
+    }
+
+    #[test]
+    fn footprint_without_pads_is_rejected() {
+        let footprint = EasyEDAFootprint::parse(r#"["CANVAS",0,0,"mil",5,5,5,5,0.5,0.5]"#).unwrap();
+        let footprint: FootprintLibrary = footprint.try_into().unwrap();
+
+        assert!(check_footprint_pads(&footprint, "EMPTY", false).is_err());
+        assert!(check_footprint_pads(&footprint, "EMPTY", true).is_ok());
+    }
+
+    #[test]
+    fn library_uri_is_relative_to_project() {
+        let project_root_dir = Path::new("/home/user/project");
+        assert_eq!(project_relative_uri(Path::new("/home/user/project"), project_root_dir), "${KIPRJMOD}");
+        assert_eq!(project_relative_uri(Path::new("/home/user/project/libs"), project_root_dir), "${KIPRJMOD}/libs");
+        assert_eq!(project_relative_uri(Path::new("/home/user/project/libs/../shared"), project_root_dir), "${KIPRJMOD}/shared");
+
+        // Directories outside the project are referenced by their absolute path
+        assert_eq!(project_relative_uri(Path::new("/home/user/project/../shared"), project_root_dir), "/home/user/shared");
+        assert_eq!(project_relative_uri(Path::new("/data/libs"), project_root_dir), "/data/libs");
+        // A sibling directory sharing the project name as a prefix
+        assert_eq!(project_relative_uri(Path::new("/home/user/project2"), project_root_dir), "/home/user/project2");
+    }
+}
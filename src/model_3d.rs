@@ -0,0 +1,261 @@
+use crate::kicad::model::common::KiCadVersion;
+use crate::kicad::model::footprint_library::{FootprintLibrary, FootprintModel, Scalar3D};
+use crate::units::{MIL_TO_MM, MM_TO_INCH};
+use clap::ValueEnum;
+use itertools::Itertools;
+use opencascade::primitives::Shape;
+use std::path::Path;
+
+pub const MODEL_TRANSFORM_PROPERTY: &str = "EasyEDA Model Transform";
+pub const ANCHOR_OFFSET_PROPERTY: &str = "Anchor Offset";
+
+#[allow(unused)]
+pub struct BoundingBox {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub min_z: f32,
+
+    pub max_x: f32,
+    pub max_y: f32,
+    pub max_z: f32,
+}
+
+pub trait HasBoundingBox {
+    fn bounding_box(&self) -> BoundingBox;
+}
+
+impl HasBoundingBox for Shape {
+    fn bounding_box(&self) -> BoundingBox {
+        let mut min_x = f32::INFINITY;
+        let mut min_y = f32::INFINITY;
+        let mut min_z = f32::INFINITY;
+        let mut max_x = f32::NEG_INFINITY;
+        let mut max_y = f32::NEG_INFINITY;
+        let mut max_z = f32::NEG_INFINITY;
+
+        for vert in self.mesh().vertices {
+            min_x = min_x.min(vert.x as f32);
+            min_y = min_y.min(vert.y as f32);
+            min_z = min_z.min(vert.z as f32);
+            max_x = max_x.max(vert.x as f32);
+            max_y = max_y.max(vert.y as f32);
+            max_z = max_z.max(vert.z as f32);
+        }
+
+        BoundingBox {
+            min_x,
+            min_y,
+            min_z,
+            max_x,
+            max_y,
+            max_z,
+        }
+    }
+}
+
+/// Computes the KiCad model placement from the STEP bounding box and the EasyEDA model transform string
+pub fn compute_model_placement(bounding_box: &BoundingBox, transform: &str, model_file: String) -> anyhow::Result<FootprintModel> {
+    let center_x = (bounding_box.max_x + bounding_box.min_x) / 2.0;
+    let center_y = (bounding_box.max_y + bounding_box.min_y) / 2.0;
+    let min_z = bounding_box.min_z;
+
+    let model_transform = transform
+        .split(',')
+        .map(|f| f.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()?;
+    if model_transform.len() < 9 {
+        return Err(anyhow::anyhow!("Invalid 3D model transform: '{}'", transform));
+    }
+    let transform_offset = &model_transform[6..9].iter().map(|v| v * MIL_TO_MM).collect_vec();
+    let rotation = &model_transform[3..6].iter().rev().collect_vec();
+
+    let rotation_z = (*rotation[2]).to_radians();
+    let mul_y = rotation_z.cos();
+
+    let offset_x = -mul_y * center_x * MM_TO_INCH + transform_offset[0] * MM_TO_INCH;
+    let offset_y = -mul_y * center_y * MM_TO_INCH + transform_offset[1] * MM_TO_INCH;
+    let offset_z = -min_z * MM_TO_INCH + transform_offset[2] * MM_TO_INCH;
+
+    Ok(FootprintModel {
+        model_file,
+        opacity: None,
+        at: Some(Scalar3D::xyz(offset_x, offset_y, offset_z)),
+        rotate: Some(Scalar3D::xyz(-*rotation[0], -*rotation[1], -*rotation[2])),
+        scale: None,
+        offset: None,
+    })
+}
+
+/// Recomputes the placement of the footprint's existing 3D model, keeping the model file, scale and opacity intact
+pub fn realign_footprint_model(footprint: &mut FootprintLibrary, bounding_box: &BoundingBox) -> anyhow::Result<()> {
+    let Some(model) = &footprint.model else {
+        return Err(anyhow::anyhow!("The footprint '{}' has no 3D model", footprint.footprint_id));
+    };
+
+    // Fall back to the current rotation if the original transform wasn't recorded during import
+    let transform = match footprint.properties.iter().find(|p| p.key == MODEL_TRANSFORM_PROPERTY).and_then(|p| p.value.clone()) {
+        Some(transform) => transform,
+        None => {
+            let rotate = model.rotate.clone().unwrap_or_default();
+            format!("0,0,0,{},{},{},0,0,0", -rotate.z, -rotate.y, -rotate.x)
+        }
+    };
+
+    let mut realigned_model = compute_model_placement(bounding_box, &transform, model.model_file.clone())?;
+
+    // Keep the shift of a footprint that was recentered on import, the 3D model placement is in inches with a Y axis that points up
+    let (dx, dy) = footprint.anchor_offset();
+    if let Some(at) = realigned_model.at.as_mut() {
+        at.x += dx * MM_TO_INCH;
+        at.y -= dy * MM_TO_INCH;
+    }
+    realigned_model.scale = model.scale.clone();
+    realigned_model.opacity = model.opacity;
+    footprint.model = Some(realigned_model);
+
+    Ok(())
+}
+
+/// Returns the path of the model file as written to the footprint, relative to the model
+/// directory variable if the file is inside it, otherwise relative to the project
+pub fn model_file_reference(model_path: &Path, project_root_dir: &Path, model_dir: Option<(&str, &Path)>) -> String {
+    if let Some((variable, model_dir)) = model_dir && let Ok(relative_path) = model_path.strip_prefix(model_dir) {
+        return format!("${{{variable}}}/{}", relative_path.to_str().unwrap());
+    }
+
+    model_path.to_str().unwrap().replace(project_root_dir.to_str().unwrap(), "${KIPRJMOD}")
+}
+
+/// Expands `${KIPRJMOD}` and the 3D model directory variables of all supported KiCad versions
+pub fn resolve_model_file(model_file: &str, project_root_dir: &Path) -> String {
+    let mut model_path = model_file.replace("${KIPRJMOD}", project_root_dir.to_str().unwrap());
+    for version in KiCadVersion::value_variants() {
+        let variable = version.model_dir_variable();
+        if let Some(model_dir) = std::env::var_os(variable) {
+            model_path = model_path.replace(&format!("${{{variable}}}"), model_dir.to_str().unwrap());
+        }
+    }
+    model_path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kicad::model::footprint_library::FootprintAnchor;
+    use crate::kicad::syntax::{KiCadParser, SyntaxItemSerializable};
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn realign_updates_model_in_place() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data").join("realign").join("SOT-23.kicad_mod");
+        let data = fs::read_to_string(path).unwrap();
+        let item = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&data));
+        let mut footprint: FootprintLibrary = SyntaxItemSerializable::deserialize(&item);
+
+        // STEP model bounding box (in mils) centered at (10, -20)
+        let bounding_box = BoundingBox { min_x: -40.0, min_y: -70.0, min_z: 5.0, max_x: 60.0, max_y: 30.0, max_z: 45.0 };
+        realign_footprint_model(&mut footprint, &bounding_box).unwrap();
+
+        let model = footprint.model.as_ref().unwrap();
+        assert_eq!(model.model_file, "${KIPRJMOD}/JLCPCB.pretty/models/SOT-23.step");
+        let at = model.at.as_ref().unwrap();
+        assert!((at.x - (-10.0 * 0.0393701)).abs() < 1e-4);
+        assert!((at.y - (20.0 * 0.0393701)).abs() < 1e-4);
+        assert!((at.z - (-5.0 * 0.0393701)).abs() < 1e-4);
+        let rotate = model.rotate.as_ref().unwrap();
+        assert_eq!((rotate.x, rotate.y, rotate.z), (0.0, 0.0, 0.0));
+        assert_eq!(model.scale.as_ref().map(|s| s.x), Some(1.0));
+    }
+
+    #[test]
+    fn realign_keeps_the_anchor_offset() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data").join("realign").join("SOT-23.kicad_mod");
+        let data = fs::read_to_string(path).unwrap();
+        let item = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&data));
+        let bounding_box = BoundingBox { min_x: -40.0, min_y: -70.0, min_z: 5.0, max_x: 60.0, max_y: 30.0, max_z: 45.0 };
+
+        let mut footprint: FootprintLibrary = SyntaxItemSerializable::deserialize(&item);
+        realign_footprint_model(&mut footprint, &bounding_box).unwrap();
+        let original_at = footprint.model.as_ref().unwrap().at.clone().unwrap();
+
+        let mut footprint: FootprintLibrary = SyntaxItemSerializable::deserialize(&item);
+        let pad_1 = footprint.pads.iter().find(|p| p.number == "1").unwrap().position.clone();
+        assert!(pad_1.x != 0.0 || pad_1.y != 0.0);
+        footprint.recenter(FootprintAnchor::Pad1);
+        realign_footprint_model(&mut footprint, &bounding_box).unwrap();
+
+        // The model moves along with the pads
+        let at = footprint.model.as_ref().unwrap().at.clone().unwrap();
+        assert!((at.x - (original_at.x - pad_1.x * MM_TO_INCH)).abs() < 1e-5);
+        assert!((at.y - (original_at.y + pad_1.y * MM_TO_INCH)).abs() < 1e-5);
+        assert_eq!(footprint.anchor_offset(), (-pad_1.x, -pad_1.y));
+    }
+
+    fn assert_placement(model: &FootprintModel, at: (f32, f32, f32), rotate: (f32, f32, f32)) {
+        let actual_at = model.at.as_ref().unwrap();
+        let actual_rotate = model.rotate.as_ref().unwrap();
+        for (actual, expected) in [(actual_at.x, at.0), (actual_at.y, at.1), (actual_at.z, at.2)] {
+            assert!((actual - expected).abs() < 1e-4, "at: expected {:?}, got {:?}", at, actual_at);
+        }
+        for (actual, expected) in [(actual_rotate.x, rotate.0), (actual_rotate.y, rotate.1), (actual_rotate.z, rotate.2)] {
+            assert!((actual - expected).abs() < 1e-4, "rotate: expected {:?}, got {:?}", rotate, actual_rotate);
+        }
+    }
+
+    #[test]
+    fn model_is_centered_on_its_bounding_box() {
+        // STEP body spanning 2.9 x 2.4 mm, offset from its own origin
+        let bounding_box = BoundingBox { min_x: -0.95, min_y: -1.45, min_z: 0.0, max_x: 1.95, max_y: 0.95, max_z: 1.1 };
+        let model = compute_model_placement(&bounding_box, "114.1732,94.4882,43.3071,0,0,0,0,0,0", "model.step".into()).unwrap();
+
+        assert_eq!(model.model_file, "model.step");
+        assert_placement(&model, (-0.5 * 0.0393701, 0.25 * 0.0393701, 0.0), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rotated_model_flips_the_center_offset() {
+        let bounding_box = BoundingBox { min_x: 0.0, min_y: 1.0, min_z: -0.5, max_x: 2.0, max_y: 3.0, max_z: 1.5 };
+        let model = compute_model_placement(&bounding_box, "78.7402,78.7402,78.7402,180,0,0,0,0,0", "model.step".into()).unwrap();
+
+        assert_placement(&model, (1.0 * 0.0393701, 2.0 * 0.0393701, 0.5 * 0.0393701), (0.0, 0.0, -180.0));
+    }
+
+    #[test]
+    fn quarter_turn_ignores_the_center_offset() {
+        let bounding_box = BoundingBox { min_x: 0.0, min_y: 1.0, min_z: 0.0, max_x: 2.0, max_y: 3.0, max_z: 1.0 };
+        let model = compute_model_placement(&bounding_box, "39.3701,39.3701,39.3701,90,0,0,0,0,0", "model.step".into()).unwrap();
+
+        assert_placement(&model, (0.0, 0.0, 0.0), (0.0, 0.0, -90.0));
+    }
+
+    #[test]
+    fn transform_offset_is_converted_from_mils() {
+        let bounding_box = BoundingBox { min_x: -1.0, min_y: -1.0, min_z: 0.0, max_x: 1.0, max_y: 1.0, max_z: 1.0 };
+        let model = compute_model_placement(&bounding_box, "78.7402,78.7402,39.3701,0,0,0,10,-20,5", "model.step".into()).unwrap();
+
+        assert_placement(&model, (0.01, -0.02, 0.005), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn invalid_transform_is_rejected() {
+        let bounding_box = BoundingBox { min_x: 0.0, min_y: 0.0, min_z: 0.0, max_x: 1.0, max_y: 1.0, max_z: 1.0 };
+        assert!(compute_model_placement(&bounding_box, "1,2,3", "model.step".into()).is_err());
+        assert!(compute_model_placement(&bounding_box, "0,0,0,0,0,0,0,0,x", "model.step".into()).is_err());
+    }
+
+    #[test]
+    fn model_file_uses_model_dir_variable() {
+        let project_root_dir = Path::new("/home/user/project");
+        let model_dir = Path::new("/usr/share/kicad/3dmodels");
+
+        let model_path = Path::new("/usr/share/kicad/3dmodels/JLCPCB/SOT-23.step");
+        let model_file = model_file_reference(model_path, project_root_dir, Some(("KICAD8_3DMODEL_DIR", model_dir)));
+        assert_eq!(model_file, "${KICAD8_3DMODEL_DIR}/JLCPCB/SOT-23.step");
+
+        // Models inside the project stay relative to it
+        let model_path = Path::new("/home/user/project/JLCPCB.pretty/models/SOT-23.step");
+        let model_file = model_file_reference(model_path, project_root_dir, Some(("KICAD8_3DMODEL_DIR", model_dir)));
+        assert_eq!(model_file, "${KIPRJMOD}/JLCPCB.pretty/models/SOT-23.step");
+    }
+}
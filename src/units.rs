@@ -0,0 +1,27 @@
+//! Unit conversions between EasyEDA and KiCad.
+//!
+//! EasyEDA footprints are drawn in mils and symbols in units of 10 mils, while KiCad
+//! uses millimeters everywhere except for the 3D model offset, which is in inches.
+
+/// EasyEDA footprint units (mil) to KiCad millimeters
+pub const MIL_TO_MM: f32 = 0.0254;
+
+/// EasyEDA symbol units (10 mil) to KiCad millimeters
+pub const SYMBOL_UNIT_TO_MM: f32 = 10.0 * MIL_TO_MM;
+
+pub const INCH_TO_MM: f32 = 25.4;
+
+pub const MM_TO_INCH: f32 = 1.0 / INCH_TO_MM;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mil_pitch_converts_to_mm() {
+        assert!((100.0 * MIL_TO_MM - 2.54).abs() < 1e-6);
+        assert!((10.0 * SYMBOL_UNIT_TO_MM - 2.54).abs() < 1e-6);
+        assert!((2.54 * MM_TO_INCH - 0.1).abs() < 1e-6);
+        assert!((1000.0 * MIL_TO_MM * MM_TO_INCH - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::kicad::model::footprint_library::FootprintLibrary;
+use crate::kicad::model::symbol_library::SymbolLib;
+use crate::kicad::syntax::{KiCadParser, SyntaxItemSerializable, TopLevelSerializable};
+use std::any::Any;
+use std::fs;
+use std::panic;
+use std::path::{Path, PathBuf};
+
+pub struct ValidationFailure {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Loads and re-serializes the symbol library and every footprint of a generated library,
+/// returning the files that fail to parse or don't survive the round-trip
+pub fn validate_library(symbol_lib_path: &Path, footprint_lib_root: &Path) -> anyhow::Result<Vec<ValidationFailure>> {
+    let mut failures = Vec::new();
+    if fs::exists(symbol_lib_path)? {
+        failures.extend(check_round_trip::<SymbolLib>(symbol_lib_path)?);
+    }
+
+    if fs::exists(footprint_lib_root)? {
+        let mut footprint_paths = fs::read_dir(footprint_lib_root)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        footprint_paths.retain(|path| path.extension().is_some_and(|ext| ext == "kicad_mod"));
+        footprint_paths.sort();
+
+        for path in footprint_paths {
+            failures.extend(check_round_trip::<FootprintLibrary>(&path)?);
+        }
+    }
+
+    Ok(failures)
+}
+
+fn check_round_trip<T: TopLevelSerializable>(path: &Path) -> anyhow::Result<Option<ValidationFailure>> {
+    let input = fs::read_to_string(path)?;
+
+    // The deserializers panic on anything they don't understand
+    let result = panic::catch_unwind(|| {
+        let original = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&input));
+        let model: T = SyntaxItemSerializable::deserialize(&original);
+
+        let tokens = KiCadParser::generate_tokens(&model.serialize());
+        let output = KiCadParser::stringify_tokens::<T>(&tokens);
+        let reparsed = KiCadParser::parse_syntax_item(&KiCadParser::tokenize(&output));
+        reparsed.deep_equals(&original)
+    });
+
+    let reason = match result {
+        Ok(true) => return Ok(None),
+        Ok(false) => "the file changes when it is written back".to_string(),
+        Err(payload) => get_panic_message(payload.as_ref()),
+    };
+
+    Ok(Some(ValidationFailure { path: path.to_path_buf(), reason }))
+}
+
+fn get_panic_message(payload: &(dyn Any + Send)) -> String {
+    payload.downcast_ref::<String>().cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "unknown parser error".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation_reports_unsupported_footprint() {
+        let library_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data").join("validate");
+        let failures = validate_library(
+            &library_dir.join("JLCPCB_Components.kicad_sym"),
+            &library_dir.join("JLCPCB_Components.pretty"),
+        ).unwrap();
+
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].path.ends_with("Bad.kicad_mod"));
+        assert!(failures[0].reason.contains("unsupported_node"));
+    }
+}